@@ -0,0 +1,92 @@
+use crate::{fetcher::Fetch, Error, QueryClient, QueryClientBuilder, QueryKey};
+use std::rc::Rc;
+use tokio::{runtime::Runtime, task::LocalSet};
+
+thread_local! {
+    // The executor of the blocking calls, reused so the background tasks spawned in a call,
+    // like the refetch intervals, keep running in the next calls.
+    static EXECUTOR: (Runtime, LocalSet) = (
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("failed to build the runtime of the blocking calls"),
+        LocalSet::new(),
+    );
+}
+
+impl QueryClient {
+    /// Executes the future then cache and returns the result, blocking the current thread
+    /// until the fetch completes.
+    ///
+    /// Useful for tools and scripts without an async runtime. The background tasks of the client,
+    /// like the refetch intervals, only make progress during the blocking calls.
+    ///
+    /// The client must be built with `QueryClientBuilder::build_blocking` if it has background
+    /// tasks from the start, like a `TtlCache` or a `MemoryPressure`.
+    ///
+    /// # Panics
+    /// If called from an async context.
+    pub fn fetch_query_blocking<F, T>(&mut self, key: QueryKey, f: F) -> Result<Rc<T>, Error>
+    where
+        F: Fetch<T> + 'static,
+        T: 'static,
+    {
+        EXECUTOR.with(|(runtime, local_set)| local_set.block_on(runtime, self.fetch_query(key, f)))
+    }
+}
+
+impl QueryClientBuilder {
+    /// Builds the `QueryClient` inside the executor of the blocking calls.
+    ///
+    /// `build` spawns the background tasks of the client, like the sweeper of a `TtlCache`,
+    /// which panics outside an async runtime, with this they run during the blocking calls.
+    ///
+    /// # Panics
+    /// If called from an async context.
+    pub fn build_blocking(self) -> QueryClient {
+        EXECUTOR.with(|(runtime, local_set)| local_set.block_on(runtime, async { self.build() }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{QueryClient, QueryKey, TtlCache};
+    use instant::Duration;
+    use std::convert::Infallible;
+
+    #[test]
+    fn fetch_query_blocking_test() {
+        let mut client = QueryClient::builder()
+            .cache_time(Duration::from_secs(1))
+            .build();
+
+        let key = QueryKey::of::<String>("color");
+        let value = client
+            .fetch_query_blocking(key.clone(), || async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Ok::<_, Infallible>(String::from("blue"))
+            })
+            .unwrap();
+
+        assert_eq!(value.as_str(), "blue");
+        assert!(client.has_query_data(&key));
+    }
+
+    #[test]
+    fn build_blocking_test() {
+        // The sweeper of the cache is spawned in the executor of the blocking calls
+        let mut client = QueryClient::builder()
+            .cache_time(Duration::from_secs(1))
+            .cache(TtlCache::new(Duration::from_secs(1)))
+            .build_blocking();
+
+        let key = QueryKey::of::<String>("color");
+        client
+            .fetch_query_blocking(key.clone(), || async {
+                Ok::<_, Infallible>(String::from("red"))
+            })
+            .unwrap();
+
+        assert!(client.has_query_data(&key));
+    }
+}
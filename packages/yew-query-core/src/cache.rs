@@ -1,8 +1,12 @@
-use crate::key::QueryKey;
+use crate::{key::QueryKey, notify, CacheSnapshot, QueryChanged};
 
 use super::query::Query;
+use instant::{Duration, Instant};
+use std::cell::{Cell, RefCell};
 use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
+use std::num::NonZeroUsize;
+use std::rc::Rc;
 
 /// Provides a way to store the query data.
 pub trait QueryCache: Debug {
@@ -23,15 +27,52 @@ pub trait QueryCache: Debug {
 
     /// Removes all the cache entries.
     fn clear(&mut self);
+
+    /// Returns an iterator over all the cache entries.
+    fn iter(&self) -> Box<dyn Iterator<Item = (&QueryKey, &Query)> + '_>;
+
+    /// Returns the number of cache entries.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if the cache has no entries.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the keys of all the cache entries.
+    fn keys(&self) -> Vec<QueryKey> {
+        self.iter().map(|(key, _)| key.clone()).collect()
+    }
+
+    /// Returns a copy of the value and state of all the cache entries.
+    fn snapshot(&self) -> CacheSnapshot {
+        CacheSnapshot {
+            queries: self.iter().map(|(_, query)| query.snapshot()).collect(),
+        }
+    }
+
+    /// Returns how often the `QueryClient` should call `sweep`, if `None` the cache is never swept.
+    fn sweep_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Removes the expired entries of the cache.
+    fn sweep(&mut self) {}
+
+    /// Returns the keys of the entries evicted by the cache on insertion since the last call,
+    /// which the `QueryClient` reports to the cache listeners as removed.
+    fn take_evicted(&mut self) -> Vec<QueryKey> {
+        Vec::new()
+    }
 }
 
 impl QueryCache for HashMap<QueryKey, Query> {
     fn get(&self, key: &QueryKey) -> Option<&Query> {
-        self.get(&key)
+        self.get(key)
     }
 
     fn get_mut(&mut self, key: &QueryKey) -> Option<&mut Query> {
-        self.get_mut(&key)
+        self.get_mut(key)
     }
 
     fn set(&mut self, key: QueryKey, entry: Query) {
@@ -49,15 +90,23 @@ impl QueryCache for HashMap<QueryKey, Query> {
     fn clear(&mut self) {
         self.clear()
     }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&QueryKey, &Query)> + '_> {
+        Box::new(self.iter())
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
 }
 
 impl QueryCache for BTreeMap<QueryKey, Query> {
     fn get(&self, key: &QueryKey) -> Option<&Query> {
-        self.get(&key)
+        self.get(key)
     }
 
     fn get_mut(&mut self, key: &QueryKey) -> Option<&mut Query> {
-        self.get_mut(&key)
+        self.get_mut(key)
     }
 
     fn set(&mut self, key: QueryKey, entry: Query) {
@@ -75,11 +124,20 @@ impl QueryCache for BTreeMap<QueryKey, Query> {
     fn clear(&mut self) {
         self.clear()
     }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&QueryKey, &Query)> + '_> {
+        Box::new(self.iter())
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
 }
 
 impl QueryCache for Vec<(QueryKey, Query)> {
     fn get(&self, key: &QueryKey) -> Option<&Query> {
-        self.iter()
+        self.as_slice()
+            .iter()
             .find_map(|(k, v)| if key == k { Some(v) } else { None })
     }
 
@@ -97,7 +155,7 @@ impl QueryCache for Vec<(QueryKey, Query)> {
     }
 
     fn remove(&mut self, key: &QueryKey) -> Option<Query> {
-        if let Some(idx) = self.iter().position(|(k, _)| k == key) {
+        if let Some(idx) = self.as_slice().iter().position(|(k, _)| k == key) {
             let (_, query) = self.remove(idx);
             Some(query)
         } else {
@@ -112,30 +170,732 @@ impl QueryCache for Vec<(QueryKey, Query)> {
     fn clear(&mut self) {
         self.clear();
     }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&QueryKey, &Query)> + '_> {
+        Box::new(self.as_slice().iter().map(|(k, v)| (k, v)))
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+type OnEvict = Rc<dyn Fn(&QueryKey, &Query)>;
+
+/// A `QueryCache` that holds a limited number of entries,
+/// evicting the least recently used entry when full.
+///
+/// The entries that are being observed or fetched are never evicted,
+/// so the cache can hold more entries than its capacity while they are in use.
+pub struct LruCache {
+    capacity: NonZeroUsize,
+    entries: HashMap<QueryKey, (Query, Cell<u64>)>,
+    order: RefCell<BTreeMap<u64, QueryKey>>,
+    tick: Cell<u64>,
+    on_evict: Option<OnEvict>,
+    evicted: Vec<QueryKey>,
+}
+
+impl LruCache {
+    /// Constructs a new `LruCache` with the given max number of entries.
+    ///
+    /// # Panics
+    /// If the capacity is zero.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).expect("capacity cannot be zero");
+
+        LruCache {
+            capacity,
+            entries: HashMap::new(),
+            order: Default::default(),
+            tick: Cell::new(0),
+            on_evict: None,
+            evicted: Vec::new(),
+        }
+    }
+
+    /// Sets a callback called with the entries evicted from the cache.
+    ///
+    /// The callback is called while the cache is borrowed, so it must not use the `QueryClient`.
+    pub fn on_evict<F>(mut self, on_evict: F) -> Self
+    where
+        F: Fn(&QueryKey, &Query) + 'static,
+    {
+        self.on_evict = Some(Rc::new(on_evict));
+        self
+    }
+
+    /// Returns the max number of entries of this cache.
+    pub fn capacity(&self) -> usize {
+        self.capacity.get()
+    }
+
+    /// Returns the number of entries in the cache.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn next_tick(&self) -> u64 {
+        let tick = self.tick.get();
+        self.tick.set(tick + 1);
+        tick
+    }
+
+    // Marks the entry as the most recently used
+    fn touch(&self, key: &QueryKey, last_used: &Cell<u64>) {
+        let tick = self.next_tick();
+        let mut order = self.order.borrow_mut();
+        order.remove(&last_used.get());
+        order.insert(tick, key.clone());
+        last_used.set(tick);
+    }
+
+    fn evict(&mut self) {
+        while self.entries.len() > self.capacity.get() {
+            if !self.evict_lru() {
+                break;
+            }
+        }
+    }
+
+    // Returns the least recently used entry that can be evicted,
+    // the most recently used entry and the entries in use are never evicted.
+    fn next_evictable(&self) -> Option<QueryKey> {
+        let order = self.order.borrow();
+        let (_, last) = order.last_key_value()?;
+
+        order
+            .values()
+            .filter(|key| *key != last)
+            .find(|key| {
+                self.entries
+                    .get(*key)
+                    .is_some_and(|(query, _)| !query.has_observers() && !query.is_fetching())
+            })
+            .cloned()
+    }
+
+    // Removes the least recently used entry, returns `false` if there is no entry to evict
+    fn evict_lru(&mut self) -> bool {
+        let Some(key) = self.next_evictable() else {
+            return false;
+        };
+
+        if let Some((query, last_used)) = self.entries.remove(&key) {
+            self.order.get_mut().remove(&last_used.get());
+            if let Some(on_evict) = &self.on_evict {
+                on_evict(&key, &query);
+            }
+
+            self.evicted.push(key);
+        }
+
+        true
+    }
+}
+
+impl QueryCache for LruCache {
+    fn get(&self, key: &QueryKey) -> Option<&Query> {
+        let (query, last_used) = self.entries.get(key)?;
+        self.touch(key, last_used);
+        Some(query)
+    }
+
+    fn get_mut(&mut self, key: &QueryKey) -> Option<&mut Query> {
+        let tick = self.next_tick();
+        let (query, last_used) = self.entries.get_mut(key)?;
+        let order = self.order.get_mut();
+        order.remove(&last_used.get());
+        order.insert(tick, key.clone());
+        last_used.set(tick);
+        Some(query)
+    }
+
+    fn set(&mut self, key: QueryKey, entry: Query) {
+        let tick = self.next_tick();
+        let order = self.order.get_mut();
+
+        if let Some((_, last_used)) = self.entries.get(&key) {
+            order.remove(&last_used.get());
+        }
+
+        order.insert(tick, key.clone());
+        self.entries.insert(key, (entry, Cell::new(tick)));
+        self.evict();
+    }
+
+    fn remove(&mut self, key: &QueryKey) -> Option<Query> {
+        let (query, last_used) = self.entries.remove(key)?;
+        self.order.get_mut().remove(&last_used.get());
+        Some(query)
+    }
+
+    fn has(&self, key: &QueryKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.get_mut().clear();
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&QueryKey, &Query)> + '_> {
+        Box::new(self.entries.iter().map(|(key, (query, _))| (key, query)))
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn take_evicted(&mut self) -> Vec<QueryKey> {
+        std::mem::take(&mut self.evicted)
+    }
+}
+
+impl Debug for LruCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LruCache")
+            .field("capacity", &self.capacity)
+            .field("len", &self.entries.len())
+            .finish()
+    }
+}
+
+type SizeEstimator = Rc<dyn Fn(&QueryKey, &Query) -> usize>;
+
+/// A `QueryCache` that keeps the estimated size of its entries under a budget in bytes,
+/// evicting the least recently used entries when over budget.
+///
+/// The size of the values can change after they are inserted, so the budget is enforced
+/// on insertion and each time the cache is swept.
+///
+/// The entries that are being observed or fetched are never evicted, see `LruCache`.
+pub struct SizedCache {
+    lru: LruCache,
+    max_size: usize,
+    estimator: SizeEstimator,
+    sweep_interval: Option<Duration>,
+}
+
+impl SizedCache {
+    /// Constructs a new `SizedCache` with the given budget in bytes.
+    ///
+    /// By default the size of an entry is estimated using `size_of_val` over its value,
+    /// which don't include the heap memory owned by the value, use `estimator`
+    /// for values like `String` or `Vec<T>`.
+    pub fn new(max_size: usize) -> Self {
+        SizedCache {
+            lru: LruCache::new(usize::MAX),
+            max_size,
+            estimator: Rc::new(default_size_estimator),
+            sweep_interval: None,
+        }
+    }
+
+    /// Sets the function used to estimate the size in bytes of an entry.
+    pub fn estimator<F>(mut self, estimator: F) -> Self
+    where
+        F: Fn(&QueryKey, &Query) -> usize + 'static,
+    {
+        self.estimator = Rc::new(estimator);
+        self
+    }
+
+    /// Sets a callback called with the entries evicted from the cache, see `LruCache::on_evict`.
+    pub fn on_evict<F>(mut self, on_evict: F) -> Self
+    where
+        F: Fn(&QueryKey, &Query) + 'static,
+    {
+        self.lru = self.lru.on_evict(on_evict);
+        self
+    }
+
+    /// Sets how often the budget is enforced, in addition of each insertion.
+    pub fn sweep_interval(mut self, sweep_interval: Duration) -> Self {
+        self.sweep_interval = Some(sweep_interval);
+        self
+    }
+
+    /// Returns the budget in bytes of this cache.
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Returns the estimated size in bytes of all the entries.
+    pub fn size(&self) -> usize {
+        self.lru
+            .entries
+            .iter()
+            .map(|(key, (query, _))| (self.estimator)(key, query))
+            .sum()
+    }
+
+    /// Returns the number of entries in the cache.
+    pub fn len(&self) -> usize {
+        self.lru.len()
+    }
+
+    /// Returns `true` if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.lru.is_empty()
+    }
+
+    // The most recently used entry and the entries in use are never evicted
+    fn evict(&mut self) {
+        let mut size = self.size();
+
+        while size > self.max_size {
+            let Some(key) = self.lru.next_evictable() else {
+                break;
+            };
+
+            if let Some((query, _)) = self.lru.entries.get(&key) {
+                size = size.saturating_sub((self.estimator)(&key, query));
+            }
+
+            self.lru.evict_lru();
+        }
+    }
+}
+
+fn default_size_estimator(key: &QueryKey, query: &Query) -> usize {
+    let value_size = query
+        .last_value()
+        .map(|value| std::mem::size_of_val(&*value))
+        .unwrap_or_default();
+
+    std::mem::size_of::<Query>() + key.key().len() + value_size
+}
+
+impl QueryCache for SizedCache {
+    fn get(&self, key: &QueryKey) -> Option<&Query> {
+        self.lru.get(key)
+    }
+
+    fn get_mut(&mut self, key: &QueryKey) -> Option<&mut Query> {
+        self.lru.get_mut(key)
+    }
+
+    fn set(&mut self, key: QueryKey, entry: Query) {
+        self.lru.set(key, entry);
+        self.evict();
+    }
+
+    fn remove(&mut self, key: &QueryKey) -> Option<Query> {
+        self.lru.remove(key)
+    }
+
+    fn has(&self, key: &QueryKey) -> bool {
+        self.lru.has(key)
+    }
+
+    fn clear(&mut self) {
+        self.lru.clear();
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&QueryKey, &Query)> + '_> {
+        self.lru.iter()
+    }
+
+    fn len(&self) -> usize {
+        self.lru.len()
+    }
+
+    fn sweep_interval(&self) -> Option<Duration> {
+        self.sweep_interval
+    }
+
+    fn sweep(&mut self) {
+        self.evict();
+    }
+
+    fn take_evicted(&mut self) -> Vec<QueryKey> {
+        self.lru.take_evicted()
+    }
+}
+
+impl Debug for SizedCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SizedCache")
+            .field("max_size", &self.max_size)
+            .field("len", &self.lru.len())
+            .finish()
+    }
+}
+
+/// A `QueryCache` that removes the entries which value was not updated for a given time.
+///
+/// The expired entries are removed periodically by the `QueryClient`,
+/// entries that are being observed or fetched are never removed.
+#[derive(Debug)]
+pub struct TtlCache {
+    ttl: Duration,
+    sweep_interval: Duration,
+    entries: HashMap<QueryKey, (Query, Instant)>,
+}
+
+impl TtlCache {
+    /// Constructs a new `TtlCache` which entries live for the given time,
+    /// the cache is swept at the same interval.
+    pub fn new(ttl: Duration) -> Self {
+        TtlCache {
+            ttl,
+            sweep_interval: ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Sets how often the expired entries are removed.
+    pub fn sweep_interval(mut self, sweep_interval: Duration) -> Self {
+        self.sweep_interval = sweep_interval;
+        self
+    }
+
+    /// Returns the time the entries live after their last update.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    fn is_expired(&self, query: &Query, inserted_at: Instant, now: Instant) -> bool {
+        if query.has_observers() || query.is_fetching() {
+            return false;
+        }
+
+        let updated_at = [query.data_updated_at(), query.error_updated_at()]
+            .into_iter()
+            .flatten()
+            .fold(inserted_at, Instant::max);
+
+        now.duration_since(updated_at) >= self.ttl
+    }
+}
+
+impl QueryCache for TtlCache {
+    fn get(&self, key: &QueryKey) -> Option<&Query> {
+        self.entries.get(key).map(|(query, _)| query)
+    }
+
+    fn get_mut(&mut self, key: &QueryKey) -> Option<&mut Query> {
+        self.entries.get_mut(key).map(|(query, _)| query)
+    }
+
+    fn set(&mut self, key: QueryKey, entry: Query) {
+        self.entries.insert(key, (entry, Instant::now()));
+    }
+
+    fn remove(&mut self, key: &QueryKey) -> Option<Query> {
+        self.entries.remove(key).map(|(query, _)| query)
+    }
+
+    fn has(&self, key: &QueryKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&QueryKey, &Query)> + '_> {
+        Box::new(self.entries.iter().map(|(key, (query, _))| (key, query)))
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn sweep_interval(&self) -> Option<Duration> {
+        Some(self.sweep_interval)
+    }
+
+    fn sweep(&mut self) {
+        let now = Instant::now();
+        let expired = self
+            .entries
+            .iter()
+            .filter(|(_, (query, inserted_at))| self.is_expired(query, *inserted_at, now))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+
+        for key in expired {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+/// A change of the entries of the cache of a `QueryClient`.
+#[derive(Debug, Clone)]
+pub enum QueryCacheEvent {
+    /// A query was added to the cache.
+    Added(QueryKey),
+
+    /// The value or state of a query changed.
+    Updated(QueryKey, QueryChanged),
+
+    /// A query was removed from the cache.
+    Removed(QueryKey),
+
+    /// All the queries were removed from the cache.
+    Cleared,
+}
+
+/// Receives the changes of the entries of the cache of a `QueryClient`.
+pub trait QueryCacheListener {
+    /// Handles the given event.
+    fn on_event(&self, event: &QueryCacheEvent);
+}
+
+impl<F> QueryCacheListener for F
+where
+    F: Fn(&QueryCacheEvent),
+{
+    fn on_event(&self, event: &QueryCacheEvent) {
+        (self)(event)
+    }
+}
+
+#[derive(Default)]
+struct CacheListenersInner {
+    next_id: usize,
+    listeners: Vec<(usize, Rc<dyn QueryCacheListener>)>,
+}
+
+// The listeners of a cache shared between the clones of a `QueryClient`.
+#[derive(Clone, Default)]
+pub(crate) struct CacheListeners(Rc<RefCell<CacheListenersInner>>);
+
+impl CacheListeners {
+    pub fn add(&self, listener: Rc<dyn QueryCacheListener>) -> usize {
+        let mut inner = self.0.borrow_mut();
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner.listeners.push((id, listener));
+        id
+    }
+
+    pub fn remove(&self, id: usize) -> bool {
+        let mut inner = self.0.borrow_mut();
+        let len = inner.listeners.len();
+        inner.listeners.retain(|(x, _)| *x != id);
+        inner.listeners.len() != len
+    }
+
+    pub fn notify(&self, event: QueryCacheEvent) {
+        // The events in a batch are delivered when the batch ends
+        if notify::is_batching() {
+            let this = self.clone();
+            notify::defer(move || this.notify(event));
+            return;
+        }
+
+        // We release the borrow before notifying, listeners may add or remove listeners
+        let listeners = self
+            .0
+            .borrow()
+            .listeners
+            .iter()
+            .map(|(_, x)| x.clone())
+            .collect::<Vec<_>>();
+
+        for listener in listeners {
+            listener.on_event(&event);
+        }
+    }
+
+    // Returns a handler that forwards the changes of the query with the given key.
+    pub fn query_handler(&self, key: QueryKey) -> Rc<dyn Fn(QueryChanged)> {
+        let this = self.clone();
+        Rc::new(move |event| this.notify(QueryCacheEvent::Updated(key.clone(), event)))
+    }
+}
+
+impl Debug for CacheListeners {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CacheListeners")
+            .field("len", &self.0.borrow().listeners.len())
+            .finish()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::{
+        cell::RefCell,
         collections::{BTreeMap, HashMap},
         convert::Infallible,
+        rc::Rc,
     };
 
-    use crate::{Query, QueryCache, QueryKey};
+    use crate::{LruCache, Query, QueryCache, QueryKey, QueryOptions, SizedCache, TtlCache};
+    use instant::Duration;
 
     #[test]
     fn hash_map_cache_test() {
-        test_cache_impl(|| HashMap::new());
+        test_cache_impl(HashMap::new);
     }
 
     #[test]
     fn tree_map_cache_test() {
-        test_cache_impl(|| BTreeMap::new());
+        test_cache_impl(BTreeMap::new);
     }
 
     #[test]
     fn vec_cache_test() {
-        test_cache_impl(|| Vec::new());
+        test_cache_impl(Vec::new);
+    }
+
+    #[test]
+    fn lru_cache_test() {
+        test_cache_impl(|| LruCache::new(10));
+    }
+
+    #[test]
+    fn sized_cache_test() {
+        test_cache_impl(|| SizedCache::new(usize::MAX));
+    }
+
+    #[test]
+    fn sized_cache_eviction_test() {
+        let mut cache = SizedCache::new(100).estimator(|key, _| key.key().len() * 10);
+
+        let query = |key: &str| {
+            Query::new(
+                QueryKey::of::<i32>(key),
+                || async { Ok::<_, Infallible>(1_i32) },
+                QueryOptions::new(),
+                None,
+            )
+        };
+
+        cache.set(QueryKey::of::<i32>("aaaa"), query("aaaa"));
+        cache.set(QueryKey::of::<i32>("bbbb"), query("bbbb"));
+        assert_eq!(cache.size(), 80);
+
+        // `aaaa` becomes the most recently used
+        assert!(cache.get(&QueryKey::of::<i32>("aaaa")).is_some());
+
+        cache.set(QueryKey::of::<i32>("ccc"), query("ccc"));
+        assert_eq!(cache.size(), 70);
+        assert!(!cache.has(&QueryKey::of::<i32>("bbbb")));
+        assert!(cache.has(&QueryKey::of::<i32>("aaaa")));
+
+        // An entry over budget is kept while is the most recently used
+        cache.set(QueryKey::of::<i32>("d".repeat(20)), query(&"d".repeat(20)));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.size(), 200);
+        assert_eq!(cache.take_evicted().len(), 3);
+
+        // The observed entries are kept over budget
+        let observed = query("eeee");
+        let _subscription = observed.subscribe(|_| {});
+        cache.set(QueryKey::of::<i32>("eeee"), observed);
+        cache.set(QueryKey::of::<i32>("f".repeat(10)), query(&"f".repeat(10)));
+        assert!(cache.has(&QueryKey::of::<i32>("eeee")));
+        assert!(!cache.has(&QueryKey::of::<i32>("d".repeat(20))));
+        assert_eq!(cache.size(), 140);
+    }
+
+    #[test]
+    fn ttl_cache_test() {
+        test_cache_impl(|| TtlCache::new(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn ttl_cache_sweep_test() {
+        let mut cache = TtlCache::new(Duration::ZERO);
+        let query = |key: &str| {
+            Query::new(
+                QueryKey::of::<i32>(key),
+                || async { Ok::<_, Infallible>(1_i32) },
+                QueryOptions::new(),
+                None,
+            )
+        };
+
+        let observed = query("observed");
+        let _subscription = observed.subscribe(|_| {});
+        cache.set(QueryKey::of::<i32>("observed"), observed);
+        cache.set(QueryKey::of::<i32>("unused"), query("unused"));
+
+        // The observed entries don't expire
+        cache.sweep();
+        assert!(cache.has(&QueryKey::of::<i32>("observed")));
+        assert!(!cache.has(&QueryKey::of::<i32>("unused")));
+    }
+
+    #[test]
+    fn lru_cache_eviction_test() {
+        let evicted = Rc::new(RefCell::new(Vec::new()));
+        let mut cache = LruCache::new(2).on_evict({
+            let evicted = evicted.clone();
+            move |key, _| evicted.borrow_mut().push(key.key().to_string())
+        });
+
+        let query = |key: &str| {
+            Query::new(
+                QueryKey::of::<i32>(key),
+                || async { Ok::<_, Infallible>(1_i32) },
+                QueryOptions::new(),
+                None,
+            )
+        };
+
+        cache.set(QueryKey::of::<i32>("a"), query("a"));
+        cache.set(QueryKey::of::<i32>("b"), query("b"));
+
+        // `a` becomes the most recently used
+        assert!(cache.get(&QueryKey::of::<i32>("a")).is_some());
+
+        cache.set(QueryKey::of::<i32>("c"), query("c"));
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.has(&QueryKey::of::<i32>("b")));
+        assert_eq!(*evicted.borrow(), vec!["b".to_owned()]);
+
+        cache.set(QueryKey::of::<i32>("d"), query("d"));
+        assert!(!cache.has(&QueryKey::of::<i32>("a")));
+        assert!(cache.has(&QueryKey::of::<i32>("c")));
+        assert!(cache.has(&QueryKey::of::<i32>("d")));
+        assert_eq!(*evicted.borrow(), vec!["b".to_owned(), "a".to_owned()]);
+
+        // Removing an entry is not an eviction
+        cache.remove(&QueryKey::of::<i32>("c"));
+        assert_eq!(evicted.borrow().len(), 2);
+    }
+
+    #[test]
+    fn lru_cache_keeps_used_entries_test() {
+        let mut cache = LruCache::new(1);
+        let query = |key: &str| {
+            Query::new(
+                QueryKey::of::<i32>(key),
+                || async { Ok::<_, Infallible>(1_i32) },
+                QueryOptions::new(),
+                None,
+            )
+        };
+
+        let a = query("a");
+        let subscription = a.subscribe(|_| {});
+        cache.set(QueryKey::of::<i32>("a"), a);
+        cache.set(QueryKey::of::<i32>("b"), query("b"));
+
+        // The observed entries are not evicted
+        assert_eq!(cache.len(), 2);
+        assert!(cache.take_evicted().is_empty());
+
+        drop(subscription);
+        cache.set(QueryKey::of::<i32>("c"), query("c"));
+        assert_eq!(cache.len(), 1);
+        assert!(cache.has(&QueryKey::of::<i32>("c")));
+        assert_eq!(
+            cache.take_evicted(),
+            vec![QueryKey::of::<i32>("a"), QueryKey::of::<i32>("b")]
+        );
+        assert!(cache.take_evicted().is_empty());
     }
 
     fn test_cache_impl<F, Q>(factory: F)
@@ -147,10 +907,9 @@ mod tests {
         cache.set(
             QueryKey::of::<String>("color"),
             Query::new(
+                QueryKey::of::<String>("color"),
                 || async { Ok::<_, Infallible>("red".to_owned()) },
-                None,
-                None,
-                None,
+                QueryOptions::new(),
                 None,
             ),
         );
@@ -158,10 +917,9 @@ mod tests {
         cache.set(
             QueryKey::of::<String>("fruit"),
             Query::new(
+                QueryKey::of::<String>("fruit"),
                 || async { Ok::<_, Infallible>("apple".to_owned()) },
-                None,
-                None,
-                None,
+                QueryOptions::new(),
                 None,
             ),
         );
@@ -169,10 +927,9 @@ mod tests {
         cache.set(
             QueryKey::of::<i32>("number"),
             Query::new(
+                QueryKey::of::<i32>("number"),
                 || async { Ok::<_, Infallible>(12_i32) },
-                None,
-                None,
-                None,
+                QueryOptions::new(),
                 None,
             ),
         );
@@ -189,10 +946,9 @@ mod tests {
         cache.set(
             QueryKey::of::<Vec<u32>>("number"),
             Query::new(
+                QueryKey::of::<Vec<u32>>("number"),
                 || async { Ok::<_, Infallible>(vec![1, 2, 3]) },
-                None,
-                None,
-                None,
+                QueryOptions::new(),
                 None,
             ),
         );
@@ -202,10 +958,18 @@ mod tests {
         assert!(cache.get_mut(&QueryKey::of::<i32>("number")).is_some());
         assert!(cache.get(&QueryKey::of::<Vec<u32>>("number")).is_some());
 
+        assert_eq!(cache.len(), 4);
+        assert_eq!(cache.keys().len(), 4);
+        assert!(cache.keys().contains(&QueryKey::of::<String>("fruit")));
+        assert!(cache
+            .iter()
+            .any(|(key, query)| key == query.key() && key == &QueryKey::of::<i32>("number")));
+
         cache.remove(&QueryKey::of::<i32>("number"));
         assert!(cache.get_mut(&QueryKey::of::<i32>("number")).is_none());
 
         cache.clear();
+        assert!(cache.is_empty());
 
         assert!(cache.get(&QueryKey::of::<String>("color")).is_none());
         assert!(cache.get(&QueryKey::of::<String>("fruit")).is_none());
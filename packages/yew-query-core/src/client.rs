@@ -1,14 +1,34 @@
 
 
-use super::{cache::QueryCache, error::QueryError, query::Query, retry::Retry, Error};
-use crate::{fetcher::Fetch, key::QueryKey, state::QueryState, QueryChanged, QueryOptions, futures::query::QueryFuture};
+use super::{
+    cache::{CacheListeners, QueryCache, QueryCacheEvent, QueryCacheListener},
+    error::{ErrorContext, ErrorReporter, QueryError},
+    middleware::{apply_middlewares, apply_validator, Middleware, MiddlewareHandle},
+    focus::{FocusManager, FocusManagerHandle},
+    online::{OnlineManager, OnlineManagerHandle},
+    options::ErrorReporterHandle,
+    query::Query,
+    retry::{OnRetry, RetryProgress},
+    spawner::{Spawner, SpawnerHandle},
+    timer::{TimerBackend, TimerHandle},
+    limiter::{FetchLimiter, OnQueued, RateLimiter},
+    dedupe::RequestDeduper,
+    time::{idle::request_idle, interval::Interval},
+    stats::{CacheStats, FetchHistory, QueryStats, SizeEstimatorHandle},
+    transaction::QueryTransaction,
+    watch::QueryWatcher,
+    Error,
+};
+use crate::{cancellation::CancellationToken, snapshot::CacheSnapshot, memory::MemoryPressure, notify, fetcher::{DuplicateFetcher, Fetch, FetchContext, FetchResponse, OnPartial, OnProgress, Validators}, key::QueryKey, state::{FetchStatus, QueryState}, FetchCause, FetchPriority, InvalidationMessage, QueryChanged, QueryFilter, QueryOptions, futures::query::QueryFuture};
+use futures::{future::Either, Future, FutureExt};
+use instant::Instant;
 use std::{
-    any::TypeId,
-    cell::{Ref, RefCell},
+    any::{Any, TypeId},
+    cell::{Cell, Ref, RefCell},
     collections::HashMap,
     fmt::Debug,
-    future::Future,
     rc::Rc,
+    task::Waker,
     time::Duration,
 };
 
@@ -17,6 +37,39 @@ use std::{
 pub struct QueryClient {
     cache: Rc<RefCell<dyn QueryCache>>,
     options: QueryOptions,
+    watchers: Rc<RefCell<HashMap<QueryKey, Vec<Waker>>>>,
+    reducers: Rc<RefCell<HashMap<QueryKey, ReducerHandle>>>,
+
+    // The fetches in progress of `join_or_fetch` for the queries that are not cached
+    uncached_fetches: RequestDeduper,
+    cache_listeners: CacheListeners,
+    paused: Rc<Cell<bool>>,
+    online_manager: Option<OnlineManagerHandle>,
+    focus_manager: Option<FocusManagerHandle>,
+    spawner: SpawnerHandle,
+    key_prefix: Option<Rc<str>>,
+    #[cfg_attr(not(debug_assertions), allow(dead_code))]
+    duplicate_fetcher: DuplicateFetcher,
+    size_estimators: Rc<[SizeEstimatorHandle]>,
+
+    // Sweeps the cache until the last client is dropped
+    #[allow(dead_code)]
+    sweeper: Option<Rc<Interval>>,
+
+    // Checks the memory pressure until the last client is dropped
+    #[allow(dead_code)]
+    memory_monitor: Option<Rc<Interval>>,
+}
+
+type Reducer = dyn Fn(&mut Query, Box<dyn std::any::Any>) -> Result<(), QueryError>;
+
+#[derive(Clone)]
+struct ReducerHandle(Rc<Reducer>);
+
+impl Debug for ReducerHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Reducer")
+    }
 }
 
 impl QueryClient {
@@ -25,8 +78,15 @@ impl QueryClient {
         QueryClientBuilder::new()
     }
 
+    /// Returns the cache of this client, the cache can be shared with other clients
+    /// using `QueryClientBuilder::shared_cache`.
+    pub fn cache(&self) -> Rc<RefCell<dyn QueryCache>> {
+        self.cache.clone()
+    }
+
     /// Returns `true` if the value for the given key not expired.
     pub fn is_stale(&self, key: &QueryKey) -> bool {
+        let key = self.scoped_key(key);
         let cache = self.cache.borrow();
         if let Some(query) = cache.get(&key) {
             query.is_stale()
@@ -35,44 +95,121 @@ impl QueryClient {
         }
     }
 
+    /// Returns the time when the value of the given key becomes stale, if the value expires.
+    pub fn stale_at(&self, key: &QueryKey) -> Option<Instant> {
+        let key = self.scoped_key(key);
+        self.cache.borrow().get(&key)?.stale_at()
+    }
+
     /// Returns `true` if is fetching the given key.
     pub fn is_fetching(&self, key: &QueryKey) -> bool {
-        match self.cache.borrow().get(key) {
-            Some(query) => !query.is_fetching(),
+        let key = self.scoped_key(key);
+        match self.cache.borrow().get(&key) {
+            Some(query) => query.is_fetching(),
             None => false,
         }
     }
 
+    /// Returns `true` if the fetch of the given key is waiting in the queue of the client to start,
+    /// see `QueryClientBuilder::max_concurrent_fetches`.
+    pub fn is_queued(&self, key: &QueryKey) -> bool {
+        let key = self.scoped_key(key);
+        self.cache.borrow().get(&key).is_some_and(|q| q.is_queued())
+    }
+
+    /// Returns whether the query with the given key is fetching, see `FetchStatus`.
+    ///
+    /// Is `Paused` while the client is paused or the `OnlineManager` of the client is offline.
+    pub fn fetch_status(&self, key: &QueryKey) -> FetchStatus {
+        let key = self.scoped_key(key);
+        let is_offline = self
+            .online_manager
+            .as_ref()
+            .is_some_and(|x| !x.0.is_online());
+
+        self.cache
+            .borrow()
+            .get(&key)
+            .map(|q| q.fetch_status().or_paused(is_offline))
+            .unwrap_or_default()
+    }
+
+    /// Returns the last fetches of the query with the given key, `None` if is not in the cache.
+    ///
+    /// Useful to check how long the fetches take and how often they fail.
+    pub fn fetch_history(&self, key: &QueryKey) -> Option<FetchHistory> {
+        let key = self.scoped_key(key);
+        self.cache.borrow().get(&key).map(|q| q.fetch_history())
+    }
+
     /// Executes the future then cache and returns the result.
-    pub async fn fetch_query<F, Fut, T, E>(&mut self, key: QueryKey, f: F) -> Result<Rc<T>, Error>
+    pub async fn fetch_query<F, T>(&mut self, key: QueryKey, f: F) -> Result<Rc<T>, Error>
     where
-        F: Fn() -> Fut + 'static,
-        Fut: Future<Output = Result<T, E>> + 'static,
+        F: Fetch<T> + 'static,
         T: 'static,
-        E: Into<Error> + 'static,
     {
         self.fetch_query_with_options(key, f, None).await
     }
 
     /// Executes the future with the given `QueryOptions` then cache and returns the result.
-    pub async fn fetch_query_with_options<F, Fut, T, E>(
+    pub async fn fetch_query_with_options<F, T>(
         &mut self,
         key: QueryKey,
         f: F,
         options: Option<&QueryOptions>,
     ) -> Result<Rc<T>, Error>
     where
-        F: Fn() -> Fut + 'static,
-        Fut: Future<Output = Result<T, E>> + 'static,
+        F: Fetch<T> + 'static,
         T: 'static,
-        E: Into<Error> + 'static,
     {
         self.fetch_query_with_options_and_observe(key, f, options, None)
             .await
     }
 
+    /// Joins the fetch in progress of the query with the given key, or starts a new one.
+    ///
+    /// Unlike `fetch_query` a fresh value in cache is not reused, but all the callers while
+    /// the query is fetching, including the hooks, share a single execution of the fetcher.
+    /// The given fetcher is only used if the query does not exist yet.
+    ///
+    /// Without `cache_time` the value is not cached, but the callers of `join_or_fetch`
+    /// still share the fetch in progress for the key.
+    pub async fn join_or_fetch<F, T>(&mut self, key: QueryKey, f: F) -> Result<Rc<T>, Error>
+    where
+        F: Fetch<T> + 'static,
+        T: 'static,
+    {
+        let options = self.resolve_options(None);
+        if options.cache_time.is_none() {
+            let key = self.scoped_key(&key);
+            let request = key.key().clone();
+            let start = move |signal| {
+                let response = FetchResponse::new(None, None);
+                fetch_with_retry(key, f, options, None, signal, None, response)
+                    .map(|ret| ret.map(|value| Rc::new(value) as Rc<dyn Any>))
+                    .boxed_local()
+            };
+
+            let value = self
+                .uncached_fetches
+                .join_or_start(request, TypeId::of::<T>(), &CancellationToken::new(), start)
+                .await?;
+
+            return value
+                .downcast::<T>()
+                .map_err(|_| QueryError::type_mismatch::<T>().into());
+        }
+
+        let mut query = self.get_or_create_query(key, f, options, None);
+        if query.is_fetching() {
+            return query.future::<T>().await;
+        }
+
+        query.fetch::<T>().await
+    }
+
     /// Executes the future with the given `QueryOptions` then cache and returns the result while observing the state changes of the query.
-    pub async fn fetch_query_with_options_and_observe<F, Fut, T, E>(
+    pub async fn fetch_query_with_options_and_observe<F, T>(
         &mut self,
         key: QueryKey,
         f: F,
@@ -80,17 +217,18 @@ impl QueryClient {
         on_change: Option<Rc<dyn Fn(QueryChanged)>>,
     ) -> Result<Rc<T>, Error>
     where
-        F: Fn() -> Fut + 'static,
-        Fut: Future<Output = Result<T, E>> + 'static,
+        F: Fetch<T> + 'static,
         T: 'static,
-        E: Into<Error> + 'static,
     {
+        let key = self.scoped_key(&key);
+
         // If is fetching for the query still fresh in cache
         {
-            let cache = self.cache.borrow();
-            if let Some(query) = cache.get(&key).cloned() {
-                // This prevent borrow errors
-                drop(cache);
+            // We clone the query to prevent borrow errors
+            let query = self.cache.borrow().get(&key).cloned();
+            if let Some(query) = query {
+                #[cfg(debug_assertions)]
+                self.check_fetcher(&query, &f);
 
                 if !query.is_stale() && query.last_value().is_some() {
                     let last_value = query.last_value().clone().unwrap();
@@ -106,40 +244,86 @@ impl QueryClient {
             }
         }
 
-        // Options
-        let cache_time = self
-            .options
-            .cache_time
-            .or(options.as_ref().and_then(|x| x.cache_time));
-        let refetch_time = self
-            .options
-            .refetch_time
-            .or(options.as_ref().and_then(|x| x.refetch_time));
-        let retrier = self
-            .options
-            .retry
-            .clone()
-            .or_else(|| options.as_ref().and_then(|x| x.retry.clone()));
-
         // Only store the result in the cache if had stale time
-        let can_cache = cache_time.is_some();
+        let options = self.resolve_options(options);
+        let can_cache = options.cache_time.is_some();
         if !can_cache {
-            let f = fetch_with_retry(f, retrier);
+            let on_retry = on_change.clone().map(|on_change| {
+                Rc::new(move |retry| {
+                    on_change(QueryChanged {
+                        value: None,
+                        state: QueryState::Loading,
+                        is_fetching: true,
+                        retry: Some(retry),
+                        ..Default::default()
+                    })
+                }) as OnRetry
+            });
+
+            let on_queued = on_change.clone().map(|on_change| {
+                Rc::new(move |is_queued| {
+                    on_change(QueryChanged {
+                        value: None,
+                        state: QueryState::Loading,
+                        is_fetching: true,
+                        is_queued,
+                        ..Default::default()
+                    })
+                }) as OnQueued
+            });
+
+            let on_progress = on_change.clone().map(|on_change| {
+                Rc::new(move |progress| {
+                    on_change(QueryChanged {
+                        value: None,
+                        state: QueryState::Loading,
+                        is_fetching: true,
+                        progress: Some(progress),
+                        ..Default::default()
+                    })
+                }) as OnProgress
+            });
+
+            let on_partial = on_change.clone().map(|on_change| {
+                Rc::new(move |value: Rc<dyn std::any::Any>| {
+                    if !value.is::<T>() {
+                        return;
+                    }
+
+                    on_change(QueryChanged {
+                        value: Some(value),
+                        state: QueryState::Ready,
+                        is_fetching: true,
+                        ..Default::default()
+                    })
+                }) as OnPartial
+            });
+
+            let f = apply_validator(f, options.validator.clone());
+            let f = apply_middlewares(f, &options.middlewares);
+            let f = fetch_with_retry(
+                key,
+                f,
+                options,
+                on_retry,
+                CancellationToken::new(),
+                None,
+                FetchResponse::new(on_progress, on_partial).with_on_queued(on_queued),
+            );
             let ret = QueryFuture::new(f, on_change).await?;
             return Ok(ret);
         }
 
-        let mut query = {
-            let mut cache = self.cache.borrow_mut();
-            match cache.get(&key).cloned() {
-                Some(x) => x,
-                None => {
-                    let query = Query::new(f, retrier, cache_time, refetch_time, on_change);
-                    cache.set(key.clone(), query.clone());
-                    query
-                }
+        let mut query = self.get_or_create_scoped_query(key, f, options, on_change);
+
+        // A query created with fresh initial data is not fetched
+        if !query.is_stale() {
+            if let Some(value) = query.last_value() {
+                return value
+                    .downcast::<T>()
+                    .map_err(|_| QueryError::type_mismatch::<T>().into());
             }
-        };
+        }
 
         // Await the value what will update the copy in the cache
         let value = query.fetch::<T>().await?;
@@ -147,37 +331,423 @@ impl QueryClient {
         Ok(value)
     }
 
+    // Returns the query in the cache or inserts a new one that is not fetched yet.
+    pub(crate) fn get_or_create_query<F, T>(
+        &self,
+        key: QueryKey,
+        f: F,
+        options: QueryOptions,
+        on_change: Option<Rc<dyn Fn(QueryChanged)>>,
+    ) -> Query
+    where
+        F: Fetch<T> + 'static,
+        T: 'static,
+    {
+        self.get_or_create_scoped_query(self.scoped_key(&key), f, options, on_change)
+    }
+
+    // Returns the query in the cache or inserts a new one, the key is already scoped.
+    fn get_or_create_scoped_query<F, T>(
+        &self,
+        key: QueryKey,
+        f: F,
+        options: QueryOptions,
+        on_change: Option<Rc<dyn Fn(QueryChanged)>>,
+    ) -> Query
+    where
+        F: Fetch<T> + 'static,
+        T: 'static,
+    {
+        let (query, evicted) = {
+            let mut cache = self.cache.borrow_mut();
+            if let Some(query) = cache.get(&key) {
+                #[cfg(debug_assertions)]
+                self.check_fetcher(query, &f);
+
+                return query.clone();
+            }
+
+            let query = Query::new(key.clone(), f, options, on_change);
+            query.set_cache_handler(self.cache_listeners.query_handler(key.clone()));
+            query.set_paused_handle(self.paused.clone());
+            query.set_spawner(self.spawner.clone());
+            cache.set(key.clone(), query.clone());
+            (query, cache.take_evicted())
+        };
+
+        self.wake_watchers(&key);
+        self.cache_listeners.notify(QueryCacheEvent::Added(key.clone()));
+        for key in evicted {
+            self.cache_listeners.notify(QueryCacheEvent::Removed(key));
+        }
+
+        // The initial data is set after the query is in the cache, so the handlers can use it
+        if let Err(err) = query.set_initial_data::<T>() {
+            log::warn!("the initial data of the query `{key}` is ignored: {err}");
+        }
+
+        query
+    }
+
+    // Reports a query used with a fetcher different from the one it was created with,
+    // see `QueryClientBuilder::duplicate_fetcher`.
+    #[cfg(debug_assertions)]
+    fn check_fetcher<F, T>(&self, query: &Query, f: &F)
+    where
+        F: Fetch<T> + 'static,
+    {
+        let expected = query.fetcher_id();
+        let fetcher_id = f.fetcher_id();
+        if self.duplicate_fetcher == DuplicateFetcher::Ignore || expected == fetcher_id {
+            return;
+        }
+
+        let message = format!(
+            "the query `{}` was created with the fetcher `{}` but is fetched with `{}`, \
+            the first fetcher is used",
+            query.key(),
+            expected.type_name(),
+            fetcher_id.type_name()
+        );
+
+        if self.duplicate_fetcher == DuplicateFetcher::Panic {
+            panic!("{message}");
+        }
+
+        log::warn!("{message}");
+    }
+
+    pub(crate) fn resolve_options(&self, options: Option<&QueryOptions>) -> QueryOptions {
+        // The options of the query override the defaults of the client
+        let cache_time = options
+            .and_then(|x| x.cache_time)
+            .or(self.options.cache_time);
+        let refetch_time = options
+            .and_then(|x| x.refetch_time)
+            .or(self.options.refetch_time);
+        let retry = options
+            .and_then(|x| x.retry.clone())
+            .or_else(|| self.options.retry.clone());
+        let timeout = options
+            .and_then(|x| x.timeout)
+            .or(self.options.timeout);
+        let priority = options.and_then(|x| x.priority).or(self.options.priority);
+        let min_fetch_interval = options
+            .and_then(|x| x.min_fetch_interval)
+            .or(self.options.min_fetch_interval);
+        let error_reporter = self.options.error_reporter.clone();
+        let timer = self.options.timer.clone();
+        let fetch_limiter = self.options.fetch_limiter.clone();
+        let rate_limiter = self.options.rate_limiter.clone();
+        let middlewares = self.options.middlewares.clone();
+        let partition = options.and_then(|x| x.partition.clone());
+        let equality = options.and_then(|x| x.equality.clone());
+        let validator = options.and_then(|x| x.validator.clone());
+        let request_key = options.and_then(|x| x.request_key.clone());
+        let deduper = self.options.deduper.clone();
+        let initial_data = options.and_then(|x| x.initial_data.clone());
+        let initial_data_updated_at = options.and_then(|x| x.initial_data_updated_at);
+        let meta = match options {
+            Some(options) => self.options.meta.merge(&options.meta),
+            None => self.options.meta.clone(),
+        };
+
+        QueryOptions {
+            cache_time,
+            refetch_time,
+            retry,
+            timeout,
+            error_reporter,
+            meta,
+            middlewares,
+            partition,
+            equality,
+            initial_data,
+            initial_data_updated_at,
+            timer,
+            validator,
+            fetch_limiter,
+            priority,
+            min_fetch_interval,
+            rate_limiter,
+            request_key,
+            deduper,
+        }
+    }
+
     /// Executes the query with the given key, then cache and return the result.
+    ///
+    /// The fetch in progress of the query is cancelled, if any.
     pub async fn refetch_query<T: 'static>(&mut self, key: QueryKey) -> Result<Rc<T>, Error> {
-        let cache = self.cache.borrow_mut();
-        let query = cache.get(&key).cloned();
+        self.refetch_query_with(key, true).await
+    }
 
-        // We drop ownership to prevent borrow errors
-        drop(cache);
+    /// Executes the query with the given key, then cache and return the result.
+    ///
+    /// If the query is fetching and `cancel_refetch` is `true` the fetch in progress is cancelled
+    /// and a new one is started, otherwise the result of the fetch in progress is returned.
+    pub async fn refetch_query_with<T: 'static>(
+        &mut self,
+        key: QueryKey,
+        cancel_refetch: bool,
+    ) -> Result<Rc<T>, Error> {
+        // We clone the query to prevent borrow errors
+        let key = self.scoped_key(&key);
+        let query = self.cache.borrow().get(&key).cloned();
 
         let Some(mut query) = query else {
             return Err(Error::new(QueryError::key_not_found(&key)));
         };
 
+        if query.is_fetching() {
+            if !cancel_refetch {
+                return query.future::<T>().await;
+            }
+
+            query.cancel();
+        }
+
         let ret = query.fetch().await?;
         Ok(ret)
     }
 
+    /// Marks as stale the queries matching the given filter.
+    ///
+    /// Returns the number of queries invalidated.
+    pub fn invalidate_queries(&self, filter: impl Into<QueryFilter>) -> usize {
+        let queries = self.find_queries(&filter.into());
+        for query in queries.iter() {
+            query.invalidate();
+        }
+
+        queries.len()
+    }
+
+    /// Invalidates and refetches the queries matching the given filter.
+    ///
+    /// The errors are not returned, the state of the failed queries is updated instead.
+    /// Returns the number of queries refetched.
+    pub async fn refetch_queries(&self, filter: impl Into<QueryFilter>) -> usize {
+        let queries = self.find_queries(&filter.into());
+        let count = queries.len();
+
+        let futures = queries.into_iter().map(|mut query| async move {
+            query.invalidate();
+            query.fetch_value().await.ok();
+        });
+
+        futures::future::join_all(futures).await;
+        count
+    }
+
+    /// Invalidates the queries affected by a message pushed by the server,
+    /// the observed queries are refetched in the background.
+    ///
+    /// Returns the number of queries invalidated.
+    pub fn handle_invalidation_message(&self, message: InvalidationMessage) -> usize {
+        let queries = self.find_queries(&message.into());
+
+        for query in queries.iter() {
+            query.invalidate();
+
+            if query.has_observers() {
+                let mut query = query.clone();
+                self.spawner.spawn(async move {
+                    // On failure the state of the query is updated
+                    query.fetch_with_cause(FetchCause::Invalidation).await.ok();
+                });
+            }
+        }
+
+        queries.len()
+    }
+
+    /// Returns the key and error of the failed queries matching the given filter.
+    pub fn failed_queries(&self, filter: impl Into<QueryFilter>) -> Vec<(QueryKey, Error)> {
+        self.find_queries(&filter.into())
+            .into_iter()
+            .filter_map(|query| match query.state() {
+                QueryState::Failed(err) => Some((query.key().clone(), err)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the number of queries matching the filter that are resolving a future.
+    pub fn fetching_count(&self, filter: impl Into<QueryFilter>) -> usize {
+        self.find_queries(&filter.into())
+            .into_iter()
+            .filter(|query| query.is_fetching())
+            .count()
+    }
+
+    // Returns a copy of the queries matching the given filter.
+    fn find_queries(&self, filter: &QueryFilter) -> Vec<Query> {
+        let cache = self.cache.borrow();
+        cache
+            .iter()
+            .filter(|(key, _)| self.matches_filter(filter, key))
+            .map(|(_, query)| query.clone())
+            .collect()
+    }
+
+    // Returns `true` if the key is in the namespace of this client and matches the filter,
+    // the filter is matched against the key without the prefix.
+    fn matches_filter(&self, filter: &QueryFilter, key: &QueryKey) -> bool {
+        match &self.key_prefix {
+            Some(prefix) => key
+                .strip_prefix(prefix)
+                .is_some_and(|key| filter.matches(&key)),
+            None => filter.matches(key),
+        }
+    }
+
+    // Returns the key in the namespace of this client, see `QueryClientBuilder::key_prefix`.
+    //
+    // The keys given by the callers are always prefixed, even if already start with the prefix.
+    pub(crate) fn scoped_key(&self, key: &QueryKey) -> QueryKey {
+        match &self.key_prefix {
+            Some(prefix) => key.with_prefix(prefix),
+            None => key.clone(),
+        }
+    }
+
+    /// Returns a stream of the changes of the query with the given key.
+    ///
+    /// The first item is the current state of the query, if the query does not exist
+    /// the stream waits until is created.
+    pub fn watch_query<T: 'static>(&self, key: QueryKey) -> QueryWatcher<T> {
+        let key = self.scoped_key(&key);
+        QueryWatcher::new(self.clone(), key)
+    }
+
+    // Wakes the waker when the query is created, the key is already scoped.
+    pub(crate) fn register_watcher(&self, key: &QueryKey, waker: Waker) {
+        let mut watchers = self.watchers.borrow_mut();
+        let wakers = watchers.entry(key.clone()).or_default();
+        if !wakers.iter().any(|w| w.will_wake(&waker)) {
+            wakers.push(waker);
+        }
+    }
+
+    fn wake_watchers(&self, key: &QueryKey) {
+        let wakers = self.watchers.borrow_mut().remove(key);
+        for waker in wakers.into_iter().flatten() {
+            waker.wake();
+        }
+    }
+
+    /// Cancels the in-flight fetch of the query with the given key.
+    ///
+    /// Returns `false` if there is no query for the given key.
+    pub fn cancel_query(&self, key: &QueryKey) -> bool {
+        let key = self.scoped_key(key);
+        match self.cache.borrow().get(&key) {
+            Some(query) => {
+                query.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets the interval used to refetch the query with the given key, `None` stops refetching.
+    ///
+    /// Returns `false` if there is no query for the given key.
+    pub fn set_refetch_time(&self, key: &QueryKey, refetch_time: Option<Duration>) -> bool {
+        // We clone the query to prevent borrow errors
+        let key = self.scoped_key(key);
+        let query = self.cache.borrow().get(&key).cloned();
+        match query {
+            Some(query) => {
+                query.set_refetch_time(refetch_time);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pauses the refetch interval of the queries of this client and its clones,
+    /// and the refetches started by events like the window focus.
+    ///
+    /// The explicit fetches and refetches are not affected.
+    pub fn pause(&self) {
+        self.paused.set(true);
+    }
+
+    /// Resumes the refetches paused with `pause`, the stale queries with enabled observers
+    /// are refetched once.
+    pub fn resume(&self) {
+        if !self.paused.replace(false) {
+            return;
+        }
+
+        let queries = self.find_queries(&QueryFilter::All);
+        for mut query in queries {
+            if query.observer_count() == 0 || !query.is_enabled() || !query.is_stale() {
+                continue;
+            }
+
+            self.spawner.spawn(async move {
+                // The errors are stored in the state of the query
+                query.fetch_value().await.ok();
+            });
+        }
+    }
+
+    /// Returns `true` if the refetches of this client are paused, see `pause`.
+    pub fn is_paused(&self) -> bool {
+        self.paused.get()
+    }
+
+    /// Returns the `OnlineManager` of this client, if any.
+    ///
+    /// When not set the environment default is used, in the browser the `navigator` status.
+    pub fn online_manager(&self) -> Option<Rc<dyn OnlineManager>> {
+        self.online_manager.as_ref().map(|x| x.0.clone())
+    }
+
+    /// Returns the `FocusManager` of this client, if any.
+    ///
+    /// When not set the environment default is used, in the browser the focus of the window.
+    pub fn focus_manager(&self) -> Option<Rc<dyn FocusManager>> {
+        self.focus_manager.as_ref().map(|x| x.0.clone())
+    }
+
+    /// Returns the `Spawner` that runs the background tasks of this client.
+    pub fn spawner(&self) -> Rc<dyn Spawner> {
+        self.spawner.0.clone()
+    }
+
+    // Spawns a background task with the spawner of this client.
+    pub(crate) fn spawn<Fut>(&self, future: Fut)
+    where
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.spawner.spawn(future);
+    }
+
     /// Returns the query associated with the given key.
     pub fn get_query(&self, key: &QueryKey) -> Option<Ref<'_, Query>> {
+        self.get_scoped_query(&self.scoped_key(key))
+    }
+
+    // Returns the query with the given key, the key is already scoped like the keys of the cache.
+    pub(crate) fn get_scoped_query(&self, key: &QueryKey) -> Option<Ref<'_, Query>> {
         let cache = self.cache.borrow();
         if !cache.has(key) {
             return None;
         }
 
-        let ret = Ref::map(cache, |x| &*x.get(key).unwrap());
+        let ret = Ref::map(cache, |x| x.get(key).unwrap());
         Some(ret)
     }
 
     /// Returns `true` if there is a query associated with the given key.
     pub fn contains_query(&self, key: &QueryKey) -> bool {
+        let key = self.scoped_key(key);
         let cache = self.cache.borrow();
-        return cache.has(key);
+        cache.has(&key)
     }
 
     /// Returns `true` if there is cached data associated with the given key.
@@ -185,6 +755,47 @@ impl QueryClient {
         self.get_query(key).map(|q| !q.is_stale()).unwrap_or(false)
     }
 
+    /// Returns the number of observers attached to the query with the given key,
+    /// or `0` if the query does not exist.
+    pub fn observer_count(&self, key: &QueryKey) -> usize {
+        self.get_query(key).map(|q| q.observer_count()).unwrap_or(0)
+    }
+
+    /// Returns information about the queries in the cache, like their age and observers,
+    /// and their approximate size if the client has size estimators.
+    ///
+    /// With a key prefix only the queries in the namespace of this client are included.
+    pub fn cache_stats(&self) -> CacheStats {
+        let queries = self
+            .find_queries(&QueryFilter::All)
+            .into_iter()
+            .map(|query| {
+                let estimated_size = query.last_value().and_then(|value| {
+                    self.size_estimators
+                        .iter()
+                        .find_map(|estimator| estimator.estimate(value.as_ref()))
+                });
+
+                QueryStats {
+                    key: query.key().clone(),
+                    age: query.data_updated_at().map(|x| x.elapsed()),
+                    observers: query.observer_count(),
+                    estimated_size,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let estimated_size = match self.size_estimators.is_empty() {
+            true => None,
+            false => Some(queries.iter().filter_map(|x| x.estimated_size).sum()),
+        };
+
+        CacheStats {
+            queries,
+            estimated_size,
+        }
+    }
+
     /// Returns the cache query data associated with the given key.
     ///
     /// # Returns
@@ -192,16 +803,17 @@ impl QueryClient {
     /// - `Err(QueryError::KeyNotFound)` if there is not query associated with the given key.
     /// - `Err(QueryError::StaleValue)` if the query exists but is stale.
     /// - `Err(QueryError::TypeMismatch)` if the key don't match the given type or
-    /// if the query value cannot be converted to the given type.
+    ///   if the query value cannot be converted to the given type.
     pub fn get_query_data<T: 'static>(&self, key: &QueryKey) -> Result<Rc<T>, QueryError> {
         if !key.is_type::<T>() {
             return Err(QueryError::type_mismatch::<T>());
         }
 
+        let key = self.scoped_key(key);
         let cache = self.cache.borrow();
         cache
-            .get(key)
-            .ok_or_else(|| QueryError::key_not_found(key))
+            .get(&key)
+            .ok_or_else(|| QueryError::key_not_found(&key))
             .and_then(|q| {
                 if q.is_stale() {
                     Err(QueryError::StaleValue)
@@ -213,7 +825,7 @@ impl QueryClient {
                 query
                     .last_value()
                     .clone()
-                    .ok_or_else(|| QueryError::NotReady)
+                    .ok_or(QueryError::NotReady)
                     .map(|x| x.downcast::<T>().unwrap())
                     .map_err(|_| QueryError::type_mismatch::<T>())
             })
@@ -225,11 +837,11 @@ impl QueryClient {
     /// - `Some(QueryState)`: with the state of the query.
     /// - `None`: if the query do not exists.
     pub fn get_query_state(&self, key: &QueryKey) -> Option<QueryState> {
+        let key = self.scoped_key(key);
         self.cache
             .borrow()
-            .get(key)
+            .get(&key)
             .filter(|q| !q.is_stale())
-            .clone()
             .map(|x| x.state())
     }
 
@@ -243,70 +855,533 @@ impl QueryClient {
             return Err(QueryError::type_mismatch::<T>());
         }
 
-        let mut cache = self.cache.borrow_mut();
+        let key = self.scoped_key(&key);
 
-        match cache.get_mut(&key) {
-            Some(query) => {
-                if query.type_id() != TypeId::of::<T>() {
-                    return Err(QueryError::type_mismatch::<T>());
-                }
+        // We clone the query to prevent borrow errors while notifying the cache listeners
+        let mut query = self
+            .cache
+            .borrow()
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| QueryError::key_not_found(&key))?;
 
-                query.set_value(value)?;
-            }
-            None => {
-                return Err(QueryError::key_not_found(&key));
-            }
+        if query.type_id() != TypeId::of::<T>() {
+            return Err(QueryError::type_mismatch::<T>());
         }
 
-        Ok(())
-    }
-
-    /// Removes the query with the given key from the cache.
-    pub fn remove_query_data(&mut self, key: &QueryKey) -> bool {
-        let mut cache = self.cache.borrow_mut();
-        cache.remove(key).is_some()
+        query.set_value(value)
     }
 
-    /// Removes all the query data from the cache.
-    pub fn clear_queries(&mut self) {
-        let mut cache = self.cache.borrow_mut();
-        cache.clear();
+    /// Sets the cache value of each of the given keys, used to route the messages
+    /// of a subscription to the queries interested on them.
+    ///
+    /// The keys without a query or of other type are skipped.
+    ///
+    /// # Returns
+    /// The keys of the queries that were updated.
+    pub fn route_query_data<T, I>(&mut self, routed: I) -> Vec<QueryKey>
+    where
+        T: 'static,
+        I: IntoIterator<Item = (QueryKey, T)>,
+    {
+        routed
+            .into_iter()
+            .filter_map(|(key, value)| {
+                self.set_query_data(key.clone(), value).ok()?;
+                Some(key)
+            })
+            .collect()
     }
-}
 
-/// A builder for creating a `QueryClient`.
-#[derive(Default)]
-pub struct QueryClientBuilder {
-    cache: Option<Rc<RefCell<dyn QueryCache>>>,
-    options: QueryOptions,
-}
+    /// Updates the cached value for the given key in place.
+    ///
+    /// The value is copied on write, it is only cloned if is referenced outside the cache,
+    /// so editing a large `Vec` or `HashMap` don't require a deep clone of the collection.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the value was updated.
+    /// - `Err(QueryError::KeyNotFound)` if there is no query for the given key.
+    /// - `Err(QueryError::NotReady)` if the query don't have a value yet.
+    /// - `Err(QueryError::TypeMismatch)` if the query is not of type `T`.
+    pub fn set_query_data_with<T, F>(&mut self, key: QueryKey, f: F) -> Result<(), QueryError>
+    where
+        T: Clone + 'static,
+        F: FnOnce(&mut T),
+    {
+        if !key.is_type::<T>() {
+            return Err(QueryError::type_mismatch::<T>());
+        }
 
-impl QueryClientBuilder {
-    /// Constructs an empty `QueryClientBuilder`.
-    pub fn new() -> Self {
-        Default::default()
-    }
+        let key = self.scoped_key(&key);
 
-    /// Sets the time a query can be reused from cache.
-    pub fn cache_time(mut self, cache_time: Duration) -> Self {
-        self.options = self.options.cache_time(cache_time);
-        self
-    }
+        // We clone the query to prevent borrow errors while notifying the cache listeners
+        let mut query = self
+            .cache
+            .borrow()
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| QueryError::key_not_found(&key))?;
 
-    /// Sets the interval at which the data will be refetched.
-    pub fn refetch_time(mut self, refetch_time: Duration) -> Self {
-        self.options = self.options.refetch_time(refetch_time);
-        self
+        query.update_value(f)
     }
 
-    /// Sets a function used to retry a failed execution.
-    pub fn retry<R, I>(mut self, retry: R) -> Self
+    /// Attaches a reducer to the query with the given key, used to merge the updates
+    /// pushed with `apply_update` into the cached value.
+    ///
+    /// Fetching the query still replaces the cached value.
+    pub fn set_query_reducer<T, U, F>(&self, key: QueryKey, reducer: F)
     where
-        R: Fn() -> I + 'static,
-        I: Iterator<Item = Duration> + 'static,
+        T: 'static,
+        U: 'static,
+        F: Fn(&T, U) -> T + 'static,
     {
-        self.options = self.options.retry(retry);
-        self
+        let reducer = move |query: &mut Query, update: Box<dyn std::any::Any>| {
+            let update = update
+                .downcast::<U>()
+                .map_err(|_| QueryError::type_mismatch::<U>())?;
+
+            let value = query
+                .last_value()
+                .ok_or(QueryError::NotReady)?
+                .downcast::<T>()
+                .map_err(|_| QueryError::type_mismatch::<T>())?;
+
+            query.set_value(reducer(&value, *update))
+        };
+
+        self.reducers
+            .borrow_mut()
+            .insert(self.scoped_key(&key), ReducerHandle(Rc::new(reducer)));
+    }
+
+    /// Removes the reducer of the query with the given key.
+    pub fn remove_query_reducer(&self, key: &QueryKey) -> bool {
+        self.reducers
+            .borrow_mut()
+            .remove(&self.scoped_key(key))
+            .is_some()
+    }
+
+    /// Merges the given update into the cached value of the query using its reducer.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the update was applied.
+    /// - `Err(QueryError::KeyNotFound)` if there is no query or reducer for the given key.
+    /// - `Err(QueryError::NotReady)` if the query don't have a value yet.
+    /// - `Err(QueryError::TypeMismatch)` if the update don't match the type of the reducer.
+    pub fn apply_update<U: 'static>(&self, key: &QueryKey, update: U) -> Result<(), QueryError> {
+        let key = self.scoped_key(key);
+        let reducer = self
+            .reducers
+            .borrow()
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| QueryError::key_not_found(&key))?;
+
+        // We clone the query to prevent borrow errors while notifying the observers
+        let mut query = self
+            .cache
+            .borrow()
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| QueryError::key_not_found(&key))?;
+
+        (reducer.0)(&mut query, Box::new(update))
+    }
+
+    /// Applies multiple changes to the cache, notifying the observers and the cache listeners
+    /// once when the batch ends, so a change that updates many queries renders once.
+    ///
+    /// # Example
+    /// ```
+    /// use yew_query_core::{QueryClient, QueryKey};
+    ///
+    /// let mut client = QueryClient::builder().build();
+    /// let todos = QueryKey::of::<Vec<String>>("todos");
+    ///
+    /// client.batch(|tx| {
+    ///     tx.set_query_data(todos.clone(), vec![String::from("Write docs")]).ok();
+    ///     tx.invalidate(QueryKey::of::<usize>("todos/count"));
+    /// });
+    /// ```
+    pub fn batch<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut QueryTransaction<'_>) -> R,
+    {
+        notify::batch(|| f(&mut QueryTransaction::new(self)))
+    }
+
+    /// Removes the query with the given key from the cache.
+    pub fn remove_query_data(&mut self, key: &QueryKey) -> bool {
+        let key = self.scoped_key(key);
+        let removed = self.cache.borrow_mut().remove(&key).is_some();
+        if removed {
+            self.cache_listeners.notify(QueryCacheEvent::Removed(key));
+        }
+
+        removed
+    }
+
+    /// Returns the query with the given key to its initial state, keeping it in the cache.
+    ///
+    /// Unlike `remove_query_data` the options and observers of the query are kept.
+    pub fn reset_query(&self, key: &QueryKey) -> bool {
+        // We clone the query to prevent borrow errors while notifying the observers
+        let key = self.scoped_key(key);
+        let query = self.cache.borrow().get(&key).cloned();
+        match query {
+            Some(query) => {
+                query.reset();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes all the query data from the cache.
+    ///
+    /// With a key prefix only the queries in the namespace of this client are removed.
+    pub fn clear_queries(&mut self) {
+        if self.key_prefix.is_none() {
+            self.cache.borrow_mut().clear();
+            self.cache_listeners.notify(QueryCacheEvent::Cleared);
+            return;
+        }
+
+        let removed = {
+            let mut cache = self.cache.borrow_mut();
+            let keys = cache
+                .keys()
+                .into_iter()
+                .filter(|key| self.matches_filter(&QueryFilter::All, key))
+                .collect::<Vec<_>>();
+
+            for key in keys.iter() {
+                cache.remove(key);
+            }
+
+            keys
+        };
+
+        for key in removed {
+            self.cache_listeners.notify(QueryCacheEvent::Removed(key));
+        }
+    }
+
+    /// Removes the queries stored in the given partition of the cache.
+    ///
+    /// With a key prefix only the queries in the namespace of this client are removed.
+    ///
+    /// Returns the number of queries removed.
+    pub fn clear_partition(&mut self, partition: &str) -> usize {
+        let removed = {
+            let mut cache = self.cache.borrow_mut();
+            let keys = cache
+                .iter()
+                .filter(|(key, _)| self.matches_filter(&QueryFilter::All, key))
+                .filter(|(_, query)| query.partition().as_deref() == Some(partition))
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+
+            for key in keys.iter() {
+                cache.remove(key);
+            }
+
+            keys
+        };
+
+        for key in removed.iter() {
+            self.cache_listeners
+                .notify(QueryCacheEvent::Removed(key.clone()));
+        }
+
+        removed.len()
+    }
+
+    /// Removes the inactive queries until the cache has at most `target` entries.
+    ///
+    /// Queries that are being observed or fetched are never removed,
+    /// the queries updated least recently are removed first.
+    ///
+    /// With a key prefix only the queries in the namespace of this client are counted and removed.
+    ///
+    /// Returns the number of queries removed.
+    pub fn trim(&self, target: usize) -> usize {
+        trim_cache(&self.cache, &self.cache_listeners, target, &|key| {
+            self.matches_filter(&QueryFilter::All, key)
+        })
+    }
+
+    /// Returns a copy of the value and state of all the queries in the cache.
+    ///
+    /// With a key prefix only the queries in the namespace of this client are included.
+    pub fn snapshot(&self) -> CacheSnapshot {
+        let snapshot = self.cache.borrow().snapshot();
+        if self.key_prefix.is_none() {
+            return snapshot;
+        }
+
+        CacheSnapshot {
+            queries: snapshot
+                .queries
+                .into_iter()
+                .filter(|x| self.matches_filter(&QueryFilter::All, x.key()))
+                .collect(),
+        }
+    }
+
+    /// Replaces the queries in the cache with the given snapshot, without executing the fetchers.
+    ///
+    /// Queries that still exist are updated in place, so their observers are notified.
+    ///
+    /// With a key prefix only the queries in the namespace of this client are replaced,
+    /// the queries of the snapshot outside the namespace are ignored.
+    pub fn restore(&mut self, snapshot: &CacheSnapshot) {
+        let mut events = Vec::new();
+        let mut restored = Vec::new();
+
+        {
+            let mut cache = self.cache.borrow_mut();
+            for key in cache.keys() {
+                if self.matches_filter(&QueryFilter::All, &key) && snapshot.get(&key).is_none() {
+                    cache.remove(&key);
+                    events.push(QueryCacheEvent::Removed(key));
+                }
+            }
+
+            for query_snapshot in snapshot.iter() {
+                let key = query_snapshot.key();
+                if !self.matches_filter(&QueryFilter::All, key) {
+                    continue;
+                }
+
+                match cache.get(key).cloned() {
+                    Some(query) => restored.push((query, query_snapshot)),
+                    None => {
+                        let query = Query::from_snapshot(query_snapshot);
+                        query.set_cache_handler(self.cache_listeners.query_handler(key.clone()));
+                        query.set_paused_handle(self.paused.clone());
+                        query.set_spawner(self.spawner.clone());
+                        cache.set(key.clone(), query);
+                        self.wake_watchers(key);
+                        events.push(QueryCacheEvent::Added(key.clone()));
+                    }
+                }
+            }
+
+            events.extend(cache.take_evicted().into_iter().map(QueryCacheEvent::Removed));
+        }
+
+        // We notify after releasing the cache, the observers may use the client
+        notify::batch(|| {
+            for (query, query_snapshot) in restored {
+                query.restore(query_snapshot);
+            }
+        });
+
+        for event in events {
+            self.cache_listeners.notify(event);
+        }
+    }
+
+    /// Registers a listener that receives the changes of the entries of the cache.
+    ///
+    /// Entries evicted by the cache on insertion are reported as removed,
+    /// if the cache implements `QueryCache::take_evicted`.
+    ///
+    /// Returns an id to remove the listener.
+    pub fn add_cache_listener<L>(&self, listener: L) -> usize
+    where
+        L: QueryCacheListener + 'static,
+    {
+        self.cache_listeners.add(Rc::new(listener))
+    }
+
+    /// Removes the cache listener with the given id.
+    pub fn remove_cache_listener(&self, id: usize) -> bool {
+        self.cache_listeners.remove(id)
+    }
+}
+
+/// A builder for creating a `QueryClient`.
+///
+/// The options set in the builder are the defaults of the queries of the client,
+/// the options of each query, like `QueryOptions::cache_time`, take precedence over them.
+#[derive(Default)]
+pub struct QueryClientBuilder {
+    cache: Option<Rc<RefCell<dyn QueryCache>>>,
+    shared: Option<QueryClient>,
+    options: QueryOptions,
+    memory_pressure: Option<MemoryPressure>,
+    online_manager: Option<OnlineManagerHandle>,
+    focus_manager: Option<FocusManagerHandle>,
+    spawner: Option<SpawnerHandle>,
+    key_prefix: Option<String>,
+    duplicate_fetcher: DuplicateFetcher,
+    size_estimators: Vec<SizeEstimatorHandle>,
+}
+
+impl QueryClientBuilder {
+    /// Constructs an empty `QueryClientBuilder`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the time a query can be reused from cache,
+    /// unless the query sets its own `QueryOptions::cache_time`.
+    pub fn cache_time(mut self, cache_time: Duration) -> Self {
+        self.options = self.options.cache_time(cache_time);
+        self
+    }
+
+    /// Sets the interval at which the data will be refetched,
+    /// unless the query sets its own `QueryOptions::refetch_time`.
+    pub fn refetch_time(mut self, refetch_time: Duration) -> Self {
+        self.options = self.options.refetch_time(refetch_time);
+        self
+    }
+
+    /// Sets a function used to retry a failed execution,
+    /// unless the query sets its own `QueryOptions::retry`.
+    pub fn retry<R, I>(mut self, retry: R) -> Self
+    where
+        R: Fn() -> I + 'static,
+        I: Iterator<Item = Duration> + 'static,
+    {
+        self.options = self.options.retry(retry);
+        self
+    }
+
+    /// Adds an entry to the metadata shared by all the queries.
+    pub fn meta(mut self, key: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.options = self.options.meta(key, value);
+        self
+    }
+
+    /// Sets the max time a fetch can take before failing,
+    /// unless the query sets its own `QueryOptions::timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options = self.options.timeout(timeout);
+        self
+    }
+
+    /// Sets the reporter that receives the errors of the queries that failed after all the retries.
+    pub fn error_reporter<R>(mut self, reporter: R) -> Self
+    where
+        R: ErrorReporter + 'static,
+    {
+        self.options.error_reporter = Some(ErrorReporterHandle::new(reporter));
+        self
+    }
+
+    /// Sets the `TimerBackend` used for the timeouts, retries and intervals of the client,
+    /// defaults to `DefaultTimer`.
+    pub fn timer<T>(mut self, timer: T) -> Self
+    where
+        T: TimerBackend + 'static,
+    {
+        self.options.timer = Some(TimerHandle(Rc::new(timer)));
+        self
+    }
+
+    /// Sets the min time between the start of two fetches of each key,
+    /// see `QueryOptions::min_fetch_interval`.
+    pub fn min_fetch_interval(mut self, interval: Duration) -> Self {
+        self.options = self.options.min_fetch_interval(interval);
+        self
+    }
+
+    /// Sets the max number of fetches of the client running at the same time,
+    /// the fetches over the limit wait in a queue and report `is_queued` until they start.
+    ///
+    /// Each retry waits for its turn again, so the delays between retries don't block the queue.
+    /// The queued fetches start by the priority of their queries, see `QueryOptions::priority`.
+    ///
+    /// # Panics
+    /// If the max is 0.
+    pub fn max_concurrent_fetches(mut self, max: usize) -> Self {
+        self.options.fetch_limiter = Some(FetchLimiter::new(max));
+        self
+    }
+
+    /// Adds a middleware that wraps every fetch made by the client.
+    ///
+    /// Middlewares run in the order they are added.
+    pub fn middleware<M>(mut self, middleware: M) -> Self
+    where
+        M: Middleware + 'static,
+    {
+        self.options
+            .middlewares
+            .push(MiddlewareHandle::new(middleware));
+        self
+    }
+
+    /// Evicts the inactive queries when the heap grows over the limit of the given `MemoryPressure`.
+    pub fn memory_pressure(mut self, memory_pressure: MemoryPressure) -> Self {
+        self.memory_pressure = Some(memory_pressure);
+        self
+    }
+
+    /// Sets the `OnlineManager` that provides the connectivity status of the client.
+    pub fn online_manager<M>(mut self, online_manager: M) -> Self
+    where
+        M: OnlineManager + 'static,
+    {
+        self.online_manager = Some(OnlineManagerHandle(Rc::new(online_manager)));
+        self
+    }
+
+    /// Sets the `FocusManager` that provides the focus status of the client.
+    pub fn focus_manager<M>(mut self, focus_manager: M) -> Self
+    where
+        M: FocusManager + 'static,
+    {
+        self.focus_manager = Some(FocusManagerHandle(Rc::new(focus_manager)));
+        self
+    }
+
+    /// Sets the `Spawner` that runs the background tasks of the client,
+    /// defaults to `DefaultSpawner`.
+    pub fn spawner<S>(mut self, spawner: S) -> Self
+    where
+        S: Spawner + 'static,
+    {
+        self.spawner = Some(SpawnerHandle(Rc::new(spawner)));
+        self
+    }
+
+    /// Sets a prefix prepended to the keys of all the queries of the client,
+    /// joined to the key with a `/`.
+    ///
+    /// Isolates the queries of clients sharing a cache, like the tenants of an app or
+    /// the embedded widgets, without changing the keys used in the hooks.
+    /// The filters only match the queries with the prefix, and are matched without the prefix.
+    /// The keys given to the client are always prefixed, while the keys of the cache
+    /// and its events include the prefix.
+    pub fn key_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.key_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Sets what to do when a key is fetched with a fetcher different from the one the query
+    /// was created with, defaults to `DuplicateFetcher::Warn`.
+    ///
+    /// Only checked in debug builds.
+    pub fn duplicate_fetcher(mut self, duplicate_fetcher: DuplicateFetcher) -> Self {
+        self.duplicate_fetcher = duplicate_fetcher;
+        self
+    }
+
+    /// Adds a function that estimates the size in bytes of the values of type `T`,
+    /// used for the estimated sizes of `QueryClient::cache_stats`.
+    pub fn size_estimator<T, F>(mut self, estimate: F) -> Self
+    where
+        T: 'static,
+        F: Fn(&T) -> usize + 'static,
+    {
+        self.size_estimators.push(SizeEstimatorHandle::new(estimate));
+        self
     }
 
     /// Sets the cache implementation used for the client.
@@ -318,288 +1393,3083 @@ impl QueryClientBuilder {
         self
     }
 
-    /// Returns the `QueryClient` using this builder options.
-    pub fn build(self) -> QueryClient {
-        let Self { cache, options } = self;
+    /// Shares the cache of the given client, with its cache listeners, watchers and sweeper,
+    /// so the changes made by any of the clients are seen by the other.
+    ///
+    /// The queries already in the cache keep the options of the client that fetched them.
+    pub fn shared_cache(mut self, client: &QueryClient) -> Self {
+        self.shared = Some(client.clone());
+        self
+    }
+
+    /// Returns the `QueryClient` using this builder options.
+    pub fn build(self) -> QueryClient {
+        let Self {
+            cache,
+            shared,
+            mut options,
+            memory_pressure,
+            online_manager,
+            focus_manager,
+            spawner,
+            key_prefix,
+            duplicate_fetcher,
+            size_estimators,
+        } = self;
+
+        let spawner = spawner.unwrap_or_default();
+        let timer = options.timer.clone().unwrap_or_default();
+        options.rate_limiter = Some(RateLimiter::default());
+        options.deduper = Some(RequestDeduper::default());
+
+        // A shared cache is swept and listened once for all the clients
+        if let Some(shared) = shared {
+            let memory_monitor = match memory_pressure {
+                Some(memory_pressure) => Some(memory_monitor(
+                    &shared.cache,
+                    &shared.cache_listeners,
+                    memory_pressure,
+                    &spawner,
+                    &timer,
+                )),
+                None => shared.memory_monitor.clone(),
+            };
+
+            return QueryClient {
+                cache: shared.cache,
+                options,
+                watchers: shared.watchers,
+                reducers: Default::default(),
+                uncached_fetches: Default::default(),
+                cache_listeners: shared.cache_listeners,
+                paused: Default::default(),
+                online_manager,
+                focus_manager,
+                spawner,
+                key_prefix: key_prefix.map(|prefix| Rc::from(format!("{prefix}/"))),
+                duplicate_fetcher,
+                size_estimators: size_estimators.into(),
+                sweeper: shared.sweeper,
+                memory_monitor,
+            };
+        }
+
+        let cache = cache
+            .or_else(|| Some(Rc::new(RefCell::new(HashMap::new()))))
+            .unwrap();
+
+        let cache_listeners = CacheListeners::default();
+        let sweep_interval = cache.borrow().sweep_interval();
+        let sweeper = sweep_interval.map(|sweep_interval| {
+            let cache = Rc::downgrade(&cache);
+            let cache_listeners = cache_listeners.clone();
+            let idle_spawner = spawner.clone();
+            let interval = Interval::new(&spawner, &timer, sweep_interval, move || {
+                let cache = cache.clone();
+                let cache_listeners = cache_listeners.clone();
+
+                // The sweep may be expensive, so we wait until the browser is idle
+                request_idle(&idle_spawner, sweep_interval, move || {
+                    let Some(cache) = cache.upgrade() else {
+                        return;
+                    };
+
+                    // If the cache is in use we wait for the next sweep
+                    let Ok(mut cache) = cache.try_borrow_mut() else {
+                        return;
+                    };
+
+                    let keys = cache.keys();
+                    cache.sweep();
+
+                    // The evicted entries are included in the removed keys
+                    cache.take_evicted();
+
+                    let removed = keys
+                        .into_iter()
+                        .filter(|key| !cache.has(key))
+                        .collect::<Vec<_>>();
+
+                    drop(cache);
+
+                    for key in removed {
+                        cache_listeners.notify(QueryCacheEvent::Removed(key));
+                    }
+                });
+            });
+
+            Rc::new(interval)
+        });
+
+        let memory_monitor = memory_pressure.map(|memory_pressure| {
+            memory_monitor(&cache, &cache_listeners, memory_pressure, &spawner, &timer)
+        });
+
+        QueryClient {
+            cache,
+            options,
+            watchers: Default::default(),
+            reducers: Default::default(),
+            uncached_fetches: Default::default(),
+            cache_listeners,
+            paused: Default::default(),
+            online_manager,
+            focus_manager,
+            spawner,
+            key_prefix: key_prefix.map(|prefix| Rc::from(format!("{prefix}/"))),
+            duplicate_fetcher,
+            size_estimators: size_estimators.into(),
+            sweeper,
+            memory_monitor,
+        }
+    }
+}
+
+// Trims the cache while the memory is over the limit, until the last client is dropped.
+fn memory_monitor(
+    cache: &Rc<RefCell<dyn QueryCache>>,
+    cache_listeners: &CacheListeners,
+    memory_pressure: MemoryPressure,
+    spawner: &SpawnerHandle,
+    timer: &TimerHandle,
+) -> Rc<Interval> {
+    let cache = Rc::downgrade(cache);
+    let cache_listeners = cache_listeners.clone();
+    let interval = Interval::new(spawner, timer, memory_pressure.check_interval, move || {
+        if !memory_pressure.is_over_limit() {
+            return;
+        }
+
+        if let Some(cache) = cache.upgrade() {
+            trim_cache(&cache, &cache_listeners, memory_pressure.target, &|_| true);
+        }
+    });
+
+    Rc::new(interval)
+}
+
+// Removes the inactive queries until the cache has at most `target` entries,
+// the queries updated least recently are removed first.
+fn trim_cache(
+    cache: &RefCell<dyn QueryCache>,
+    cache_listeners: &CacheListeners,
+    target: usize,
+    scope: &dyn Fn(&QueryKey) -> bool,
+) -> usize {
+    let Ok(mut cache) = cache.try_borrow_mut() else {
+        return 0;
+    };
+
+    let len = cache.keys().iter().filter(|key| scope(key)).count();
+    if len <= target {
+        return 0;
+    }
+
+    let mut inactive = cache
+        .iter()
+        .filter(|(key, _)| scope(key))
+        .filter(|(_, query)| !query.has_observers() && !query.is_fetching())
+        .map(|(key, query)| (query.data_updated_at(), key.clone()))
+        .collect::<Vec<_>>();
+
+    inactive.sort_by_key(|(updated_at, _)| *updated_at);
+
+    let removed = inactive
+        .into_iter()
+        .take(len - target)
+        .filter_map(|(_, key)| cache.remove(&key).map(|_| key))
+        .collect::<Vec<_>>();
+
+    drop(cache);
+
+    for key in removed.iter() {
+        cache_listeners.notify(QueryCacheEvent::Removed(key.clone()));
+    }
+
+    removed.len()
+}
+
+pub(crate) async fn fetch_with_retry<F, T>(
+    key: QueryKey,
+    fetcher: F,
+    options: QueryOptions,
+    on_retry: Option<OnRetry>,
+    signal: CancellationToken,
+    validators: Option<Validators>,
+    response: FetchResponse,
+) -> Result<T, Error>
+where
+    F: Fetch<T> + 'static,
+    T: 'static,
+{
+    let timer = options.timer.clone().unwrap_or_default();
+    if let (Some(interval), Some(rate_limiter)) = (options.min_fetch_interval, &options.rate_limiter)
+    {
+        let wait = rate_limiter.wait(&key, interval, &timer).map(Ok);
+        with_cancellation(wait, &signal).await?;
+    }
+
+    let started_at = Instant::now();
+    #[cfg(feature = "perf-marks")]
+    let mark = crate::perf::FetchMark::start(&key);
+
+    let timeout = options.timeout;
+    let limiter = options
+        .fetch_limiter
+        .as_ref()
+        .map(|limiter| (limiter, options.priority.unwrap_or_default()));
+    let on_queued = response.on_queued().cloned();
+    let on_queued = on_queued.as_ref();
+    let mut attempts = 1;
+    let context = |attempt| FetchContext {
+        key: key.clone(),
+        attempt,
+        meta: options.meta.clone(),
+        page_param: None,
+        signal: signal.clone(),
+        validators: validators.clone(),
+        response: response.clone(),
+    };
+
+    let mut ret = with_cancellation(
+        fetch_with_limit(&fetcher, context(attempts), timeout, &timer, limiter, on_queued),
+        &signal,
+    )
+    .await;
+
+    if should_retry(&ret) {
+        if let Some(retry) = options.retry {
+            let iter = retry.get();
+            for (idx, delay) in iter.enumerate() {
+                if let Some(on_retry) = &on_retry {
+                    on_retry(RetryProgress::new(idx + 1, delay));
+                }
+
+                let sleep = timer.sleep(delay).map(Ok);
+                if let Err(err) = with_cancellation(sleep, &signal).await {
+                    ret = Err(err);
+                    break;
+                }
+
+                attempts += 1;
+                let ctx = context(attempts);
+                ret = with_cancellation(
+                    fetch_with_limit(&fetcher, ctx, timeout, &timer, limiter, on_queued),
+                    &signal,
+                )
+                .await;
+                if !should_retry(&ret) {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "perf-marks")]
+    mark.end(ret.is_ok());
+
+    match (&ret, &options.error_reporter) {
+        (Err(err), Some(reporter)) if !err.is_cancelled() && !err.is_not_modified() => {
+            let context = ErrorContext {
+                key,
+                meta: options.meta.clone(),
+                attempts,
+                duration: started_at.elapsed(),
+            };
+
+            reporter.report(err, &context);
+        }
+        _ => {}
+    }
+
+    ret
+}
+
+// Waits for a permit of the limiter, if any, before the fetch and its timeout start.
+async fn fetch_with_limit<F, T>(
+    fetcher: &F,
+    ctx: FetchContext,
+    timeout: Option<Duration>,
+    timer: &TimerHandle,
+    limiter: Option<(&FetchLimiter, FetchPriority)>,
+    on_queued: Option<&OnQueued>,
+) -> Result<T, Error>
+where
+    F: Fetch<T> + 'static,
+    T: 'static,
+{
+    let _permit = match limiter {
+        Some((limiter, priority)) => Some(limiter.acquire(priority, on_queued).await),
+        None => None,
+    };
+
+    fetch_with_timeout(fetcher, ctx, timeout, timer).await
+}
+
+async fn fetch_with_timeout<F, T>(
+    fetcher: &F,
+    ctx: FetchContext,
+    timeout: Option<Duration>,
+    timer: &TimerHandle,
+) -> Result<T, Error>
+where
+    F: Fetch<T> + 'static,
+    T: 'static,
+{
+    let fut = fetcher.get(ctx);
+    let Some(timeout) = timeout else {
+        return fut.await;
+    };
+
+    match timer.timeout(timeout, fut).await {
+        Some(ret) => ret,
+        None => Err(Error::new(QueryError::Timeout)),
+    }
+}
+
+// Resolves to a cancelled error if the signal is cancelled before the future completes
+pub(crate) async fn with_cancellation<Fut, T>(fut: Fut, signal: &CancellationToken) -> Result<T, Error>
+where
+    Fut: Future<Output = Result<T, Error>>,
+{
+    if signal.is_cancelled() {
+        return Err(Error::new(QueryError::Cancelled));
+    }
+
+    let cancelled = signal.cancelled();
+    futures::pin_mut!(fut);
+    futures::pin_mut!(cancelled);
+
+    match futures::future::select(fut, cancelled).await {
+        Either::Left((ret, _)) => ret,
+        Either::Right(_) => Err(Error::new(QueryError::Cancelled)),
+    }
+}
+
+// Cancelled and not modified fetches are not retried
+fn should_retry<T>(ret: &Result<T, Error>) -> bool {
+    match ret {
+        Ok(_) => false,
+        Err(err) => {
+            !err.is_cancelled() && !err.is_not_modified() && err.validation_error().is_none()
+        }
+    }
+}
+
+#[cfg(test)]
+// `repeat_n` is not used to support the versions of Rust before 1.82
+#[allow(clippy::manual_repeat_n)]
+mod tests {
+    use std::{
+        cell::{Cell, RefCell},
+        convert::Infallible,
+        rc::Rc,
+    };
+
+    use futures::{future::LocalBoxFuture, Future, FutureExt, StreamExt};
+    use instant::{Duration, Instant};
+    use tokio::task::LocalSet;
+
+    use crate::{
+        error::{ErrorContext, QueryError, ValidationError},
+        fetcher::{BoxFetcher, DuplicateFetcher, FetchContext, Progress, Validators},
+        middleware::{AuthRefresh, FetchOutput, Next},
+        query::Query,
+        CacheSnapshot, Error, FetchCause, FetchOutcome, FetchStatus, InvalidationMessage, LruCache,
+        ManualFocusManager, ManualOnlineManager, ObserveTarget, QueryCacheEvent, QueryChangeEvent,
+        QueryChanged, QueryClient, QueryFilter, QueryKey, QueryObserver, QueryOptions, QueryState,
+        SnapshotDiff, Spawner, TimerBackend, TtlCache,
+    };
+
+    #[tokio::test]
+    async fn fetch_and_cache_query_test() {
+        #[derive(Debug, PartialEq)]
+        struct Item {
+            name: String,
+        }
+
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(400))
+                .build();
+
+            let key = QueryKey::of::<Item>("sword");
+
+            assert!(!client.contains_query(&key));
+
+            let ret = client
+                .fetch_query(key.clone(), || async {
+                    Ok::<_, Infallible>(Item {
+                        name: "Fire Sword".to_owned(),
+                    })
+                })
+                .await
+                .unwrap();
+
+            assert_eq!(
+                ret.as_ref(),
+                &Item {
+                    name: "Fire Sword".to_owned()
+                }
+            );
+
+            assert!(!client.is_stale(&key));
+            assert_eq!(
+                client.get_query_data::<Item>(&key).ok().as_deref(),
+                Some(&Item {
+                    name: "Fire Sword".to_owned()
+                })
+            );
+
+            // Let the data expire
+            tokio::time::sleep(Duration::from_millis(500)).await;
+
+            assert!(client.is_stale(&key));
+            assert!(matches!(
+                client.get_query_data::<Item>(&key),
+                Err(QueryError::StaleValue)
+            ));
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn refetch_and_cache_query_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .build();
+
+            let key = QueryKey::of::<String>("color");
+            let value = client
+                .fetch_query(key.clone(), || async {
+                    Ok::<_, Infallible>("magenta".to_owned())
+                })
+                .await
+                .unwrap();
+
+            assert!(client.has_query_data(&key));
+            assert_eq!(value.as_str(), "magenta");
+
+            // Wait for timeout
+            tokio::time::sleep(Duration::from_millis(300)).await;
+
+            // Expired
+            assert!(!client.has_query_data(&key));
+
+            // Refetch
+            let value = client.refetch_query::<String>(key.clone()).await.unwrap();
+            assert!(client.has_query_data(&key));
+            assert_eq!(value.as_str(), "magenta");
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn set_and_get_query_data_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .build();
+
+            let key = QueryKey::of::<String>("color");
+            client
+                .fetch_query(key.clone(), || async {
+                    Ok::<_, Infallible>("pink".to_owned())
+                })
+                .await
+                .unwrap();
+
+            assert_eq!(
+                client.get_query_data(&key).ok().as_deref(),
+                Some(&String::from("pink"))
+            );
+
+            assert!(client.has_query_data(&key));
+
+            // Wait for timeout
+            tokio::time::sleep(Duration::from_millis(300)).await;
+
+            assert!(!client.has_query_data(&key));
+
+            // Sets the data
+            client
+                .set_query_data(key.clone(), String::from("aqua"))
+                .unwrap();
+
+            assert_eq!(
+                client.get_query_data(&key).ok().as_deref(),
+                Some(&String::from("aqua"))
+            );
+
+            // Wait for timeout
+            tokio::time::sleep(Duration::from_millis(300)).await;
+
+            assert!(matches!(
+                client.get_query_data::<String>(&key),
+                Err(QueryError::StaleValue)
+            ));
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn contains_and_get_query_then_remove_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .build();
+
+            let key = QueryKey::of::<String>("fruit");
+
+            assert!(!client.contains_query(&key));
+            assert!(client.get_query(&key).is_none());
+            assert!(!client.has_query_data(&key));
+
+            client
+                .fetch_query(key.clone(), || async {
+                    Ok::<_, Infallible>("strawberry".to_owned())
+                })
+                .await
+                .unwrap();
+
+            assert!(client.contains_query(&key));
+            assert!(client.get_query(&key).is_some());
+            assert!(client.has_query_data(&key));
+
+            // Wait for timeout
+            tokio::time::sleep(Duration::from_millis(300)).await;
+
+            assert!(client.contains_query(&key));
+            assert!(client.get_query(&key).is_some());
+            assert!(!client.has_query_data(&key));
+
+            // Remove the query
+            client.remove_query_data(&key);
+
+            assert!(!client.contains_query(&key));
+            assert!(client.get_query(&key).is_none());
+            assert!(!client.has_query_data(&key));
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn clear_queries_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .build();
+
+            let fruit_key = QueryKey::of::<String>("fruit");
+            let color_key = QueryKey::of::<String>("color");
+
+            client
+                .fetch_query(fruit_key.clone(), || async {
+                    Ok::<_, Infallible>("apple".to_owned())
+                })
+                .await
+                .unwrap();
+
+            client
+                .fetch_query(color_key.clone(), || async {
+                    Ok::<_, Infallible>("red".to_owned())
+                })
+                .await
+                .unwrap();
+
+            assert!(client.contains_query(&fruit_key));
+            assert!(client.contains_query(&color_key));
+
+            client.clear_queries();
+
+            assert!(!client.contains_query(&fruit_key));
+            assert!(!client.contains_query(&color_key));
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn query_with_refetch_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(300))
+                .refetch_time(Duration::from_millis(400))
+                .build();
+
+            let key = QueryKey::of::<String>("fruit");
+            client
+                .fetch_query(key.clone(), || async { Ok::<_, Infallible>("pineapple") })
+                .await
+                .unwrap();
+
+            assert!(client.has_query_data(&key));
+
+            // Timeout
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            assert!(!client.has_query_data(&key));
+
+            // Wait for refetch
+            tokio::time::sleep(Duration::from_millis(600)).await;
+            assert!(client.has_query_data(&key));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn keep_last_value_on_failed_refetch_test() {
+        run_local(async {
+            let client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .build();
+
+            let count = Rc::new(Cell::new(0));
+            let fetcher = move || {
+                let count = count.clone();
+                async move {
+                    count.set(count.get() + 1);
+                    match count.get() {
+                        1 => Ok("mars".to_owned()),
+                        _ => Err(QueryError::NotReady),
+                    }
+                }
+            };
+
+            let events = Rc::new(RefCell::new(Vec::new()));
+            let on_change = {
+                let events = events.clone();
+                move |event: QueryChangeEvent<String>| {
+                    events.borrow_mut().push((event.state, event.value))
+                }
+            };
+
+            let observer = QueryObserver::<String>::new(client.clone(), "planet".into());
+            observer.observe(ObserveTarget::Fetch, fetcher.clone(), on_change.clone());
+            tokio::time::sleep(Duration::from_millis(20)).await;
+
+            observer.observe(ObserveTarget::Refetch, fetcher, on_change);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+
+            // The failed refetch is reported with the last value
+            let events = events.borrow();
+            let (state, value) = events.last().unwrap();
+            assert!(state.is_failed());
+            assert_eq!(value.as_deref().map(String::as_str), Some("mars"));
+
+            let query = client.get_query(observer.key()).unwrap();
+            assert!(query.data_updated_at().is_some());
+            assert!(query.error_updated_at() >= query.data_updated_at());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn revalidate_not_modified_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .build();
+
+            let key = QueryKey::of::<String>("planet");
+            let fetcher = BoxFetcher::with_context(|ctx: FetchContext| async move {
+                let etag = ctx.validators.as_ref().and_then(|v| v.etag.as_deref());
+                if etag == Some("v1") {
+                    return Err(QueryError::NotModified);
+                }
+
+                ctx.set_validators(Validators::new().etag("v1"));
+                Ok("venus".to_owned())
+            });
+
+            let first = client.fetch_query(key.clone(), fetcher).await.unwrap();
+            let updated_at = client.get_query(&key).unwrap().data_updated_at();
+
+            let second = client.refetch_query::<String>(key.clone()).await.unwrap();
+            assert!(Rc::ptr_eq(&first, &second));
+
+            let query = client.get_query(&key).unwrap();
+            assert!(query.state().is_ready());
+            assert!(query.data_updated_at() > updated_at);
+            assert_eq!(query.validators(), Some(Validators::new().etag("v1")));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn fresh_for_hint_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .build();
+
+            let short = QueryKey::of::<String>("short");
+            let fetcher = BoxFetcher::with_context(|ctx: FetchContext| async move {
+                ctx.set_cache_control("public, max-age=0");
+                Ok::<_, Infallible>("mercury".to_owned())
+            });
+
+            client.fetch_query(short.clone(), fetcher).await.unwrap();
+            assert!(client.is_stale(&short));
+
+            let long = QueryKey::of::<String>("long");
+            let fetcher = BoxFetcher::with_context(|ctx: FetchContext| async move {
+                ctx.set_fresh_for(Duration::from_secs(60));
+                Ok::<_, Infallible>("jupiter".to_owned())
+            });
+
+            client.fetch_query(long.clone(), fetcher).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            assert!(!client.is_stale(&long));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn notify_retry_progress_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .retry(|| std::iter::repeat(Duration::from_millis(10)).take(2))
+                .build();
+
+            let attempts = Rc::new(RefCell::new(Vec::new()));
+            let on_change = {
+                let attempts = attempts.clone();
+                move |event: QueryChanged| {
+                    if let Some(retry) = event.retry {
+                        assert!(event.is_fetching);
+                        assert!(retry.retry_in() <= Duration::from_millis(10));
+                        attempts.borrow_mut().push(retry.attempt());
+                    }
+                }
+            };
+
+            let key = QueryKey::of::<String>("unknown");
+            let ret = client
+                .fetch_query_with_options_and_observe(
+                    key,
+                    || async { Err::<String, _>(QueryError::NotReady) },
+                    None,
+                    Some(Rc::new(on_change)),
+                )
+                .await;
+
+            assert!(ret.is_err());
+            assert_eq!(*attempts.borrow(), vec![1, 2]);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn notify_fetch_progress_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .build();
+
+            let percents = Rc::new(RefCell::new(Vec::new()));
+            let on_change = {
+                let percents = percents.clone();
+                move |event: QueryChanged| {
+                    if let Some(progress) = event.progress {
+                        assert!(event.is_fetching);
+                        percents.borrow_mut().push(progress.percent().unwrap());
+                    }
+                }
+            };
+
+            let key = QueryKey::of::<String>("report.pdf");
+            let fetcher = BoxFetcher::with_context(|ctx: FetchContext| async move {
+                ctx.report_progress(Progress::new(512, Some(1024)));
+                ctx.report_progress(Progress::new(1024, Some(1024)));
+                Ok::<_, Infallible>("report".to_owned())
+            });
+
+            client
+                .fetch_query_with_options_and_observe(key, fetcher, None, Some(Rc::new(on_change)))
+                .await
+                .unwrap();
+
+            assert_eq!(*percents.borrow(), vec![50.0, 100.0]);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn fetch_from_stream_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .build();
+
+            let partials = Rc::new(RefCell::new(Vec::new()));
+            let on_change = {
+                let partials = partials.clone();
+                move |event: QueryChanged| {
+                    if let (true, Some(value)) = (event.is_fetching, event.value) {
+                        let value = value.downcast::<Vec<u32>>().unwrap();
+                        partials.borrow_mut().push(value.len());
+                    }
+                }
+            };
+
+            let key = QueryKey::of::<Vec<u32>>("numbers");
+            let fetcher = BoxFetcher::from_stream(
+                |_| futures::stream::iter(vec![Ok::<_, Infallible>(1_u32), Ok(2), Ok(3)]),
+                Vec::new,
+                |mut acc, x| {
+                    acc.push(x);
+                    acc
+                },
+            );
+
+            let ret = client
+                .fetch_query_with_options_and_observe(key, fetcher, None, Some(Rc::new(on_change)))
+                .await
+                .unwrap();
+
+            assert_eq!(*ret, vec![1, 2, 3]);
+            assert_eq!(*partials.borrow(), vec![1, 2, 3]);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn report_failed_query_test() {
+        run_local(async {
+            let reports = Rc::new(RefCell::new(Vec::new()));
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .retry(|| std::iter::repeat(Duration::from_millis(10)).take(2))
+                .meta("app", "store")
+                .error_reporter({
+                    let reports = reports.clone();
+                    move |err: &Error, ctx: &ErrorContext| {
+                        reports.borrow_mut().push((err.to_string(), ctx.clone()));
+                    }
+                })
+                .build();
+
+            let key = QueryKey::of::<String>("missing");
+            let options = QueryOptions::new().meta("endpoint", "/items");
+            let ret = client
+                .fetch_query_with_options(
+                    key.clone(),
+                    || async { Err::<String, _>(QueryError::NotReady) },
+                    Some(&options),
+                )
+                .await;
+
+            assert!(ret.is_err());
+
+            let reports = reports.borrow();
+            assert_eq!(reports.len(), 1);
+
+            let (err, ctx) = &reports[0];
+            assert_eq!(err, &QueryError::NotReady.to_string());
+            assert_eq!(ctx.key, key);
+            assert_eq!(ctx.attempts, 3);
+            assert_eq!(ctx.meta.get("app"), Some(&"store".into()));
+            assert_eq!(ctx.meta.get("endpoint"), Some(&"/items".into()));
+            assert!(ctx.duration >= Duration::from_millis(20));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn fetch_with_context_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .retry(|| std::iter::repeat(Duration::from_millis(10)).take(2))
+                .meta("resource", "users")
+                .build();
+
+            let key = QueryKey::of::<String>("user/2");
+            let fetcher = BoxFetcher::with_context(|ctx: FetchContext| async move {
+                assert_eq!(ctx.meta.get("resource"), Some(&"users".into()));
+                assert!(ctx.page_param.is_none());
+
+                if ctx.attempt < 2 {
+                    return Err(QueryError::NotReady);
+                }
+
+                Ok(format!("{} (attempt {})", ctx.key, ctx.attempt))
+            });
+
+            let ret = client.fetch_query(key, fetcher).await.unwrap();
+            assert_eq!(ret.as_str(), "user/2 (attempt 2)");
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn fetch_with_middlewares_test() {
+        run_local(async {
+            let calls = Rc::new(RefCell::new(Vec::new()));
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .middleware({
+                    let calls = calls.clone();
+                    move |ctx: FetchContext, next: Next| {
+                        calls.borrow_mut().push(format!("log {}", ctx.key));
+                        next.run(ctx)
+                    }
+                })
+                .middleware({
+                    let calls = calls.clone();
+                    move |ctx: FetchContext, next: Next| {
+                        calls.borrow_mut().push("upper".to_owned());
+                        async move {
+                            let value = next.run(ctx).await?;
+                            let value = value.downcast::<String>().unwrap();
+                            Ok(Box::new(value.to_uppercase()) as FetchOutput)
+                        }
+                    }
+                })
+                .build();
+
+            let key = QueryKey::of::<String>("greeting");
+            let ret = client
+                .fetch_query(key, || async { Ok::<_, Infallible>("hello".to_owned()) })
+                .await
+                .unwrap();
+
+            assert_eq!(ret.as_str(), "HELLO");
+            assert_eq!(*calls.borrow(), vec!["log greeting", "upper"]);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn auth_refresh_test() {
+        #[derive(Debug)]
+        struct Unauthorized;
+        impl std::fmt::Display for Unauthorized {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "unauthorized")
+            }
+        }
+        impl std::error::Error for Unauthorized {}
+
+        run_local(async {
+            let token = Rc::new(Cell::new(0));
+            let refreshes = Rc::new(Cell::new(0));
+            let client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .middleware(AuthRefresh::new(|err: &Error| err.is::<Unauthorized>(), {
+                    let token = token.clone();
+                    let refreshes = refreshes.clone();
+                    move || {
+                        let token = token.clone();
+                        let refreshes = refreshes.clone();
+                        async move {
+                            refreshes.set(refreshes.get() + 1);
+                            tokio::time::sleep(Duration::from_millis(20)).await;
+                            token.set(1);
+                            Ok::<_, Infallible>(())
+                        }
+                    }
+                }))
+                .build();
+
+            let fetch = |name: &'static str| {
+                let mut client = client.clone();
+                let token = token.clone();
+                async move {
+                    client
+                        .fetch_query(QueryKey::of::<String>(name), move || {
+                            let token = token.get();
+                            async move {
+                                if token == 0 {
+                                    return Err(Unauthorized);
+                                }
+
+                                Ok(name.to_owned())
+                            }
+                        })
+                        .await
+                }
+            };
+
+            let (a, b) = futures::join!(fetch("a"), fetch("b"));
+            assert_eq!(a.unwrap().as_str(), "a");
+            assert_eq!(b.unwrap().as_str(), "b");
+            assert_eq!(refreshes.get(), 1);
+
+            // A fetch started before the refresh completed, fails after it and reuses it
+            token.set(0);
+            let slow = {
+                let mut client = client.clone();
+                let token = token.clone();
+                async move {
+                    client
+                        .fetch_query(QueryKey::of::<String>("slow"), move || {
+                            let token = token.get();
+                            async move {
+                                tokio::time::sleep(Duration::from_millis(50)).await;
+                                if token == 0 {
+                                    return Err(Unauthorized);
+                                }
+
+                                Ok("slow".to_owned())
+                            }
+                        })
+                        .await
+                }
+            };
+
+            let (c, slow) = futures::join!(fetch("c"), slow);
+            assert_eq!(c.unwrap().as_str(), "c");
+            assert_eq!(slow.unwrap().as_str(), "slow");
+            assert_eq!(refreshes.get(), 2);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn fetch_query_timeout_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .timeout(Duration::from_millis(50))
+                .build();
+
+            let key = QueryKey::of::<String>("slow");
+            let err = client
+                .fetch_query(key.clone(), || async {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    Ok::<_, Infallible>("turtle".to_owned())
+                })
+                .await
+                .unwrap_err();
+
+            assert!(err.is_timeout());
+            assert!(!err.is_cancelled());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn cancel_query_test() {
+        run_local(async {
+            let client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .build();
+
+            let key = QueryKey::of::<String>("slow");
+            let task = tokio::task::spawn_local({
+                let mut client = client.clone();
+                let key = key.clone();
+                async move {
+                    client
+                        .fetch_query(key, || async {
+                            tokio::time::sleep(Duration::from_millis(500)).await;
+                            Ok::<_, Infallible>("turtle".to_owned())
+                        })
+                        .await
+                }
+            });
+
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            assert!(client.cancel_query(&key));
+
+            let err = task.await.unwrap().unwrap_err();
+            assert!(err.is_cancelled());
+            assert!(client.get_query_state(&key).unwrap().is_idle());
+            assert!(!client.cancel_query(&QueryKey::of::<String>("other")));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn new_query_is_not_fetching_test() {
+        run_local(async {
+            let key = QueryKey::of::<String>("planet");
+            let fetch = || async { Ok::<_, Infallible>("jupiter".to_owned()) };
+            let query = Query::new(key.clone(), fetch, QueryOptions::new(), None);
+            assert!(!query.is_fetching());
+
+            let options = QueryOptions::new().initial_data("neptune".to_owned());
+            let query = Query::new(key.clone(), fetch, options, None);
+            query.set_initial_data::<String>().unwrap();
+            assert!(!query.is_fetching());
+
+            // The restored queries are not fetching either
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+            client.fetch_query(key.clone(), fetch).await.unwrap();
+
+            let snapshot = client.snapshot();
+            client.clear_queries();
+            client.restore(&snapshot);
+            assert!(!client.get_query(&key).unwrap().is_fetching());
+            assert_eq!(client.trim(0), 1);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn evicted_queries_are_removed_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache(LruCache::new(1))
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let removed = Rc::new(RefCell::new(Vec::new()));
+            client.add_cache_listener({
+                let removed = removed.clone();
+                move |event: &QueryCacheEvent| {
+                    if let QueryCacheEvent::Removed(key) = event {
+                        removed.borrow_mut().push(key.clone());
+                    }
+                }
+            });
+
+            let fetch = || async { Ok::<_, Infallible>(1_i32) };
+            let first = QueryKey::of::<i32>("first");
+            client.fetch_query(first.clone(), fetch).await.unwrap();
+            client
+                .fetch_query(QueryKey::of::<i32>("second"), fetch)
+                .await
+                .unwrap();
+
+            assert!(!client.contains_query(&first));
+            assert_eq!(*removed.borrow(), vec![first]);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn watch_query_test() {
+        run_local(async {
+            let client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .build();
+
+            let key = QueryKey::of::<String>("planet");
+            let mut watcher = client.watch_query::<String>(key.clone());
+
+            tokio::task::spawn_local({
+                let mut client = client.clone();
+                let key = key.clone();
+                async move {
+                    client
+                        .fetch_query(key, || async {
+                            tokio::time::sleep(Duration::from_millis(20)).await;
+                            Ok::<_, Infallible>("saturn".to_owned())
+                        })
+                        .await
+                }
+            });
+
+            let event = watcher.next().await.unwrap();
+            assert!(event.state.is_loading());
+            assert!(event.value.is_none());
+
+            let event = watcher.next().await.unwrap();
+            assert!(event.state.is_ready());
+            assert!(!event.is_fetching);
+            assert_eq!(event.value.unwrap().as_str(), "saturn");
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn watch_removed_query_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let key = QueryKey::of::<String>("planet");
+            let mut watcher = client.watch_query::<String>(key.clone());
+
+            client
+                .fetch_query(key.clone(), || async { Ok::<_, Infallible>("earth".to_owned()) })
+                .await
+                .unwrap();
+
+            let event = watcher.next().await.unwrap();
+            assert_eq!(event.value.unwrap().as_str(), "earth");
+
+            // The watcher follows the new query after the key is removed and fetched again
+            assert!(client.remove_query_data(&key));
+            client
+                .fetch_query(key.clone(), || async { Ok::<_, Infallible>("mars".to_owned()) })
+                .await
+                .unwrap();
+
+            let event = tokio::time::timeout(Duration::from_millis(100), watcher.next())
+                .await
+                .expect("the watcher didn't follow the new query")
+                .unwrap();
+            assert!(event.state.is_ready());
+            assert_eq!(event.value.unwrap().as_str(), "mars");
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn invalidate_and_refetch_queries_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let (counter, fetcher) = counting_fetcher(Duration::ZERO, |n| n as i32);
+
+            let todos = QueryKey::of::<i32>("todos/1");
+            let users = QueryKey::of::<i32>("users/1");
+            client
+                .fetch_query(todos.clone(), fetcher.clone())
+                .await
+                .unwrap();
+            client
+                .fetch_query(users.clone(), fetcher.clone())
+                .await
+                .unwrap();
+            assert_eq!(counter.get(), 2);
+
+            assert_eq!(client.invalidate_queries("todos"), 1);
+            assert!(client.is_stale(&todos));
+            assert!(!client.is_stale(&users));
+
+            let value = client
+                .fetch_query(todos.clone(), fetcher.clone())
+                .await
+                .unwrap();
+            assert_eq!(*value, 3);
+            assert!(!client.is_stale(&todos));
+
+            assert_eq!(client.refetch_queries(QueryFilter::all()).await, 2);
+            assert_eq!(counter.get(), 5);
+
+            let refetched = client
+                .refetch_queries(QueryFilter::predicate(|key| key.key().ends_with("/1")))
+                .await;
+            assert_eq!(refetched, 2);
+            assert_eq!(counter.get(), 7);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn handle_invalidation_message_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let (counter, fetcher) = counting_fetcher(Duration::ZERO, |n| n as i32);
+
+            let observed = QueryKey::of::<i32>("todos/1");
+            let unobserved = QueryKey::of::<i32>("todos/2");
+            client
+                .fetch_query_with_options_and_observe(
+                    observed.clone(),
+                    fetcher.clone(),
+                    None,
+                    Some(Rc::new(|_| {})),
+                )
+                .await
+                .unwrap();
+            client
+                .fetch_query(unobserved.clone(), fetcher.clone())
+                .await
+                .unwrap();
+            assert_eq!(counter.get(), 2);
+
+            let message = InvalidationMessage::entity("todos");
+            assert_eq!(client.handle_invalidation_message(message), 2);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+
+            // Only the observed query is refetched
+            assert_eq!(counter.get(), 3);
+            assert!(!client.is_stale(&observed));
+            assert!(client.is_stale(&unobserved));
+            assert_eq!(*client.get_query_data::<i32>(&observed).unwrap(), 3);
+
+            let message = InvalidationMessage::entity_id("users", 1);
+            assert_eq!(client.handle_invalidation_message(message), 0);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn apply_update_with_reducer_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let key = QueryKey::of::<Vec<String>>("messages");
+            client.set_query_reducer(key.clone(), |messages: &Vec<String>, message: String| {
+                let mut messages = messages.clone();
+                messages.push(message);
+                messages
+            });
+
+            let err = client.apply_update(&key, "early".to_owned()).unwrap_err();
+            assert!(matches!(err, QueryError::KeyNotFound(_)));
+
+            client
+                .fetch_query(key.clone(), || async {
+                    Ok::<_, Infallible>(vec!["hello".to_owned()])
+                })
+                .await
+                .unwrap();
+
+            client.apply_update(&key, "world".to_owned()).unwrap();
+            let messages = client.get_query_data::<Vec<String>>(&key).unwrap();
+            assert_eq!(*messages, vec!["hello".to_owned(), "world".to_owned()]);
+
+            let err = client.apply_update(&key, 10_i32).unwrap_err();
+            assert!(matches!(err, QueryError::TypeMismatch(_)));
+
+            // A fetch replaces the value
+            client
+                .refetch_query::<Vec<String>>(key.clone())
+                .await
+                .unwrap();
+            let messages = client.get_query_data::<Vec<String>>(&key).unwrap();
+            assert_eq!(*messages, vec!["hello".to_owned()]);
+
+            assert!(client.remove_query_reducer(&key));
+            assert!(client.apply_update(&key, "again".to_owned()).is_err());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn sweep_ttl_cache_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache(
+                    TtlCache::new(Duration::from_millis(100))
+                        .sweep_interval(Duration::from_millis(20)),
+                )
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let key = QueryKey::of::<String>("ephemeral");
+            client
+                .fetch_query(key.clone(), || async {
+                    Ok::<_, Infallible>("mayfly".to_owned())
+                })
+                .await
+                .unwrap();
+
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            assert!(client.contains_query(&key));
+
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            assert!(!client.contains_query(&key));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn cache_listener_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let events = Rc::new(RefCell::new(Vec::new()));
+            let id = client.add_cache_listener({
+                let events = events.clone();
+                move |event: &QueryCacheEvent| {
+                    let event = match event {
+                        QueryCacheEvent::Added(key) => format!("added {key}"),
+                        QueryCacheEvent::Updated(key, change) => {
+                            format!("updated {key} {:?}", change.state)
+                        }
+                        QueryCacheEvent::Removed(key) => format!("removed {key}"),
+                        QueryCacheEvent::Cleared => "cleared".to_owned(),
+                    };
+
+                    events.borrow_mut().push(event);
+                }
+            });
+
+            let key = QueryKey::of::<String>("color");
+            client
+                .fetch_query(key.clone(), || async {
+                    Ok::<_, Infallible>("blue".to_owned())
+                })
+                .await
+                .unwrap();
+
+            client
+                .set_query_data(key.clone(), "red".to_owned())
+                .unwrap();
+            client.remove_query_data(&key);
+            client.clear_queries();
+
+            assert_eq!(
+                *events.borrow(),
+                vec![
+                    "added color",
+                    "updated color Loading",
+                    "updated color Loading",
+                    "updated color Ready",
+                    "updated color Ready",
+                    "removed color",
+                    "cleared",
+                ]
+            );
+
+            assert!(client.remove_cache_listener(id));
+            client.clear_queries();
+            assert_eq!(events.borrow().len(), 7);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn cache_listener_reads_client_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let key = QueryKey::of::<String>("color");
+            let updates = Rc::new(RefCell::new(Vec::new()));
+            client.add_cache_listener({
+                let client = client.clone();
+                let updates = updates.clone();
+                move |event: &QueryCacheEvent| {
+                    if let QueryCacheEvent::Updated(key, _) = event {
+                        assert!(client.contains_query(key));
+                        let value = client.get_query_data::<String>(key).ok();
+                        updates.borrow_mut().push(value.map(|x| x.to_string()));
+                    }
+                }
+            });
+
+            client
+                .fetch_query(key.clone(), || async {
+                    Ok::<_, Infallible>("blue".to_owned())
+                })
+                .await
+                .unwrap();
+            updates.borrow_mut().clear();
+
+            // The listeners can use the client while the cached value is set
+            client
+                .set_query_data(key.clone(), "red".to_owned())
+                .unwrap();
+            client.route_query_data([(key.clone(), "green".to_owned())]);
+            client
+                .set_query_data_with(key.clone(), |color: &mut String| color.push('!'))
+                .unwrap();
+
+            assert_eq!(
+                *updates.borrow(),
+                vec![
+                    Some("red".to_owned()),
+                    Some("green".to_owned()),
+                    Some("green!".to_owned())
+                ]
+            );
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn trim_queries_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let fetcher = || async { Ok::<_, Infallible>(1_i32) };
+            let observed = QueryKey::of::<i32>("observed");
+            client
+                .fetch_query_with_options_and_observe(
+                    observed.clone(),
+                    fetcher,
+                    None,
+                    Some(Rc::new(|_| {})),
+                )
+                .await
+                .unwrap();
+
+            for key in ["oldest", "older", "newest"] {
+                client
+                    .fetch_query(QueryKey::of::<i32>(key), fetcher)
+                    .await
+                    .unwrap();
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+
+            assert_eq!(client.trim(4), 0);
+            assert_eq!(client.trim(2), 2);
+            assert!(!client.contains_query(&QueryKey::of::<i32>("oldest")));
+            assert!(!client.contains_query(&QueryKey::of::<i32>("older")));
+            assert!(client.contains_query(&QueryKey::of::<i32>("newest")));
+
+            // Observed queries are never removed
+            assert_eq!(client.trim(0), 1);
+            assert!(client.contains_query(&observed));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn clear_partition_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let user = QueryOptions::new().partition("user");
+            let fetcher = || async { Ok::<_, Infallible>(1_i32) };
+
+            let profile = QueryKey::of::<i32>("profile");
+            let cart = QueryKey::of::<i32>("cart");
+            let config = QueryKey::of::<i32>("config");
+            client
+                .fetch_query_with_options(profile.clone(), fetcher, Some(&user))
+                .await
+                .unwrap();
+            client
+                .fetch_query_with_options(cart.clone(), fetcher, Some(&user))
+                .await
+                .unwrap();
+            client.fetch_query(config.clone(), fetcher).await.unwrap();
+
+            assert_eq!(client.clear_partition("guest"), 0);
+            assert_eq!(client.clear_partition("user"), 2);
+            assert!(!client.contains_query(&profile));
+            assert!(!client.contains_query(&cart));
+            assert!(client.contains_query(&config));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn snapshot_and_restore_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let (counter, fetcher) = counting_fetcher(Duration::ZERO, |n| n as i32);
+
+            let a = QueryKey::of::<i32>("a");
+            let b = QueryKey::of::<i32>("b");
+            client
+                .fetch_query(a.clone(), fetcher.clone())
+                .await
+                .unwrap();
+            client
+                .fetch_query(b.clone(), fetcher.clone())
+                .await
+                .unwrap();
+
+            let snapshot = client.snapshot();
+            assert_eq!(snapshot.len(), 2);
+            assert_eq!(*snapshot.get(&a).unwrap().value::<i32>().unwrap(), 1);
+
+            client.set_query_data(a.clone(), 10).unwrap();
+            client.remove_query_data(&b);
+            let c = QueryKey::of::<i32>("c");
+            client
+                .fetch_query(c.clone(), fetcher.clone())
+                .await
+                .unwrap();
+
+            let mut diff = snapshot.diff(&client.snapshot());
+            diff.sort_by_key(|x| format!("{x:?}"));
+            assert_eq!(
+                diff,
+                vec![
+                    SnapshotDiff::Added(c.clone()),
+                    SnapshotDiff::Changed(a.clone()),
+                    SnapshotDiff::Removed(b.clone()),
+                ]
+            );
+
+            client.restore(&snapshot);
+            assert!(snapshot.diff(&client.snapshot()).is_empty());
+            assert!(!client.contains_query(&c));
+            assert_eq!(*client.get_query_data::<i32>(&a).unwrap(), 1);
+            assert_eq!(*client.get_query_data::<i32>(&b).unwrap(), 2);
+
+            // The fetchers are not executed on restore
+            assert_eq!(counter.get(), 3);
+            let value = client.refetch_query::<i32>(b.clone()).await.unwrap();
+            assert_eq!(*value, 4);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn restore_query_without_value_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let key = QueryKey::of::<String>("color");
+            let ret = client
+                .fetch_query(key.clone(), || async {
+                    Err::<String, _>(QueryError::NotReady)
+                })
+                .await;
+            assert!(ret.is_err());
+
+            let snapshot = client.snapshot();
+            assert!(snapshot.get(&key).unwrap().value::<String>().is_none());
+
+            client
+                .set_query_data(key.clone(), "red".to_owned())
+                .unwrap();
+            client.restore(&snapshot);
+
+            assert!(client.get_query_state(&key).unwrap().is_failed());
+            assert!(client.get_query_data::<String>(&key).is_err());
+            assert!(client.get_query(&key).unwrap().last_value().is_none());
+
+            let query = client.get_query(&key).unwrap().clone();
+            assert!(query.future::<String>().await.is_err());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn shared_cache_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let key = QueryKey::of::<String>("session");
+            client
+                .fetch_query(key.clone(), || async {
+                    Ok::<_, Infallible>("guest".to_owned())
+                })
+                .await
+                .unwrap();
+
+            let mut rebuilt = QueryClient::builder()
+                .shared_cache(&client)
+                .cache_time(Duration::from_secs(10))
+                .meta("user", "admin")
+                .build();
+
+            // The cache listeners and watchers of the clients see the queries of the other
+            let added = Rc::new(RefCell::new(Vec::new()));
+            rebuilt.add_cache_listener({
+                let added = added.clone();
+                move |event: &QueryCacheEvent| {
+                    if let QueryCacheEvent::Added(key) = event {
+                        added.borrow_mut().push(key.clone());
+                    }
+                }
+            });
+
+            let user = QueryKey::of::<String>("user");
+            let mut watcher = rebuilt.watch_query::<String>(user.clone());
+            client
+                .fetch_query(user.clone(), || async { Ok::<_, Infallible>("ada".to_owned()) })
+                .await
+                .unwrap();
+
+            assert_eq!(*added.borrow(), vec![user]);
+            let event = watcher.next().await.unwrap();
+            assert_eq!(event.value.unwrap().as_str(), "ada");
+
+            let value = rebuilt
+                .fetch_query(key.clone(), || async {
+                    Ok::<_, Infallible>("admin".to_owned())
+                })
+                .await
+                .unwrap();
+            assert_eq!(value.as_str(), "guest");
+
+            rebuilt.remove_query_data(&key);
+            assert!(!client.contains_query(&key));
+
+            // A shared cache is swept by a single task
+            let ttl = QueryClient::builder()
+                .cache(TtlCache::new(Duration::from_secs(10)))
+                .build();
+            let other = QueryClient::builder().shared_cache(&ttl).build();
+            assert!(Rc::ptr_eq(
+                ttl.sweeper.as_ref().unwrap(),
+                other.sweeper.as_ref().unwrap()
+            ));
+        })
+        .await
+    }
+
+    #[cfg(debug_assertions)]
+    #[tokio::test]
+    #[should_panic(expected = "was created with the fetcher")]
+    async fn duplicate_fetcher_test() {
+        async fn fetch_number() -> Result<i32, Infallible> {
+            Ok(1)
+        }
+
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .duplicate_fetcher(DuplicateFetcher::Panic)
+                .build();
+
+            let key = QueryKey::of::<i32>("number");
+            client.fetch_query(key.clone(), fetch_number).await.unwrap();
+
+            // The same fetcher can be used again
+            client.fetch_query(key.clone(), fetch_number).await.unwrap();
+
+            client
+                .fetch_query(key, || async { Ok::<_, Infallible>(2) })
+                .await
+                .unwrap();
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn key_prefix_test() {
+        run_local(async {
+            let mut tenant_a = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .key_prefix("tenant-a")
+                .build();
+
+            let mut tenant_b = QueryClient::builder()
+                .shared_cache(&tenant_a)
+                .cache_time(Duration::from_secs(10))
+                .key_prefix("tenant-b")
+                .build();
+
+            let key = QueryKey::of::<String>("user");
+            tenant_a
+                .fetch_query(key.clone(), || async { Ok::<_, Infallible>("alice".to_owned()) })
+                .await
+                .unwrap();
+
+            let value = tenant_b
+                .fetch_query(key.clone(), || async { Ok::<_, Infallible>("bob".to_owned()) })
+                .await
+                .unwrap();
+            assert_eq!(value.as_str(), "bob");
+
+            let a_key = QueryKey::of::<String>("tenant-a/user");
+            assert!(tenant_a.cache().borrow().has(&a_key));
+            assert_eq!(tenant_a.get_query(&key).unwrap().key(), &a_key);
+
+            // The keys given to the client are always prefixed, even if start with the prefix
+            let shared = QueryClient::builder()
+                .shared_cache(&tenant_a)
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            assert!(shared.contains_query(&a_key));
+            assert!(!tenant_a.contains_query(&a_key));
+            tenant_a
+                .fetch_query(a_key.clone(), || async { Ok::<_, Infallible>("eve".to_owned()) })
+                .await
+                .unwrap();
+
+            assert_eq!(shared.get_query_data::<String>(&a_key).unwrap().as_str(), "alice");
+            assert_eq!(tenant_a.get_query_data::<String>(&a_key).unwrap().as_str(), "eve");
+            assert!(shared.contains_query(&QueryKey::of::<String>("tenant-a/tenant-a/user")));
+            tenant_a.remove_query_data(&a_key);
+
+            // The filters only match the queries of the client
+            assert_eq!(tenant_b.invalidate_queries("user"), 1);
+            assert!(tenant_a.has_query_data(&key));
+            assert!(!tenant_b.has_query_data(&key));
+
+            // The partitions and trims only remove the queries of the client
+            let options = QueryOptions::new().partition("users");
+            for (client, name) in [(&mut tenant_a, "carol"), (&mut tenant_b, "dave")] {
+                client
+                    .fetch_query_with_options(
+                        QueryKey::of::<String>("friend"),
+                        move || async move { Ok::<_, Infallible>(name.to_owned()) },
+                        Some(&options),
+                    )
+                    .await
+                    .unwrap();
+            }
+
+            assert_eq!(tenant_b.clear_partition("users"), 1);
+            assert!(tenant_a.contains_query(&QueryKey::of::<String>("friend")));
+
+            assert_eq!(tenant_b.trim(0), 1);
+            assert!(tenant_a.contains_query(&key));
+            assert_eq!(tenant_a.cache().borrow().len(), 2);
+
+            // The snapshots and restores only include the queries of the client
+            tenant_b
+                .fetch_query(key.clone(), || async { Ok::<_, Infallible>("bob".to_owned()) })
+                .await
+                .unwrap();
+
+            let snapshot = tenant_b.snapshot();
+            assert_eq!(snapshot.len(), 1);
+            assert!(snapshot.get(&QueryKey::of::<String>("tenant-b/user")).is_some());
+
+            tenant_b.restore(&CacheSnapshot::default());
+            assert!(tenant_a.contains_query(&key));
+            assert!(!tenant_b.contains_query(&key));
+
+            tenant_b.restore(&snapshot);
+            assert!(tenant_a.contains_query(&key));
+            assert!(tenant_b.contains_query(&key));
+
+            tenant_b.clear_queries();
+            assert!(tenant_a.contains_query(&key));
+            assert!(!tenant_b.contains_query(&key));
+
+            // The watchers use the keys of the client
+            let mut watcher = tenant_b.watch_query::<String>(key.clone());
+            tenant_b
+                .fetch_query(key.clone(), || async { Ok::<_, Infallible>("bob".to_owned()) })
+                .await
+                .unwrap();
+
+            let event = watcher.next().await.unwrap();
+            assert_eq!(event.value.unwrap().as_str(), "bob");
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn multiple_observers_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let observe = |counter: Rc<Cell<usize>>| {
+                let observer = QueryObserver::<String>::new(client.clone(), "color".into());
+                observer.observe(
+                    ObserveTarget::Fetch,
+                    || async { Ok::<_, Infallible>("blue".to_owned()) },
+                    move |_| counter.set(counter.get() + 1),
+                );
+                observer
+            };
+
+            let first = Rc::new(Cell::new(0));
+            let second = Rc::new(Cell::new(0));
+            let first_observer = observe(first.clone());
+            let second_observer = observe(second.clone());
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            let key = QueryKey::of::<String>("color");
+            let (before_first, before_second) = (first.get(), second.get());
+            client.set_query_data(key.clone(), "red".to_owned()).unwrap();
+            assert_eq!(first.get(), before_first + 1);
+            assert_eq!(second.get(), before_second + 1);
+
+            assert_eq!(client.observer_count(&key), 2);
+
+            // Dropping an observer removes its subscription
+            drop(second_observer);
+            assert_eq!(client.observer_count(&key), 1);
+            client.set_query_data(key, "green".to_owned()).unwrap();
+            assert_eq!(first.get(), before_first + 2);
+            assert_eq!(second.get(), before_second + 1);
+
+            drop(first_observer);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn structural_sharing_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let (counter, fetcher) = counting_fetcher(Duration::ZERO, |_| vec![1, 2, 3]);
+
+            let key = QueryKey::of::<Vec<i32>>("numbers");
+            let options = QueryOptions::new().structural_sharing::<Vec<i32>>();
+            let first = client
+                .fetch_query_with_options(key.clone(), fetcher, Some(&options))
+                .await
+                .unwrap();
+
+            let events = Rc::new(RefCell::new(Vec::new()));
+            let _subscription = client.get_query(&key).unwrap().subscribe({
+                let events = events.clone();
+                move |event: QueryChanged| events.borrow_mut().push(event.is_fetching)
+            });
+
+            let second = client.refetch_query::<Vec<i32>>(key.clone()).await.unwrap();
+
+            assert_eq!(counter.get(), 2);
+            assert!(Rc::ptr_eq(&first, &second));
+            assert!(Rc::ptr_eq(
+                &first,
+                &client.get_query_data::<Vec<i32>>(&key).unwrap()
+            ));
+            assert_eq!(*events.borrow(), vec![true, false]);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn disabled_observer_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let observe = |counter: Rc<Cell<usize>>| {
+                let observer = QueryObserver::<String>::new(client.clone(), "color".into());
+                observer.observe(
+                    ObserveTarget::Fetch,
+                    || async { Ok::<_, Infallible>("blue".to_owned()) },
+                    move |_| counter.set(counter.get() + 1),
+                );
+                observer
+            };
+
+            let enabled = Rc::new(Cell::new(0));
+            let disabled = Rc::new(Cell::new(0));
+            let enabled_observer = observe(enabled.clone());
+            let disabled_observer = observe(disabled.clone());
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            let key = QueryKey::of::<String>("color");
+            let (before_enabled, before_disabled) = (enabled.get(), disabled.get());
+            disabled_observer.set_enabled(false);
+            client
+                .set_query_data(key.clone(), "red".to_owned())
+                .unwrap();
+
+            assert_eq!(enabled.get(), before_enabled + 1);
+            assert_eq!(disabled.get(), before_disabled);
+            assert_eq!(client.observer_count(&key), 2);
+            assert!(client.get_query(&key).unwrap().is_enabled());
+
+            enabled_observer.set_enabled(false);
+            assert!(!client.get_query(&key).unwrap().is_enabled());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn join_or_fetch_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let (counter, fetcher) = counting_fetcher(Duration::from_millis(50), |n| n as i32);
+
+            // Concurrent callers share the fetch in progress
+            let key = QueryKey::of::<i32>("number");
+            let (mut first, mut second) = (client.clone(), client.clone());
+            let (a, b, c) = futures::join!(
+                first.fetch_query(key.clone(), fetcher.clone()),
+                second.join_or_fetch(key.clone(), fetcher.clone()),
+                client.join_or_fetch(key.clone(), fetcher.clone()),
+            );
+
+            assert_eq!(counter.get(), 1);
+            assert_eq!(*a.unwrap(), 1);
+            assert_eq!(*b.unwrap(), 1);
+            assert_eq!(*c.unwrap(), 1);
+
+            // A fresh value is not reused
+            let value = client.join_or_fetch(key, fetcher).await.unwrap();
+            assert_eq!(*value, 2);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn join_or_fetch_without_cache_time_test() {
+        run_local(async {
+            let client = QueryClient::builder().build();
+            let (counter, fetcher) = counting_fetcher(Duration::from_millis(50), |n| n as i32);
+
+            // The callers share the fetch in progress, even if the value is not cached
+            let key = QueryKey::of::<i32>("number");
+            let (mut first, mut second) = (client.clone(), client.clone());
+            let (a, b) = futures::join!(
+                first.join_or_fetch(key.clone(), fetcher.clone()),
+                second.join_or_fetch(key.clone(), fetcher.clone()),
+            );
+
+            assert_eq!(counter.get(), 1);
+            assert_eq!(*a.unwrap(), 1);
+            assert_eq!(*b.unwrap(), 1);
+            assert!(!client.contains_query(&key));
+
+            // The next call starts a new fetch
+            let value = first.join_or_fetch(key, fetcher).await.unwrap();
+            assert_eq!(*value, 2);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn query_changed_cause_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let events = Rc::new(RefCell::new(Vec::new()));
+            let observer = QueryObserver::<String>::new(client.clone(), "color".into());
+            observer.observe(
+                ObserveTarget::Fetch,
+                || async { Ok::<_, Infallible>("blue".to_owned()) },
+                {
+                    let events = events.clone();
+                    move |event| events.borrow_mut().push((event.cause, event.timestamp))
+                },
+            );
+
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let key = QueryKey::of::<String>("color");
+            client
+                .set_query_data(key.clone(), "red".to_owned())
+                .unwrap();
+
+            let events = events.borrow();
+            let causes = events.iter().map(|(cause, _)| *cause).collect::<Vec<_>>();
+            assert_eq!(causes.first(), Some(&None));
+            assert!(causes[1..causes.len() - 1]
+                .iter()
+                .all(|x| *x == Some(FetchCause::Mount)));
+            assert_eq!(causes.last(), Some(&None));
+            assert!(events.windows(2).all(|x| x[0].1 <= x[1].1));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn failed_queries_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let todos = QueryKey::of::<String>("todos/1");
+            let users = QueryKey::of::<String>("users/1");
+            client
+                .fetch_query(todos.clone(), || async {
+                    Err::<String, _>(QueryError::NotReady)
+                })
+                .await
+                .unwrap_err();
+            client
+                .fetch_query(users.clone(), || async {
+                    Ok::<_, Infallible>("user".to_owned())
+                })
+                .await
+                .unwrap();
+
+            let failed = client.failed_queries(QueryFilter::all());
+            assert_eq!(failed.len(), 1);
+            assert_eq!(failed[0].0, todos);
+            assert_eq!(failed[0].1.to_string(), QueryError::NotReady.to_string());
+            assert!(client.failed_queries("users").is_empty());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn fetching_count_test() {
+        run_local(async {
+            let client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let fetcher = || async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Ok::<_, Infallible>(1)
+            };
+
+            let (mut todos, mut users) = (client.clone(), client.clone());
+            let fetch_todos = todos.fetch_query(QueryKey::of::<i32>("todos/1"), fetcher);
+            let fetch_users = users.fetch_query(QueryKey::of::<i32>("users/1"), fetcher);
+            let check = async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                (client.fetching_count(QueryFilter::all()), client.fetching_count("users"))
+            };
+
+            let (_, _, (all, users)) = futures::join!(fetch_todos, fetch_users, check);
+            assert_eq!(all, 2);
+            assert_eq!(users, 1);
+            assert_eq!(client.fetching_count(QueryFilter::all()), 0);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn reset_query_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let key = QueryKey::of::<String>("todos/1");
+            client
+                .fetch_query(key.clone(), || async {
+                    Err::<String, _>(QueryError::NotReady)
+                })
+                .await
+                .unwrap_err();
+
+            assert!(client.reset_query(&key));
+            let mut query = client.get_query(&key).map(|q| q.clone()).unwrap();
+            assert!(matches!(query.state(), QueryState::Idle));
+            assert_eq!(query.error_updated_at(), None);
+            assert!(!query.is_fetching());
+            assert!(client.failed_queries(QueryFilter::all()).is_empty());
+
+            // The entry is kept and can be fetched again
+            let err = query.fetch_value().await.unwrap_err();
+            assert_eq!(err.to_string(), QueryError::NotReady.to_string());
+            assert!(!client.reset_query(&QueryKey::of::<String>("users/1")));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn refetch_query_with_test() {
+        run_local(async {
+            let client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let (counter, fetcher) = counting_fetcher(Duration::from_millis(50), |n| n as i32);
+
+            // Joins the fetch in progress
+            let key = QueryKey::of::<i32>("number");
+            let (mut first, mut second) = (client.clone(), client.clone());
+            let (a, b) = futures::join!(
+                first.fetch_query(key.clone(), fetcher.clone()),
+                async {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    second.refetch_query_with::<i32>(key.clone(), false).await
+                }
+            );
+
+            assert_eq!(counter.get(), 1);
+            assert_eq!(*a.unwrap(), 1);
+            assert_eq!(*b.unwrap(), 1);
+
+            // Cancels the fetch in progress and starts a new one
+            let (mut first, mut second) = (client.clone(), client.clone());
+            let (a, b) = futures::join!(first.refetch_query::<i32>(key.clone()), async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                second.refetch_query_with::<i32>(key.clone(), true).await
+            });
+
+            assert_eq!(counter.get(), 3);
+            assert!(a.unwrap_err().is_cancelled());
+            assert_eq!(*b.unwrap(), 3);
+
+            let query = client.get_query(&key).map(|q| q.clone()).unwrap();
+            assert!(matches!(query.state(), QueryState::Ready));
+            assert!(!query.is_fetching());
+            assert_eq!(*client.get_query_data::<i32>(&key).unwrap(), 3);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn set_refetch_time_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let (counter, fetcher) = counting_fetcher(Duration::ZERO, |n| n as i32);
+            let key = QueryKey::of::<i32>("number");
+            client.fetch_query(key.clone(), fetcher).await.unwrap();
+
+            // Starts polling
+            assert!(client.set_refetch_time(&key, Some(Duration::from_millis(50))));
+            tokio::time::sleep(Duration::from_millis(130)).await;
+            assert!(counter.get() >= 3);
+
+            // Stops polling
+            assert!(client.set_refetch_time(&key, None));
+            let count = counter.get();
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            assert_eq!(counter.get(), count);
+            assert!(!client.set_refetch_time(&QueryKey::of::<i32>("other"), None));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn stale_at_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let key = QueryKey::of::<i32>("number");
+            assert_eq!(client.stale_at(&key), None);
+
+            client
+                .fetch_query(key.clone(), || async { Ok::<_, Infallible>(1) })
+                .await
+                .unwrap();
+
+            let updated_at = client.get_query(&key).unwrap().data_updated_at().unwrap();
+            assert_eq!(client.stale_at(&key), Some(updated_at + Duration::from_secs(10)));
+
+            // An invalidated value is already stale
+            client.invalidate_queries(QueryFilter::all());
+            assert_eq!(client.stale_at(&key), Some(updated_at));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn initial_data_test() {
+        run_local(async {
+            let mut client = QueryClient::builder().build();
+            let (counter, fetcher) = counting_fetcher(Duration::ZERO, |_| "fetched".to_owned());
+
+            // Fresh initial data is not fetched
+            let key = QueryKey::of::<String>("todos/1");
+            let options = QueryOptions::new()
+                .cache_time(Duration::from_secs(10))
+                .initial_data("initial".to_owned());
+
+            let value = client
+                .fetch_query_with_options(key.clone(), fetcher.clone(), Some(&options))
+                .await
+                .unwrap();
+
+            assert_eq!(*value, "initial");
+            assert_eq!(counter.get(), 0);
+            assert!(client.get_query(&key).unwrap().data_updated_at().is_some());
+
+            // Stale initial data is fetched
+            let key = QueryKey::of::<String>("todos/2");
+            let updated_at = Instant::now() - Duration::from_secs(20);
+            let options = options.initial_data_updated_at(updated_at);
+
+            let value = client
+                .fetch_query_with_options(key.clone(), fetcher.clone(), Some(&options))
+                .await
+                .unwrap();
+
+            assert_eq!(*value, "fetched");
+            assert_eq!(counter.get(), 1);
+
+            // Initial data of other type is ignored
+            let key = QueryKey::of::<String>("todos/3");
+            let options = QueryOptions::new().initial_data(3_u32);
+            let value = client
+                .fetch_query_with_options(key.clone(), fetcher.clone(), Some(&options))
+                .await
+                .unwrap();
+
+            assert_eq!(*value, "fetched");
+            assert_eq!(counter.get(), 2);
+
+            // The observers are notified of the initial data after the query is in the cache
+            let key = QueryKey::of::<String>("todos/4");
+            let options = QueryOptions::new()
+                .cache_time(Duration::from_secs(10))
+                .initial_data("initial".to_owned());
+            let observer = QueryObserver::<String>::with_options(
+                client.clone(),
+                "todos/4".into(),
+                Some(options),
+            );
+
+            let values = Rc::new(RefCell::new(Vec::new()));
+            observer.observe(ObserveTarget::Fetch, fetcher, {
+                let client = client.clone();
+                let values = values.clone();
+                move |event: QueryChangeEvent<String>| {
+                    if let Some(value) = event.value {
+                        assert!(client.get_query(&key).is_some());
+                        values.borrow_mut().push(value);
+                    }
+                }
+            });
+
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            assert_eq!(values.borrow().last().unwrap().as_str(), "initial");
+            assert_eq!(counter.get(), 2);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn query_options_override_client_options_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_millis(10))
+                .build();
+
+            // The options of the query take precedence over the client
+            let defaults = QueryOptions::new().cache_time(Duration::from_secs(10));
+            let options = QueryOptions::new().merge(&defaults);
+            let key = QueryKey::of::<i32>("number");
+            let fetcher = || async { Ok::<_, Infallible>(1) };
+            client
+                .fetch_query_with_options(key.clone(), fetcher, Some(&options))
+                .await
+                .unwrap();
+
+            let other = QueryKey::of::<i32>("other");
+            client
+                .fetch_query(other.clone(), || async { Ok::<_, Infallible>(2) })
+                .await
+                .unwrap();
+
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            assert!(!client.is_stale(&key));
+            assert!(client.is_stale(&other));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn pause_and_resume_test() {
+        run_local(async {
+            let mut client = QueryClient::builder().build();
+            let (counter, fetcher) = counting_fetcher(Duration::ZERO, |n| n as i32);
+
+            let key = QueryKey::of::<i32>("number");
+            let options = QueryOptions::new()
+                .cache_time(Duration::from_millis(20))
+                .refetch_time(Duration::from_millis(50));
+
+            client
+                .fetch_query_with_options(key.clone(), fetcher, Some(&options))
+                .await
+                .unwrap();
+
+            let query = client.get_query(&key).map(|q| q.clone()).unwrap();
+            let _subscription = query.subscribe(|_| {});
+
+            // The interval don't refetch while paused
+            client.pause();
+            assert!(client.is_paused());
+            tokio::time::sleep(Duration::from_millis(120)).await;
+            assert_eq!(counter.get(), 1);
+
+            // The stale query is refetched once on resume
+            client.resume();
+            assert!(!client.is_paused());
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            assert_eq!(counter.get(), 2);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn online_manager_test() {
+        run_local(async {
+            let manager = ManualOnlineManager::new(false);
+            let client = QueryClient::builder()
+                .online_manager(manager.clone())
+                .build();
+
+            assert!(QueryClient::builder().build().online_manager().is_none());
+            assert!(!client.online_manager().unwrap().is_online());
+
+            manager.set_online(true);
+            assert!(client.clone().online_manager().unwrap().is_online());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn focus_manager_test() {
+        run_local(async {
+            let manager = ManualFocusManager::default();
+            let client = QueryClient::builder()
+                .focus_manager(manager.clone())
+                .build();
+
+            assert!(QueryClient::builder().build().focus_manager().is_none());
+            assert!(client.focus_manager().unwrap().is_focused());
+
+            manager.set_focused(false);
+            assert!(!client.focus_manager().unwrap().is_focused());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn custom_spawner_test() {
+        struct CountingSpawner(Rc<Cell<usize>>);
+
+        impl Spawner for CountingSpawner {
+            fn spawn_local(&self, future: LocalBoxFuture<'static, ()>) {
+                self.0.set(self.0.get() + 1);
+                tokio::task::spawn_local(future);
+            }
+
+        }
+
+        run_local(async {
+            let spawned = Rc::new(Cell::new(0));
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(1))
+                .refetch_time(Duration::from_millis(100))
+                .spawner(CountingSpawner(spawned.clone()))
+                .build();
+
+            let (count, fetcher) = counting_fetcher(Duration::ZERO, |n| n);
+            let key = QueryKey::of::<usize>("count");
+            client.fetch_query(key.clone(), fetcher).await.unwrap();
+
+            // The interval and its refetches run in the custom spawner
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            assert!(count.get() > 1);
+            assert!(spawned.get() > 1);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn custom_timer_test() {
+        // Completes the sleeps immediately, recording the durations
+        struct TestTimer(Rc<RefCell<Vec<Duration>>>);
+
+        impl TimerBackend for TestTimer {
+            fn sleep(&self, duration: Duration) -> LocalBoxFuture<'static, ()> {
+                self.0.borrow_mut().push(duration);
+                Box::pin(futures::future::ready(()))
+            }
+        }
+
+        run_local(async {
+            let sleeps = Rc::new(RefCell::new(Vec::new()));
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(1))
+                .retry(|| std::iter::repeat(Duration::from_secs(3600)).take(2))
+                .timer(TestTimer(sleeps.clone()))
+                .build();
+
+            let started_at = Instant::now();
+            let key = QueryKey::of::<String>("failing");
+            let err = client
+                .fetch_query(key, || async { Err::<String, _>(QueryError::NotReady) })
+                .await
+                .unwrap_err();
+
+            assert!(err.is::<QueryError>());
+            assert!(started_at.elapsed() < Duration::from_secs(1));
+            assert_eq!(
+                *sleeps.borrow(),
+                vec![Duration::from_secs(3600), Duration::from_secs(3600)]
+            );
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn removed_query_stops_refetch_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(5))
+                .refetch_time(Duration::from_millis(50))
+                .build();
+
+            let completed = Rc::new(Cell::new(0));
+            let (started, fetcher) = counting_fetcher(Duration::from_millis(100), {
+                let completed = completed.clone();
+                move |_| {
+                    completed.set(completed.get() + 1);
+                    completed.get()
+                }
+            });
+
+            let key = QueryKey::of::<usize>("slow");
+            client.fetch_query(key.clone(), fetcher).await.unwrap();
+
+            // Wait for the refetch of the interval to start
+            tokio::time::sleep(Duration::from_millis(80)).await;
+            assert_eq!(started.get(), 2);
+            assert_eq!(completed.get(), 1);
+
+            // Removing the query aborts the refetch in progress and stops the interval
+            assert!(client.remove_query_data(&key));
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            assert_eq!(started.get(), 2);
+            assert_eq!(completed.get(), 1);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn validate_query_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let attempts = Rc::new(Cell::new(0));
+            let options = QueryOptions::new()
+                .retry(|| std::iter::repeat(Duration::from_millis(1)).take(3))
+                .validate(|age: &i32| match *age {
+                    0.. => Ok(()),
+                    _ => Err(ValidationError::with_field("age", "must be positive")),
+                });
+
+            let key = QueryKey::of::<i32>("age");
+            let err = client
+                .fetch_query_with_options(
+                    key.clone(),
+                    {
+                        let attempts = attempts.clone();
+                        move || {
+                            attempts.set(attempts.get() + 1);
+                            async { Ok::<_, Infallible>(-1) }
+                        }
+                    },
+                    Some(&options),
+                )
+                .await
+                .unwrap_err();
+
+            // The invalid values are not retried
+            assert_eq!(attempts.get(), 1);
+            let validation_error = err.validation_error().unwrap();
+            assert_eq!(validation_error.field(), Some("age"));
+            assert_eq!(validation_error.message(), "must be positive");
+
+            let value = client
+                .fetch_query_with_options(
+                    QueryKey::of::<i32>("valid-age"),
+                    || async { Ok::<_, Infallible>(18) },
+                    Some(&options),
+                )
+                .await
+                .unwrap();
+            assert_eq!(*value, 18);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn max_concurrent_fetches_test() {
+        run_local(async {
+            let client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .max_concurrent_fetches(2)
+                .build();
+
+            let running = Rc::new(Cell::new(0));
+            let max_running = Rc::new(Cell::new(0));
+            let tasks = (0..5)
+                .map(|idx| {
+                    let mut client = client.clone();
+                    let running = running.clone();
+                    let max_running = max_running.clone();
+                    tokio::task::spawn_local(async move {
+                        let key = QueryKey::of::<usize>(format!("item/{idx}"));
+                        client
+                            .fetch_query(key, move || {
+                                let running = running.clone();
+                                let max_running = max_running.clone();
+                                async move {
+                                    running.set(running.get() + 1);
+                                    max_running.set(max_running.get().max(running.get()));
+                                    tokio::time::sleep(Duration::from_millis(20)).await;
+                                    running.set(running.get() - 1);
+                                    Ok::<_, Infallible>(idx)
+                                }
+                            })
+                            .await
+                    })
+                })
+                .collect::<Vec<_>>();
 
-        let cache = cache
-            .or_else(|| Some(Rc::new(RefCell::new(HashMap::new()))))
-            .unwrap();
+            // The fetches over the limit wait in the queue
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            assert!(!client.is_queued(&QueryKey::of::<usize>("item/0")));
+            assert!(client.is_queued(&QueryKey::of::<usize>("item/4")));
+            assert_eq!(
+                client.fetch_status(&QueryKey::of::<usize>("item/4")),
+                FetchStatus::Paused
+            );
+
+            for (idx, task) in tasks.into_iter().enumerate() {
+                assert_eq!(*task.await.unwrap().unwrap(), idx);
+            }
 
-        QueryClient { cache, options }
+            assert_eq!(max_running.get(), 2);
+            assert!(!client.is_queued(&QueryKey::of::<usize>("item/4")));
+        })
+        .await
     }
-}
 
-pub(crate) async fn fetch_with_retry<F, T>(fetcher: F, retrier: Option<Retry>) -> Result<T, Error>
-where
-    F: Fetch<T> + 'static,
-    T: 'static,
-{
-    let mut ret = fetcher.get().await;
+    #[tokio::test]
+    async fn min_fetch_interval_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .min_fetch_interval(Duration::from_millis(100))
+                .build();
 
-    if ret.is_ok() {
-        return ret;
-    }
+            let started = Rc::new(RefCell::new(Vec::new()));
+            let fetch = {
+                let started = started.clone();
+                move || {
+                    started.borrow_mut().push(Instant::now());
+                    async { Ok::<_, Infallible>(1) }
+                }
+            };
 
-    if let Some(retry) = retrier {
-        let iter = retry.get();
-        for delay in iter {
-            prokio::time::sleep(delay).await;
-            ret = fetcher.get().await;
-            if ret.is_ok() {
-                return ret;
+            for _ in 0..3 {
+                let key = QueryKey::of::<i32>("limited");
+                client.fetch_query(key, fetch.clone()).await.unwrap();
             }
-        }
+
+            // Other keys are not delayed
+            let now = Instant::now();
+            let key = QueryKey::of::<i32>("other");
+            client.fetch_query(key, fetch.clone()).await.unwrap();
+            assert!(now.elapsed() < Duration::from_millis(100));
+
+            let started = started.borrow();
+            assert!(started[1] - started[0] >= Duration::from_millis(100));
+            assert!(started[2] - started[1] >= Duration::from_millis(100));
+        })
+        .await
     }
 
-    ret
-}
+    #[tokio::test]
+    async fn request_key_test() {
+        run_local(async {
+            let client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
+                .build();
 
-#[cfg(test)]
-mod tests {
-    use std::convert::Infallible;
+            let (requests, fetch) =
+                counting_fetcher(Duration::from_millis(20), |_| String::from("mercury"));
 
-    use futures::Future;
-    use instant::Duration;
-    use tokio::task::LocalSet;
+            let options = QueryOptions::new().request_key("GET /planets/1");
+            let tasks = ["planet/1", "planet/mercury"]
+                .into_iter()
+                .map(|key| {
+                    let mut client = client.clone();
+                    let fetch = fetch.clone();
+                    let options = options.clone();
+                    tokio::task::spawn_local(async move {
+                        let key = QueryKey::of::<String>(key);
+                        client
+                            .fetch_query_with_options(key, fetch, Some(&options))
+                            .await
+                    })
+                })
+                .collect::<Vec<_>>();
 
-    use crate::{error::QueryError, QueryClient, QueryKey};
+            for task in tasks {
+                assert_eq!(task.await.unwrap().unwrap().as_str(), "mercury");
+            }
 
-    #[tokio::test]
-    async fn fetch_and_cache_query_test() {
-        #[derive(Debug, PartialEq)]
-        struct Item {
-            name: String,
-        }
+            // Both keys are cached from a single request
+            assert_eq!(requests.get(), 1);
+            assert!(client.contains_query(&QueryKey::of::<String>("planet/mercury")));
+
+            // The request is fetched again after completed
+            let mut client = client;
+            let key = QueryKey::of::<String>("planet/1");
+            client.refetch_query::<String>(key).await.unwrap();
+            assert_eq!(requests.get(), 2);
+        })
+        .await
+    }
 
+    #[tokio::test]
+    async fn request_key_cancel_test() {
         run_local(async {
-            let mut client = QueryClient::builder()
-                .cache_time(Duration::from_millis(400))
+            let client = QueryClient::builder()
+                .cache_time(Duration::from_millis(200))
                 .build();
 
-            let key = QueryKey::of::<Item>("sword");
-
-            assert!(!client.contains_query(&key));
+            let fetch = || async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Ok::<_, Infallible>(String::from("venus"))
+            };
 
-            let ret = client
-                .fetch_query(key.clone(), || async {
-                    Ok::<_, Infallible>(Item {
-                        name: "Fire Sword".to_owned(),
+            let options = QueryOptions::new().request_key("GET /planets/2");
+            let tasks = ["planet/2", "planet/venus"]
+                .into_iter()
+                .map(|key| {
+                    let mut client = client.clone();
+                    let options = options.clone();
+                    tokio::task::spawn_local(async move {
+                        let key = QueryKey::of::<String>(key);
+                        client
+                            .fetch_query_with_options(key, fetch, Some(&options))
+                            .await
                     })
                 })
-                .await
-                .unwrap();
+                .collect::<Vec<_>>();
 
-            assert_eq!(
-                ret.as_ref(),
-                &Item {
-                    name: "Fire Sword".to_owned()
-                }
-            );
+            // Cancelling the query that started the request don't cancel the other query
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            assert!(client.cancel_query(&QueryKey::of::<String>("planet/2")));
 
-            assert!(!client.is_stale(&key));
-            assert_eq!(
-                client.get_query_data::<Item>(&key).ok().as_deref(),
-                Some(&Item {
-                    name: "Fire Sword".to_owned()
-                })
-            );
+            let mut tasks = tasks.into_iter();
+            let first = tasks.next().unwrap().await.unwrap();
+            assert!(first.unwrap_err().is_cancelled());
 
-            // Let the data expire
-            tokio::time::sleep(Duration::from_millis(500)).await;
+            let second = tasks.next().unwrap().await.unwrap();
+            assert_eq!(second.unwrap().as_str(), "venus");
 
-            assert!(client.is_stale(&key));
-            assert!(matches!(
-                client.get_query_data::<Item>(&key),
-                Err(QueryError::StaleValue)
-            ));
+            let query = client
+                .get_query(&QueryKey::of::<String>("planet/venus"))
+                .unwrap()
+                .clone();
+            assert!(query.state().is_ready());
+            assert!(!query.is_fetching());
         })
-        .await;
+        .await
     }
 
     #[tokio::test]
-    async fn refetch_and_cache_query_test() {
+    async fn batch_test() {
         run_local(async {
             let mut client = QueryClient::builder()
-                .cache_time(Duration::from_millis(200))
+                .cache_time(Duration::from_secs(10))
                 .build();
 
-            let key = QueryKey::of::<String>("color");
-            let value = client
-                .fetch_query(key.clone(), || async {
-                    Ok::<_, Infallible>("magenta".to_owned())
-                })
-                .await
-                .unwrap();
+            let todos = QueryKey::of::<Vec<&str>>("todos");
+            let done = QueryKey::of::<Vec<&str>>("todos/done");
+            for key in [&todos, &done] {
+                let empty = || async { Ok::<Vec<&str>, Infallible>(Vec::new()) };
+                client.fetch_query(key.clone(), empty).await.unwrap();
+            }
 
-            assert!(client.has_query_data(&key));
-            assert_eq!(value.as_str(), "magenta");
+            let changes = Rc::new(RefCell::new(Vec::new()));
+            let _subscription = client.get_query(&todos).unwrap().subscribe({
+                let changes = changes.clone();
+                move |event: QueryChanged| {
+                    let value = event.value.unwrap().downcast::<Vec<&str>>().unwrap();
+                    changes.borrow_mut().push(value.len());
+                }
+            });
 
-            // Wait for timeout
-            tokio::time::sleep(Duration::from_millis(300)).await;
+            let events = Rc::new(Cell::new(0));
+            client.add_cache_listener({
+                let events = events.clone();
+                move |_: &QueryCacheEvent| events.set(events.get() + 1)
+            });
 
-            // Expired
-            assert!(!client.has_query_data(&key));
+            let invalidated = client.batch(|tx| {
+                tx.set_query_data(todos.clone(), vec!["a"]).unwrap();
+                tx.set_query_data(todos.clone(), vec!["a", "b"]).unwrap();
+                tx.set_query_data(done.clone(), vec!["c"]).unwrap();
+                tx.remove_query_data(&QueryKey::of::<Vec<&str>>("other"));
 
-            // Refetch
-            let value = client.refetch_query::<String>(key.clone()).await.unwrap();
-            assert!(client.has_query_data(&key));
-            assert_eq!(value.as_str(), "magenta");
+                // The changes are applied but not notified until the batch ends
+                assert_eq!(tx.client().get_query_data::<Vec<&str>>(&todos).unwrap().len(), 2);
+                assert!(changes.borrow().is_empty());
+                assert_eq!(events.get(), 0);
+
+                tx.invalidate(QueryFilter::from(done.clone()))
+            });
+
+            assert_eq!(invalidated, 1);
+            assert_eq!(*changes.borrow(), vec![2]);
+            assert_eq!(events.get(), 2);
+            assert!(client.is_stale(&done));
         })
-        .await;
+        .await
     }
 
     #[tokio::test]
-    async fn set_and_get_query_data_test() {
+    async fn route_query_data_test() {
         run_local(async {
             let mut client = QueryClient::builder()
-                .cache_time(Duration::from_millis(200))
+                .cache_time(Duration::from_secs(10))
                 .build();
 
-            let key = QueryKey::of::<String>("color");
-            client
-                .fetch_query(key.clone(), || async {
-                    Ok::<_, Infallible>("pink".to_owned())
-                })
-                .await
-                .unwrap();
+            let btc = QueryKey::of::<String>("price/btc");
+            let eth = QueryKey::of::<String>("price/eth");
+            for key in [&btc, &eth] {
+                client
+                    .fetch_query(key.clone(), || async {
+                        Ok::<_, Infallible>("0".to_owned())
+                    })
+                    .await
+                    .unwrap();
+            }
+
+            let changes = Rc::new(Cell::new(0));
+            let subscription = client.get_query(&eth).unwrap().subscribe({
+                let changes = changes.clone();
+                move |_| changes.set(changes.get() + 1)
+            });
+
+            // A single message is routed to several queries, the keys without a query are skipped
+            let missing = QueryKey::of::<String>("price/sol");
+            let updated = client.route_query_data([
+                (btc.clone(), "100".to_owned()),
+                (eth.clone(), "10".to_owned()),
+                (missing, "1".to_owned()),
+            ]);
 
+            assert_eq!(updated, vec![btc.clone(), eth.clone()]);
             assert_eq!(
-                client.get_query_data(&key).ok().as_deref(),
-                Some(&String::from("pink"))
+                client.get_query_data::<String>(&btc).unwrap().as_str(),
+                "100"
+            );
+            assert_eq!(
+                client.get_query_data::<String>(&eth).unwrap().as_str(),
+                "10"
             );
+            assert_eq!(changes.get(), 1);
 
-            assert!(client.has_query_data(&key));
+            drop(subscription);
+        })
+        .await
+    }
 
-            // Wait for timeout
-            tokio::time::sleep(Duration::from_millis(300)).await;
+    #[tokio::test]
+    async fn set_query_data_with_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
 
-            assert!(!client.has_query_data(&key));
+            let key = QueryKey::of::<Vec<u32>>("numbers");
+            let fetch = || async { Ok::<_, Infallible>((0..1000).collect::<Vec<u32>>()) };
+            let ptr = Rc::as_ptr(&client.fetch_query(key.clone(), fetch).await.unwrap());
 
-            // Sets the data
+            // Only the cache holds the value, so is updated in place
             client
-                .set_query_data(key.clone(), String::from("aqua"))
+                .set_query_data_with(key.clone(), |x: &mut Vec<u32>| x.push(1000))
                 .unwrap();
 
-            assert_eq!(
-                client.get_query_data(&key).ok().as_deref(),
-                Some(&String::from("aqua"))
-            );
+            let value = client.get_query_data::<Vec<u32>>(&key).unwrap();
+            assert_eq!(Rc::as_ptr(&value), ptr);
+            assert_eq!(value.len(), 1001);
 
-            // Wait for timeout
-            tokio::time::sleep(Duration::from_millis(300)).await;
+            // A value referenced outside the cache is cloned
+            client
+                .set_query_data_with(key.clone(), |x: &mut Vec<u32>| x.clear())
+                .unwrap();
 
-            assert!(matches!(
-                client.get_query_data::<String>(&key),
-                Err(QueryError::StaleValue)
-            ));
+            assert_eq!(value.len(), 1001);
+            assert!(client.get_query_data::<Vec<u32>>(&key).unwrap().is_empty());
+
+            let err = client
+                .set_query_data_with(QueryKey::of::<Vec<u32>>("other"), |x: &mut Vec<u32>| {
+                    x.clear()
+                })
+                .unwrap_err();
+            assert!(matches!(err, QueryError::KeyNotFound(_)));
         })
-        .await;
+        .await
     }
 
     #[tokio::test]
-    async fn contains_and_get_query_then_remove_test() {
+    async fn cache_stats_test() {
         run_local(async {
             let mut client = QueryClient::builder()
-                .cache_time(Duration::from_millis(200))
+                .cache_time(Duration::from_secs(10))
+                .size_estimator(|x: &Vec<u64>| x.len() * std::mem::size_of::<u64>())
                 .build();
 
-            let key = QueryKey::of::<String>("fruit");
-
-            assert!(!client.contains_query(&key));
-            assert!(client.get_query(&key).is_none());
-            assert!(!client.has_query_data(&key));
-
-            client
-                .fetch_query(key.clone(), || async {
-                    Ok::<_, Infallible>("strawberry".to_owned())
-                })
-                .await
-                .unwrap();
+            let numbers = QueryKey::of::<Vec<u64>>("numbers");
+            let fetch = || async { Ok::<_, Infallible>(vec![1_u64, 2, 3]) };
+            client.fetch_query(numbers.clone(), fetch).await.unwrap();
 
-            assert!(client.contains_query(&key));
-            assert!(client.get_query(&key).is_some());
-            assert!(client.has_query_data(&key));
+            let name = QueryKey::of::<String>("name");
+            let fetch = || async { Ok::<_, Infallible>(String::from("ferris")) };
+            client.fetch_query(name.clone(), fetch).await.unwrap();
 
-            // Wait for timeout
-            tokio::time::sleep(Duration::from_millis(300)).await;
+            let _subscription = client.get_query(&name).unwrap().subscribe(|_| {});
 
-            assert!(client.contains_query(&key));
-            assert!(client.get_query(&key).is_some());
-            assert!(!client.has_query_data(&key));
+            let stats = client.cache_stats();
+            assert_eq!(stats.len(), 2);
+            assert_eq!(stats.active_count(), 1);
+            assert_eq!(stats.estimated_size, Some(24));
 
-            // Remove the query
-            client.remove_query_data(&key);
+            let numbers = stats.queries.iter().find(|x| x.key == numbers).unwrap();
+            assert_eq!(numbers.estimated_size, Some(24));
+            assert_eq!(numbers.observers, 0);
+            assert!(numbers.age.is_some());
 
-            assert!(!client.contains_query(&key));
-            assert!(client.get_query(&key).is_none());
-            assert!(!client.has_query_data(&key));
+            // Values without estimator are not estimated
+            let name = stats.queries.iter().find(|x| x.key == name).unwrap();
+            assert_eq!(name.estimated_size, None);
+            assert_eq!(name.observers, 1);
         })
-        .await;
+        .await
     }
 
     #[tokio::test]
-    async fn clear_queries_test() {
+    async fn fetch_history_test() {
         run_local(async {
             let mut client = QueryClient::builder()
-                .cache_time(Duration::from_millis(200))
+                .cache_time(Duration::from_secs(10))
                 .build();
 
-            let fruit_key = QueryKey::of::<String>("fruit");
-            let color_key = QueryKey::of::<String>("color");
+            let key = QueryKey::of::<u32>("number");
+            assert!(client.fetch_history(&key).is_none());
 
-            client
-                .fetch_query(fruit_key.clone(), || async {
-                    Ok::<_, Infallible>("apple".to_owned())
-                })
-                .await
-                .unwrap();
+            let fails = Rc::new(Cell::new(false));
+            let fetch = {
+                let fails = fails.clone();
+                move || {
+                    let fails = fails.get();
+                    async move {
+                        match fails {
+                            true => Err(QueryError::Timeout),
+                            false => Ok(42_u32),
+                        }
+                    }
+                }
+            };
 
-            client
-                .fetch_query(color_key.clone(), || async {
-                    Ok::<_, Infallible>("red".to_owned())
-                })
-                .await
-                .unwrap();
+            client.fetch_query(key.clone(), fetch).await.unwrap();
+            fails.set(true);
+            client.refetch_query::<u32>(key.clone()).await.unwrap_err();
 
-            assert!(client.contains_query(&fruit_key));
-            assert!(client.contains_query(&color_key));
+            let history = client.fetch_history(&key).unwrap();
+            assert_eq!(history.len(), 2);
+            assert_eq!(history.error_rate(), Some(0.5));
 
-            client.clear_queries();
+            let records = history.iter().collect::<Vec<_>>();
+            assert!(matches!(records[0].outcome, FetchOutcome::Success));
+            assert!(matches!(records[1].outcome, FetchOutcome::Failed(_)));
+            assert!(records.iter().all(|x| x.cause == FetchCause::Manual));
+        })
+        .await
+    }
 
-            assert!(!client.contains_query(&fruit_key));
-            assert!(!client.contains_query(&color_key));
+    #[tokio::test]
+    async fn observe_cached_query_is_not_fetching_test() {
+        run_local(async {
+            let mut client = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .build();
+
+            let key = QueryKey::of::<u32>("number");
+            let fetch = || async { Ok::<_, Infallible>(42_u32) };
+            client.fetch_query(key.clone(), fetch).await.unwrap();
+            assert!(!client.is_fetching(&key));
+
+            let events = Rc::new(RefCell::new(Vec::new()));
+            let observer = QueryObserver::<u32>::new(client.clone(), "number".into());
+            observer.observe(ObserveTarget::Fetch, fetch, {
+                let events = events.clone();
+                move |event: QueryChangeEvent<u32>| {
+                    events.borrow_mut().push((event.is_fetching, event.fetch_status()))
+                }
+            });
+
+            assert!(!observer.is_fetching());
+            assert_eq!(events.borrow()[0], (false, FetchStatus::Idle));
         })
-        .await;
+        .await
     }
 
     #[tokio::test]
-    async fn query_with_refetch_test() {
+    async fn fetch_status_test() {
         run_local(async {
             let mut client = QueryClient::builder()
-                .cache_time(Duration::from_millis(300))
-                .refetch_time(Duration::from_millis(400))
+                .cache_time(Duration::from_secs(10))
                 .build();
 
-            let key = QueryKey::of::<String>("fruit");
-            client
-                .fetch_query(key.clone(), || async { Ok::<_, Infallible>("pineapple") })
-                .await
-                .unwrap();
+            let key = QueryKey::of::<u32>("number");
+            assert_eq!(client.fetch_status(&key), FetchStatus::Idle);
 
-            assert!(client.has_query_data(&key));
+            let fetch = || async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Ok::<_, Infallible>(42_u32)
+            };
 
-            // Timeout
-            tokio::time::sleep(Duration::from_millis(300)).await;
-            assert!(!client.has_query_data(&key));
+            client.fetch_query(key.clone(), fetch).await.unwrap();
+            assert_eq!(client.fetch_status(&key), FetchStatus::Idle);
 
-            // Wait for refetch
-            tokio::time::sleep(Duration::from_millis(600)).await;
-            assert!(client.has_query_data(&key));
+            let task = tokio::task::spawn_local({
+                let mut client = client.clone();
+                let key = key.clone();
+                async move { client.refetch_query::<u32>(key).await }
+            });
+
+            // The query keeps its data while fetching in background
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            let query = client.get_query(&key).unwrap().clone();
+            assert!(query.state().is_ready());
+            assert_eq!(query.fetch_status(), FetchStatus::Fetching);
+
+            task.await.unwrap().unwrap();
+            assert_eq!(client.fetch_status(&key), FetchStatus::Idle);
+
+            // The refetches can't run while the client is paused or offline
+            client.pause();
+            assert_eq!(client.fetch_status(&key), FetchStatus::Paused);
+            client.resume();
+            assert_eq!(client.fetch_status(&key), FetchStatus::Idle);
+
+            let manager = ManualOnlineManager::new(false);
+            let mut offline = QueryClient::builder()
+                .cache_time(Duration::from_secs(10))
+                .online_manager(manager.clone())
+                .build();
+
+            offline.fetch_query(key.clone(), fetch).await.unwrap();
+            assert_eq!(offline.fetch_status(&key), FetchStatus::Paused);
+            manager.set_online(true);
+            assert_eq!(offline.fetch_status(&key), FetchStatus::Idle);
         })
         .await
     }
 
+    // Returns the number of calls of the fetcher, and a fetcher that resolves
+    // to the value for the number of the call after the given delay.
+    fn counting_fetcher<T, F>(
+        delay: Duration,
+        value: F,
+    ) -> (
+        Rc<Cell<usize>>,
+        impl Fn() -> LocalBoxFuture<'static, Result<T, Infallible>> + Clone + 'static,
+    )
+    where
+        T: 'static,
+        F: Fn(usize) -> T + 'static,
+    {
+        let calls = Rc::new(Cell::new(0));
+        let value = Rc::new(value);
+        let fetcher = {
+            let calls = calls.clone();
+            move || {
+                let (calls, value) = (calls.clone(), value.clone());
+                async move {
+                    calls.set(calls.get() + 1);
+                    let call = calls.get();
+                    if !delay.is_zero() {
+                        tokio::time::sleep(delay).await;
+                    }
+
+                    Ok(value(call))
+                }
+                .boxed_local()
+            }
+        };
+
+        (calls, fetcher)
+    }
+
     async fn run_local<Fut>(future: Fut) -> Fut::Output
     where
         Fut: Future,
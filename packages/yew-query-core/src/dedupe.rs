@@ -0,0 +1,150 @@
+use crate::{cancellation::CancellationToken, client::with_cancellation, key::Key, Error};
+use futures::{
+    future::{LocalBoxFuture, Shared},
+    FutureExt,
+};
+use std::{
+    any::{Any, TypeId},
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt::Debug,
+    rc::{Rc, Weak},
+};
+
+type SharedFetch = Shared<LocalBoxFuture<'static, Result<Rc<dyn Any>, Error>>>;
+type Requests = RefCell<HashMap<(Key, TypeId), SharedRequest>>;
+
+// A fetch in progress and the number of queries waiting for it.
+#[derive(Clone)]
+struct SharedRequest {
+    fut: SharedFetch,
+    signal: CancellationToken,
+    joiners: Rc<Cell<usize>>,
+}
+
+// The fetches in progress by request key, shared by the queries with the same request key,
+// the type is part of the key so only queries of the same type share a fetch.
+#[derive(Clone, Default)]
+pub(crate) struct RequestDeduper(Rc<Requests>);
+
+impl PartialEq for RequestDeduper {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl RequestDeduper {
+    // Returns the fetch in progress of the request, or starts a fetch with the given function.
+    //
+    // The fetch has its own signal, which is only cancelled when all the queries waiting for it
+    // are cancelled, a cancelled query stops waiting with `QueryError::Cancelled`.
+    pub fn join_or_start<F>(
+        &self,
+        request: Key,
+        type_id: TypeId,
+        signal: &CancellationToken,
+        start: F,
+    ) -> LocalBoxFuture<'static, Result<Rc<dyn Any>, Error>>
+    where
+        F: FnOnce(CancellationToken) -> LocalBoxFuture<'static, Result<Rc<dyn Any>, Error>>,
+    {
+        let id = (request, type_id);
+        let existing = self.0.borrow().get(&id).cloned();
+        let shared = match existing {
+            Some(shared) => shared,
+            None => {
+                let shared = self.start(id.clone(), start);
+                self.0.borrow_mut().insert(id.clone(), shared.clone());
+                shared
+            }
+        };
+
+        shared.joiners.set(shared.joiners.get() + 1);
+        let joiner = Joiner {
+            id,
+            requests: Rc::downgrade(&self.0),
+            shared: shared.clone(),
+        };
+
+        let signal = signal.clone();
+        async move {
+            let ret = with_cancellation(joiner.shared.fut.clone(), &signal).await;
+            drop(joiner);
+            ret
+        }
+        .boxed_local()
+    }
+
+    fn start<F>(&self, id: (Key, TypeId), start: F) -> SharedRequest
+    where
+        F: FnOnce(CancellationToken) -> LocalBoxFuture<'static, Result<Rc<dyn Any>, Error>>,
+    {
+        let signal = CancellationToken::new();
+        let joiners = Rc::new(Cell::new(0));
+        let fetch = start(signal.clone());
+
+        // The request is removed when completes, so the next fetch starts a new request
+        let requests = Rc::downgrade(&self.0);
+        let fut = {
+            let joiners = Rc::downgrade(&joiners);
+            async move {
+                let ret = fetch.await;
+                if let (Some(requests), Some(joiners)) = (requests.upgrade(), joiners.upgrade()) {
+                    remove_request(&requests, &id, &joiners);
+                }
+
+                ret
+            }
+        }
+        .boxed_local()
+        .shared();
+
+        SharedRequest {
+            fut,
+            signal,
+            joiners,
+        }
+    }
+}
+
+// Removes the request if is still the one in progress, it may be replaced by a newer request.
+fn remove_request(requests: &Requests, id: &(Key, TypeId), joiners: &Rc<Cell<usize>>) {
+    let mut requests = requests.borrow_mut();
+    if requests
+        .get(id)
+        .is_some_and(|x| Rc::ptr_eq(&x.joiners, joiners))
+    {
+        requests.remove(id);
+    }
+}
+
+// A query waiting for a shared request, the last one to stop waiting cancels the request.
+struct Joiner {
+    id: (Key, TypeId),
+    requests: Weak<Requests>,
+    shared: SharedRequest,
+}
+
+impl Drop for Joiner {
+    fn drop(&mut self) {
+        let joiners = &self.shared.joiners;
+        joiners.set(joiners.get() - 1);
+
+        if joiners.get() > 0 || self.shared.fut.peek().is_some() {
+            return;
+        }
+
+        self.shared.signal.cancel();
+        if let Some(requests) = self.requests.upgrade() {
+            remove_request(&requests, &self.id, joiners);
+        }
+    }
+}
+
+impl Debug for RequestDeduper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RequestDeduper")
+            .field("requests", &self.0.borrow().len())
+            .finish()
+    }
+}
@@ -1,4 +1,5 @@
-use crate::QueryKey;
+use crate::{QueryKey, QueryMeta};
+use instant::Duration;
 use std::fmt::Display;
 use std::sync::Arc;
 
@@ -16,6 +17,94 @@ impl Error {
     {
         Error(Arc::new(error))
     }
+
+    /// Returns `true` if the inner error is of type `E`.
+    pub fn is<E>(&self) -> bool
+    where
+        E: StdError + 'static,
+    {
+        self.0.is::<E>()
+    }
+
+    /// Returns a reference to the inner error if is of type `E`.
+    pub fn downcast_ref<E>(&self) -> Option<&E>
+    where
+        E: StdError + 'static,
+    {
+        self.0.downcast_ref::<E>()
+    }
+
+    /// Attempts to downcast the inner error to `E`.
+    ///
+    /// The error is shared between clones so the value is returned as an `Arc<E>`,
+    /// on failure the original error is returned.
+    pub fn downcast<E>(self) -> Result<Arc<E>, Self>
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        if !self.is::<E>() {
+            return Err(self);
+        }
+
+        let raw = Arc::into_raw(self.0);
+
+        // SAFETY: we checked the inner error is of type `E`
+        let inner = unsafe { Arc::from_raw(raw as *const E) };
+        Ok(inner)
+    }
+
+    /// Returns the lower-level source of this error, if any.
+    pub fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.0.source()
+    }
+
+    /// Returns an iterator over this error and its sources.
+    pub fn chain(&self) -> impl Iterator<Item = &(dyn StdError + 'static)> {
+        let inner: &(dyn StdError + 'static) = &*self.0;
+        std::iter::successors(Some(inner), |&e| e.source())
+    }
+
+    /// Returns the last error in the source chain.
+    pub fn root_cause(&self) -> &(dyn StdError + 'static) {
+        self.chain().last().expect("error chain is never empty")
+    }
+
+    /// Wraps this error with additional context.
+    pub fn context<C>(self, context: C) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        Error::new(ContextError {
+            context: context.to_string(),
+            source: self,
+        })
+    }
+
+    /// Returns `true` if this is a `QueryError::Cancelled` error.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.downcast_ref::<QueryError>(), Some(QueryError::Cancelled))
+    }
+
+    /// Returns `true` if this is a `QueryError::Timeout` error.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self.downcast_ref::<QueryError>(), Some(QueryError::Timeout))
+    }
+
+    /// Returns `true` if this is a `QueryError::NotModified` error.
+    pub fn is_not_modified(&self) -> bool {
+        matches!(
+            self.downcast_ref::<QueryError>(),
+            Some(QueryError::NotModified)
+        )
+    }
+
+    /// Returns the `ValidationError` if this is a `QueryError::Validation` error.
+    pub fn validation_error(&self) -> Option<&ValidationError> {
+        match self.downcast_ref::<QueryError>() {
+            Some(QueryError::Validation(err)) => Some(err),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Debug for Error {
@@ -40,6 +129,52 @@ where
     }
 }
 
+/// An error with additional context.
+#[derive(Debug)]
+pub struct ContextError {
+    context: String,
+    source: Error,
+}
+
+impl ContextError {
+    /// Returns the context of this error.
+    pub fn context(&self) -> &str {
+        &self.context
+    }
+}
+
+impl Display for ContextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.context, self.source)
+    }
+}
+
+impl StdError for ContextError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&*self.source.0)
+    }
+}
+
+/// Extension methods for adding context to a `Result`.
+pub trait ResultExt<T> {
+    /// Wraps the error with additional context.
+    fn context<C>(self, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static;
+}
+
+impl<T, E> ResultExt<T> for Result<T, E>
+where
+    E: Into<Error>,
+{
+    fn context<C>(self, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        self.map_err(|e| e.into().context(context))
+    }
+}
+
 #[doc(hidden)]
 #[derive(Debug)]
 pub struct TypeMismatchError {
@@ -50,6 +185,52 @@ pub struct TypeMismatchError {
 #[derive(Debug)]
 pub struct KeyNotFoundError(String);
 
+/// An error returned by the validator of a query, see `QueryOptions::validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    field: Option<String>,
+    message: String,
+}
+
+impl ValidationError {
+    /// Constructs a `ValidationError` with the given message.
+    pub fn new(message: impl Into<String>) -> Self {
+        ValidationError {
+            field: None,
+            message: message.into(),
+        }
+    }
+
+    /// Constructs a `ValidationError` for the given field of the value.
+    pub fn with_field(field: impl Into<String>, message: impl Into<String>) -> Self {
+        ValidationError {
+            field: Some(field.into()),
+            message: message.into(),
+        }
+    }
+
+    /// Returns the field that failed the validation, if any.
+    pub fn field(&self) -> Option<&str> {
+        self.field.as_deref()
+    }
+
+    /// Returns the message of this error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.field {
+            Some(field) => write!(f, "`{field}`: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl StdError for ValidationError {}
+
 /// An error ocurred in a query.
 #[derive(Debug)]
 pub enum QueryError {
@@ -64,6 +245,18 @@ pub enum QueryError {
 
     /// If the query exists but is stale.
     StaleValue,
+
+    /// If the fetch was cancelled before completion.
+    Cancelled,
+
+    /// If the fetch took longer than the allowed time.
+    Timeout,
+
+    /// If the fetcher determined the cached value is still valid.
+    NotModified,
+
+    /// If the value returned by the fetcher was rejected by the validator of the query.
+    Validation(ValidationError),
 }
 
 impl QueryError {
@@ -79,6 +272,37 @@ impl QueryError {
 
 impl std::error::Error for QueryError {}
 
+/// Information about a query that failed.
+#[derive(Debug, Clone)]
+pub struct ErrorContext {
+    /// The key of the query.
+    pub key: QueryKey,
+
+    /// The metadata of the query.
+    pub meta: QueryMeta,
+
+    /// The number of attempts made, including the retries.
+    pub attempts: usize,
+
+    /// The time taken since the first attempt until the last failure.
+    pub duration: Duration,
+}
+
+/// Receives the errors of the queries after all the retries had failed.
+pub trait ErrorReporter {
+    /// Reports the given error.
+    fn report(&self, error: &Error, context: &ErrorContext);
+}
+
+impl<F> ErrorReporter for F
+where
+    F: Fn(&Error, &ErrorContext),
+{
+    fn report(&self, error: &Error, context: &ErrorContext) {
+        (self)(error, context)
+    }
+}
+
 impl Display for QueryError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use QueryError::*;
@@ -88,6 +312,63 @@ impl Display for QueryError {
             KeyNotFound(KeyNotFoundError(k)) => write!(f, "key not found `{k}`"),
             NotReady => write!(f, "query had not resolved yet"),
             StaleValue => write!(f, "value is tale"),
+            Cancelled => write!(f, "query was cancelled"),
+            Timeout => write!(f, "query timed out"),
+            NotModified => write!(f, "query value was not modified"),
+            Validation(err) => write!(f, "invalid query value: {err}"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ContextError, Error, QueryError, ResultExt};
+    use std::fmt::Display;
+
+    #[derive(Debug, PartialEq)]
+    struct NetworkError(u16);
+
+    impl Display for NetworkError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "network error: {}", self.0)
+        }
+    }
+
+    impl std::error::Error for NetworkError {}
+
+    #[test]
+    fn downcast_error_test() {
+        let error = Error::new(NetworkError(404));
+
+        assert!(error.is::<NetworkError>());
+        assert!(!error.is::<QueryError>());
+
+        assert_eq!(error.downcast_ref::<NetworkError>(), Some(&NetworkError(404)));
+        assert!(error.downcast_ref::<QueryError>().is_none());
+
+        let error = error.downcast::<QueryError>().unwrap_err();
+        let inner = error.downcast::<NetworkError>().unwrap();
+        assert_eq!(*inner, NetworkError(404));
+    }
+
+    #[test]
+    fn error_context_test() {
+        let ret: Result<(), _> = Err(NetworkError(500));
+        let error = ret.context("while loading posts").unwrap_err();
+
+        assert_eq!(error.to_string(), "while loading posts: network error: 500");
+        assert!(error.is::<ContextError>());
+        assert_eq!(
+            error.downcast_ref::<ContextError>().unwrap().context(),
+            "while loading posts"
+        );
+
+        let error = error.context("while rendering");
+        assert_eq!(error.chain().count(), 3);
+        assert_eq!(error.root_cause().to_string(), "network error: 500");
+        assert_eq!(
+            error.source().unwrap().to_string(),
+            "while loading posts: network error: 500"
+        );
+    }
+}
@@ -1,21 +1,293 @@
 use super::Error;
-use futures::{Future, TryFutureExt};
-use std::{pin::Pin, rc::Rc};
+use crate::{limiter::OnQueued, CancellationToken, QueryKey, QueryMeta};
+use futures::{Future, Stream, StreamExt, TryFutureExt};
+use instant::Duration;
+use std::{
+    any::{type_name, Any, TypeId},
+    cell::RefCell,
+    fmt::Debug,
+    pin::Pin,
+    rc::Rc,
+};
 
 /// Represents a future that resolves to a `Result<T, E>`.
 type TryBoxFuture<T, E = Error> = Pin<Box<dyn Future<Output = Result<T, E>>>>;
 
+/// Information about the query being fetched.
+#[derive(Debug, Clone)]
+pub struct FetchContext {
+    /// The key of the query.
+    pub key: QueryKey,
+
+    /// The number of the current attempt, starting from `1`.
+    pub attempt: usize,
+
+    /// The metadata of the query.
+    pub meta: QueryMeta,
+
+    /// The page to fetch, for paginated queries.
+    pub page_param: Option<usize>,
+
+    /// A token that is cancelled when the fetch is no longer needed.
+    pub signal: CancellationToken,
+
+    /// The validators stored with the cached value, if any.
+    ///
+    /// Fetchers can send these to the server and return `QueryError::NotModified`
+    /// to keep the cached value.
+    pub validators: Option<Validators>,
+
+    pub(crate) response: FetchResponse,
+}
+
+impl FetchContext {
+    /// Sets the validators of the fetched value, these are stored with the cached value
+    /// and passed back on the next fetch.
+    pub fn set_validators(&self, validators: Validators) {
+        self.response.info.borrow_mut().validators = Some(validators);
+    }
+
+    /// Sets how long the fetched value stays fresh, this overrides the cache time of the query.
+    pub fn set_fresh_for(&self, duration: Duration) {
+        self.response.info.borrow_mut().fresh_for = Some(duration);
+    }
+
+    /// Reports the progress of the fetch to the observers of the query.
+    pub fn report_progress(&self, progress: Progress) {
+        if let Some(on_progress) = &self.response.on_progress {
+            on_progress(progress);
+        }
+    }
+
+    /// Updates the value of the query with a partial value while the fetch is still running.
+    ///
+    /// Values that don't match the type of the query are ignored.
+    pub fn report_partial<T: 'static>(&self, value: T) {
+        if let Some(on_partial) = &self.response.on_partial {
+            on_partial(Rc::new(value));
+        }
+    }
+
+    /// Sets how long the fetched value stays fresh from a `Cache-Control` header value.
+    ///
+    /// `max-age` is used as the fresh time, `no-cache` and `no-store` makes the value stale immediately.
+    pub fn set_cache_control(&self, cache_control: &str) {
+        if let Some(duration) = parse_cache_control(cache_control) {
+            self.set_fresh_for(duration);
+        }
+    }
+}
+
+fn parse_cache_control(cache_control: &str) -> Option<Duration> {
+    let mut max_age = None;
+
+    for directive in cache_control.split(',').map(str::trim) {
+        let (name, value) = match directive.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+            None => (directive, None),
+        };
+
+        match (name.to_ascii_lowercase().as_str(), value) {
+            ("no-cache" | "no-store", _) => return Some(Duration::ZERO),
+            ("max-age", Some(secs)) => {
+                max_age = secs.parse::<u64>().ok().map(Duration::from_secs);
+            }
+            _ => {}
+        }
+    }
+
+    max_age
+}
+
+/// Validators of a response used for conditional revalidation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    /// The `ETag` of the response.
+    pub etag: Option<String>,
+
+    /// The `Last-Modified` date of the response.
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// Constructs an empty `Validators`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the `ETag` of the response.
+    pub fn etag(mut self, etag: impl Into<String>) -> Self {
+        self.etag = Some(etag.into());
+        self
+    }
+
+    /// Sets the `Last-Modified` date of the response.
+    pub fn last_modified(mut self, last_modified: impl Into<String>) -> Self {
+        self.last_modified = Some(last_modified.into());
+        self
+    }
+}
+
+/// The progress of a fetch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The amount of units loaded, usually bytes.
+    pub loaded: u64,
+
+    /// The total amount of units to load, if known.
+    pub total: Option<u64>,
+}
+
+impl Progress {
+    /// Constructs a new `Progress`.
+    pub fn new(loaded: u64, total: Option<u64>) -> Self {
+        Progress { loaded, total }
+    }
+
+    /// Returns the progress as a fraction between `0.0` and `1.0`, if the total is known.
+    pub fn fraction(&self) -> Option<f64> {
+        match self.total {
+            Some(0) => Some(1.0),
+            Some(total) => Some((self.loaded as f64 / total as f64).min(1.0)),
+            None => None,
+        }
+    }
+
+    /// Returns the progress as a percent between `0.0` and `100.0`, if the total is known.
+    pub fn percent(&self) -> Option<f64> {
+        self.fraction().map(|x| x * 100.0)
+    }
+}
+
+pub(crate) type OnProgress = Rc<dyn Fn(Progress)>;
+pub(crate) type OnPartial = Rc<dyn Fn(Rc<dyn Any>)>;
+
+// Information attached by the fetcher to the response.
+#[derive(Debug, Default)]
+pub(crate) struct ResponseInfo {
+    pub validators: Option<Validators>,
+    pub fresh_for: Option<Duration>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct FetchResponse {
+    info: Rc<RefCell<ResponseInfo>>,
+    on_progress: Option<OnProgress>,
+    on_partial: Option<OnPartial>,
+    on_queued: Option<OnQueued>,
+}
+
+impl FetchResponse {
+    pub fn new(on_progress: Option<OnProgress>, on_partial: Option<OnPartial>) -> Self {
+        FetchResponse {
+            info: Default::default(),
+            on_progress,
+            on_partial,
+            on_queued: None,
+        }
+    }
+
+    // Sets the handler notified when the fetch waits in the queue of the client.
+    pub fn with_on_queued(mut self, on_queued: Option<OnQueued>) -> Self {
+        self.on_queued = on_queued;
+        self
+    }
+
+    pub fn on_queued(&self) -> Option<&OnQueued> {
+        self.on_queued.as_ref()
+    }
+
+    pub fn take(&self) -> ResponseInfo {
+        std::mem::take(&mut *self.info.borrow_mut())
+    }
+}
+
+impl Debug for FetchResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FetchResponse")
+            .field("info", &self.info)
+            .finish()
+    }
+}
+
 /// Represents a function to get data.
 pub trait Fetch<T> {
     /// The future returning the data.
-    type Fut: Future<Output = Result<T, Error>>;
+    type Fut: Future<Output = Result<T, Error>> + 'static;
 
     /// Returns a future that resolves to the data.
-    fn get(&self) -> Self::Fut;
+    fn get(&self, ctx: FetchContext) -> Self::Fut;
+
+    /// Returns the identity of this fetcher, used to detect a key fetched with different fetchers.
+    fn fetcher_id(&self) -> FetcherId
+    where
+        Self: Sized + 'static,
+    {
+        FetcherId::of::<Self>()
+    }
+}
+
+/// Identifies a fetcher by its type.
+///
+/// Each closure has its own type, so the same closure written in two places
+/// are different fetchers, share a function or a closure to use the same fetcher.
+#[derive(Clone, Copy)]
+pub struct FetcherId {
+    type_id: TypeId,
+    type_name: &'static str,
+}
+
+impl FetcherId {
+    /// Returns the identity of the fetcher of type `F`.
+    pub fn of<F: 'static>() -> Self {
+        FetcherId {
+            type_id: TypeId::of::<F>(),
+            type_name: type_name::<F>(),
+        }
+    }
+
+    /// Returns the name of the type of the fetcher.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl PartialEq for FetcherId {
+    fn eq(&self, other: &Self) -> bool {
+        self.type_id == other.type_id
+    }
+}
+
+impl Eq for FetcherId {}
+
+impl Debug for FetcherId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FetcherId").field(&self.type_name).finish()
+    }
+}
+
+/// What to do when a key is fetched with a fetcher different from the one
+/// the query was created with, the first fetcher is the one used.
+///
+/// Only checked in debug builds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateFetcher {
+    /// The fetcher is not checked.
+    Ignore,
+
+    /// Logs a warning.
+    #[default]
+    Warn,
+
+    /// Panics, useful in the tests.
+    Panic,
 }
 
 /// Boxes a `Fetcher`.
-pub struct BoxFetcher<T>(Rc<dyn Fn() -> TryBoxFuture<T>>);
+pub struct BoxFetcher<T> {
+    f: Rc<dyn Fn(FetchContext) -> TryBoxFuture<T>>,
+    id: FetcherId,
+}
 
 impl<T> BoxFetcher<T> {
     /// Constructs a new `BoxFetcher`.
@@ -25,8 +297,55 @@ impl<T> BoxFetcher<T> {
         Fut: Future<Output = Result<T, E>> + 'static,
         E: Into<Error> + 'static,
     {
-        let f = Rc::new(move || {
-            let fut = fetcher();
+        Self::with_context(move |_| fetcher()).with_id(FetcherId::of::<F>())
+    }
+
+    /// Constructs a new `BoxFetcher` from a function returning a stream of chunks.
+    ///
+    /// Each chunk is reduced into the value returned by `init`, and observers are notified
+    /// of the partial value before the stream completes.
+    pub fn from_stream<F, S, I, R, C, E>(fetcher: F, init: I, reduce: R) -> Self
+    where
+        F: Fn(FetchContext) -> S + 'static,
+        S: Stream<Item = Result<C, E>> + 'static,
+        I: Fn() -> T + 'static,
+        R: Fn(T, C) -> T + 'static,
+        T: Clone + 'static,
+        E: Into<Error> + 'static,
+    {
+        let init = Rc::new(init);
+        let reduce = Rc::new(reduce);
+
+        let fetcher_id = FetcherId::of::<F>();
+        Self::with_context(move |ctx: FetchContext| {
+            let stream = fetcher(ctx.clone());
+            let init = init.clone();
+            let reduce = reduce.clone();
+
+            async move {
+                futures::pin_mut!(stream);
+                let mut value = init();
+
+                while let Some(chunk) = stream.next().await {
+                    value = reduce(value, chunk.map_err(Into::into)?);
+                    ctx.report_partial(value.clone());
+                }
+
+                Ok::<_, Error>(value)
+            }
+        })
+        .with_id(fetcher_id)
+    }
+
+    /// Constructs a new `BoxFetcher` from a function that receives the `FetchContext`.
+    pub fn with_context<F, Fut, E>(fetcher: F) -> Self
+    where
+        F: Fn(FetchContext) -> Fut + 'static,
+        Fut: Future<Output = Result<T, E>> + 'static,
+        E: Into<Error> + 'static,
+    {
+        let f = Rc::new(move |ctx| {
+            let fut = fetcher(ctx);
             Box::pin(async move {
                 match fut.await {
                     Ok(x) => Ok(x),
@@ -35,22 +354,37 @@ impl<T> BoxFetcher<T> {
             }) as TryBoxFuture<T>
         });
 
-        BoxFetcher(f)
+        BoxFetcher {
+            f,
+            id: FetcherId::of::<F>(),
+        }
+    }
+
+    /// Sets the identity of this fetcher, used when the fetcher wraps another one.
+    pub fn with_id(mut self, id: FetcherId) -> Self {
+        self.id = id;
+        self
     }
 }
 
-impl<T> Fetch<T> for BoxFetcher<T> {
+impl<T: 'static> Fetch<T> for BoxFetcher<T> {
     type Fut = TryBoxFuture<T, Error>;
 
-    fn get(&self) -> Self::Fut {
-        let ret = (self.0)();
-        ret
+    fn get(&self, ctx: FetchContext) -> Self::Fut {
+        (self.f)(ctx)
+    }
+
+    fn fetcher_id(&self) -> FetcherId {
+        self.id
     }
 }
 
 impl<T> Clone for BoxFetcher<T> {
     fn clone(&self) -> Self {
-        Self(self.0.clone())
+        Self {
+            f: self.f.clone(),
+            id: self.id,
+        }
     }
 }
 
@@ -69,7 +403,7 @@ where
 {
     type Fut = TryBoxFuture<T, Error>;
 
-    fn get(&self) -> Self::Fut {
+    fn get(&self, _: FetchContext) -> Self::Fut {
         let fut = (self)();
         let ret = fut.map_err(|e| e.into());
         Box::pin(ret)
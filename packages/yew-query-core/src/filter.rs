@@ -0,0 +1,78 @@
+use crate::{Key, QueryKey};
+use std::{fmt::Debug, rc::Rc};
+
+/// Selects the queries affected by an operation over multiple queries.
+#[derive(Clone)]
+pub enum QueryFilter {
+    /// Matches all the queries.
+    All,
+
+    /// Matches the queries which key starts with the given prefix.
+    Prefix(Key),
+
+    /// Matches the queries for which the predicate returns `true`.
+    Predicate(Rc<dyn Fn(&QueryKey) -> bool>),
+}
+
+impl QueryFilter {
+    /// Constructs a filter matching all the queries.
+    pub fn all() -> Self {
+        QueryFilter::All
+    }
+
+    /// Constructs a filter matching the queries which key starts with the given prefix.
+    pub fn prefix(prefix: impl Into<Key>) -> Self {
+        QueryFilter::Prefix(prefix.into())
+    }
+
+    /// Constructs a filter matching the queries for which the predicate returns `true`.
+    pub fn predicate<F>(predicate: F) -> Self
+    where
+        F: Fn(&QueryKey) -> bool + 'static,
+    {
+        QueryFilter::Predicate(Rc::new(predicate))
+    }
+
+    /// Returns `true` if the given key matches this filter.
+    pub fn matches(&self, key: &QueryKey) -> bool {
+        match self {
+            QueryFilter::All => true,
+            QueryFilter::Prefix(prefix) => key.key().starts_with(&**prefix),
+            QueryFilter::Predicate(predicate) => predicate(key),
+        }
+    }
+}
+
+impl Debug for QueryFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryFilter::All => write!(f, "All"),
+            QueryFilter::Prefix(prefix) => f.debug_tuple("Prefix").field(prefix).finish(),
+            QueryFilter::Predicate(_) => write!(f, "Predicate"),
+        }
+    }
+}
+
+impl From<&'_ str> for QueryFilter {
+    fn from(prefix: &'_ str) -> Self {
+        QueryFilter::prefix(prefix)
+    }
+}
+
+impl From<String> for QueryFilter {
+    fn from(prefix: String) -> Self {
+        QueryFilter::prefix(prefix)
+    }
+}
+
+impl From<Key> for QueryFilter {
+    fn from(prefix: Key) -> Self {
+        QueryFilter::Prefix(prefix)
+    }
+}
+
+impl From<QueryKey> for QueryFilter {
+    fn from(key: QueryKey) -> Self {
+        QueryFilter::predicate(move |x| x == &key)
+    }
+}
@@ -0,0 +1,105 @@
+use crate::status::ManualStatus;
+use std::{fmt::Debug, rc::Rc};
+
+/// Provides the focus status of the application of a `QueryClient`.
+///
+/// Allows environments without a browser, like desktop shells or tests,
+/// to emit the focus changes programmatically.
+pub trait FocusManager {
+    /// Returns `true` if the application is focused.
+    fn is_focused(&self) -> bool;
+
+    /// Registers a listener called with the new status each time the focus changes.
+    ///
+    /// Returns an id to remove the listener.
+    fn add_listener(&self, listener: Rc<dyn Fn(bool)>) -> usize;
+
+    /// Removes the listener with the given id.
+    fn remove_listener(&self, id: usize) -> bool;
+}
+
+#[derive(Clone)]
+pub(crate) struct FocusManagerHandle(pub Rc<dyn FocusManager>);
+
+impl Debug for FocusManagerHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FocusManager")
+    }
+}
+
+/// A `FocusManager` which status is set manually, starts focused.
+///
+/// The clones share the same status and listeners.
+#[derive(Clone)]
+pub struct ManualFocusManager(ManualStatus);
+
+impl ManualFocusManager {
+    /// Constructs a `ManualFocusManager` with the given status.
+    pub fn new(focused: bool) -> Self {
+        ManualFocusManager(ManualStatus::new(focused))
+    }
+
+    /// Sets the focus status, the listeners are notified if the status changed.
+    pub fn set_focused(&self, focused: bool) {
+        self.0.set(focused);
+    }
+}
+
+impl Default for ManualFocusManager {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+impl FocusManager for ManualFocusManager {
+    fn is_focused(&self) -> bool {
+        self.0.get()
+    }
+
+    fn add_listener(&self, listener: Rc<dyn Fn(bool)>) -> usize {
+        self.0.add_listener(listener)
+    }
+
+    fn remove_listener(&self, id: usize) -> bool {
+        self.0.remove_listener(id)
+    }
+}
+
+impl Debug for ManualFocusManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ManualFocusManager")
+            .field("focused", &self.0.get())
+            .field("listeners", &self.0.listener_count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FocusManager, ManualFocusManager};
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn manual_focus_manager_test() {
+        let manager = ManualFocusManager::default();
+        let events = Rc::new(RefCell::new(Vec::new()));
+
+        let id = {
+            let events = events.clone();
+            manager.add_listener(Rc::new(move |focused| events.borrow_mut().push(focused)))
+        };
+
+        assert!(manager.is_focused());
+
+        manager.set_focused(false);
+        manager.set_focused(false);
+        assert!(!manager.is_focused());
+
+        manager.set_focused(true);
+        assert_eq!(*events.borrow(), vec![false, true]);
+
+        assert!(manager.remove_listener(id));
+        manager.set_focused(false);
+        assert_eq!(*events.borrow(), vec![false, true]);
+    }
+}
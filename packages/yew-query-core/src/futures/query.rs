@@ -1,5 +1,6 @@
 use crate::{Error, QueryChanged, QueryState};
 use futures::Future;
+use instant::Instant;
 use pin_project_lite::pin_project;
 use std::{
     marker::PhantomData,
@@ -47,6 +48,7 @@ where
                     value: None,
                     state: QueryState::Loading,
                     is_fetching: true,
+                    ..Default::default()
                 })
             }
         }
@@ -61,11 +63,15 @@ where
                             value: Some(value),
                             state: QueryState::Ready,
                             is_fetching: false,
+                            data_updated_at: Some(Instant::now()),
+                            ..Default::default()
                         }),
                         Err(err) => callback(QueryChanged {
                             value: None,
                             state: QueryState::Failed(err),
                             is_fetching: false,
+                            error_updated_at: Some(Instant::now()),
+                            ..Default::default()
                         }),
                     }
                 }
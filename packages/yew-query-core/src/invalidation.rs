@@ -0,0 +1,141 @@
+use crate::{QueryFilter, QueryKey};
+use serde::{Deserialize, Serialize};
+
+/// A message pushed by a server notifying that some data changed.
+///
+/// Entities are matched against the query keys using `/` as separator,
+/// the entity `todos` with id `1` matches the keys `todos` and `todos/1/...` but not `todos/2`.
+///
+/// The JSON representation is tagged with a `type` field:
+/// ```json
+/// { "type": "entity", "entity": "todos", "id": "1" }
+/// { "type": "prefix", "prefix": "users/" }
+/// { "type": "all" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InvalidationMessage {
+    /// An entity or all the entities of a kind changed.
+    Entity {
+        /// The kind of the entity.
+        entity: String,
+
+        /// The id of the entity that changed, if `None` all the entities are invalidated.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+    },
+
+    /// The queries which key starts with the prefix changed.
+    Prefix {
+        /// The prefix of the keys.
+        prefix: String,
+    },
+
+    /// All the queries changed.
+    All,
+}
+
+impl InvalidationMessage {
+    /// Constructs a message for all the entities of the given kind.
+    pub fn entity(entity: impl Into<String>) -> Self {
+        InvalidationMessage::Entity {
+            entity: entity.into(),
+            id: None,
+        }
+    }
+
+    /// Constructs a message for the entity with the given id.
+    pub fn entity_id(entity: impl Into<String>, id: impl ToString) -> Self {
+        InvalidationMessage::Entity {
+            entity: entity.into(),
+            id: Some(id.to_string()),
+        }
+    }
+
+    /// Parses a message from JSON.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Returns `true` if the given key is affected by this message.
+    pub fn matches(&self, key: &QueryKey) -> bool {
+        let key: &str = key.key();
+
+        match self {
+            InvalidationMessage::Entity { entity, id } => {
+                let Some(rest) = key.strip_prefix(entity.as_str()) else {
+                    return false;
+                };
+
+                // The collection is affected by any change of its entities
+                if rest.is_empty() {
+                    return true;
+                }
+
+                let Some(rest) = rest.strip_prefix('/') else {
+                    return false;
+                };
+
+                match id {
+                    Some(id) => {
+                        let segment = rest.split('/').next().unwrap_or_default();
+                        segment == id
+                    }
+                    None => true,
+                }
+            }
+            InvalidationMessage::Prefix { prefix } => key.starts_with(prefix.as_str()),
+            InvalidationMessage::All => true,
+        }
+    }
+}
+
+impl From<InvalidationMessage> for QueryFilter {
+    fn from(message: InvalidationMessage) -> Self {
+        QueryFilter::predicate(move |key| message.matches(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InvalidationMessage;
+    use crate::QueryKey;
+
+    #[test]
+    fn parse_invalidation_message_test() {
+        let message =
+            InvalidationMessage::from_json(r#"{ "type": "entity", "entity": "todos", "id": "1" }"#);
+        assert_eq!(message.unwrap(), InvalidationMessage::entity_id("todos", 1));
+
+        let message = InvalidationMessage::from_json(r#"{ "type": "entity", "entity": "todos" }"#);
+        assert_eq!(message.unwrap(), InvalidationMessage::entity("todos"));
+
+        let message = InvalidationMessage::from_json(r#"{ "type": "all" }"#);
+        assert_eq!(message.unwrap(), InvalidationMessage::All);
+
+        assert!(InvalidationMessage::from_json(r#"{ "type": "unknown" }"#).is_err());
+    }
+
+    #[test]
+    fn match_invalidation_message_test() {
+        let key = |k: &str| QueryKey::of::<String>(k);
+
+        let message = InvalidationMessage::entity_id("todos", 1);
+        assert!(message.matches(&key("todos")));
+        assert!(message.matches(&key("todos/1")));
+        assert!(message.matches(&key("todos/1/comments")));
+        assert!(!message.matches(&key("todos/10")));
+        assert!(!message.matches(&key("todos/2")));
+        assert!(!message.matches(&key("todoslist")));
+
+        let message = InvalidationMessage::entity("todos");
+        assert!(message.matches(&key("todos/2")));
+        assert!(!message.matches(&key("users/2")));
+
+        let message = InvalidationMessage::Prefix {
+            prefix: "users/".to_owned(),
+        };
+        assert!(message.matches(&key("users/2")));
+        assert!(!message.matches(&key("todos")));
+    }
+}
@@ -1,8 +1,13 @@
 use std::{
-    any::TypeId,
+    any::{type_name, TypeId},
+    cell::RefCell,
+    collections::HashMap,
     fmt::{self, Debug, Display, Formatter},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
     ops::Deref,
     rc::Rc,
+    str::FromStr,
 };
 use self::x::TypeNameMap;
 
@@ -11,6 +16,17 @@ thread_local! {
     static TYPE_NAMES: TypeNameMap = TypeNameMap::new();
 }
 
+thread_local! {
+    static KEY_TYPES: KeyTypeRegistry = KeyTypeRegistry::default();
+}
+
+// The names registered with `QueryKey::register_type`.
+#[derive(Default)]
+struct KeyTypeRegistry {
+    names: RefCell<HashMap<TypeId, Rc<str>>>,
+    types: RefCell<HashMap<Rc<str>, TypeId>>,
+}
+
 /// An string key to identify a query.
 #[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct Key {
@@ -69,6 +85,234 @@ key_impl_from_to_string!(i64);
 key_impl_from_to_string!(i128);
 key_impl_from_to_string!(isize);
 
+// Separates the parts of a key
+const KEY_PART_SEPARATOR: char = '/';
+
+/// A value that can be part of a `Key`, see `Key::from_parts`.
+///
+/// Implement it for the ids and enums used in the keys, the representation must be unique
+/// for each value to not collide with other keys.
+pub trait QueryKeyPart {
+    /// Writes the representation of this part of the key.
+    fn write_key_part(&self, out: &mut String);
+}
+
+impl<T: QueryKeyPart + ?Sized> QueryKeyPart for &'_ T {
+    fn write_key_part(&self, out: &mut String) {
+        (**self).write_key_part(out)
+    }
+}
+
+impl QueryKeyPart for str {
+    fn write_key_part(&self, out: &mut String) {
+        // A leading `#` is escaped so a string can't be confused with a `Hashed` part
+        if self.starts_with('#') {
+            out.push('\\');
+        }
+
+        // The separators are escaped so a string can't be confused with several parts
+        for c in self.chars() {
+            if c == KEY_PART_SEPARATOR || c == '\\' {
+                out.push('\\');
+            }
+
+            out.push(c);
+        }
+    }
+}
+
+impl QueryKeyPart for String {
+    fn write_key_part(&self, out: &mut String) {
+        self.as_str().write_key_part(out)
+    }
+}
+
+impl QueryKeyPart for char {
+    fn write_key_part(&self, out: &mut String) {
+        self.encode_utf8(&mut [0; 4]).write_key_part(out)
+    }
+}
+
+impl QueryKeyPart for bool {
+    fn write_key_part(&self, out: &mut String) {
+        out.push_str(if *self { "true" } else { "false" });
+    }
+}
+
+// Writes an integer padded with zeros to the max number of digits of its type,
+// so the keys sort like the numbers. The negative numbers are written as `-`
+// and the nines' complement of the digits, so the smaller numbers sort first.
+fn write_int_key_part(out: &mut String, is_negative: bool, abs: u128, width: usize) {
+    let digits = format!("{abs:0width$}");
+    if !is_negative {
+        out.push_str(&digits);
+        return;
+    }
+
+    out.push('-');
+    out.extend(digits.bytes().map(|d| (b'9' - d + b'0') as char));
+}
+
+macro_rules! key_part_impl_unsigned {
+    ($($type:ty => $width:literal),*) => {
+        $(
+            impl QueryKeyPart for $type {
+                fn write_key_part(&self, out: &mut String) {
+                    write_int_key_part(out, false, *self as u128, $width);
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! key_part_impl_signed {
+    ($($type:ty => $width:literal),*) => {
+        $(
+            impl QueryKeyPart for $type {
+                fn write_key_part(&self, out: &mut String) {
+                    let abs = self.unsigned_abs() as u128;
+                    write_int_key_part(out, self.is_negative(), abs, $width);
+                }
+            }
+        )*
+    };
+}
+
+key_part_impl_unsigned!(u8 => 3, u16 => 5, u32 => 10, u64 => 20, u128 => 39, usize => 20);
+key_part_impl_signed!(i8 => 3, i16 => 5, i32 => 10, i64 => 19, i128 => 39, isize => 19);
+
+impl Key {
+    /// Constructs a key from the given parts, the parts are separated by `/`.
+    ///
+    /// The separators within a part are escaped, so different parts never produce the same key
+    /// and the keys sharing the first parts can be matched with `QueryFilter::prefix`.
+    ///
+    /// The integers are padded with zeros, so the keys sort like the numbers,
+    /// for example `("todos", 9_u32)` is `todos/0000000009`.
+    pub fn from_parts(parts: &[&dyn QueryKeyPart]) -> Self {
+        let mut key = String::new();
+        for (idx, part) in parts.iter().enumerate() {
+            if idx > 0 {
+                key.push(KEY_PART_SEPARATOR);
+            }
+
+            part.write_key_part(&mut key);
+        }
+
+        Self::from(key.as_str())
+    }
+}
+
+/// A part of a key represented by the hash of the value, see `Key::hashed`.
+///
+/// Useful to include large parameters in a key, like the filters of a search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hashed<T>(pub T);
+
+impl<T: Hash> QueryKeyPart for Hashed<T> {
+    fn write_key_part(&self, out: &mut String) {
+        use std::fmt::Write;
+
+        let mut hasher = StableHasher::new();
+        self.0.hash(&mut hasher);
+        write!(out, "#{:016x}", hasher.finish()).expect("failed to write key part");
+    }
+}
+
+impl Key {
+    /// Constructs a key from the hash of the given value.
+    ///
+    /// The hash is the same on every platform and run, so can be used for persisted queries.
+    /// Use `Hashed` to combine the hash with other parts of a key.
+    pub fn hashed<T: Hash + ?Sized>(value: &T) -> Self {
+        Self::from_parts(&[&Hashed(value)])
+    }
+}
+
+// A FNV-1a hasher which output don't depend on the platform or the run,
+// unlike the `DefaultHasher` of the std.
+struct StableHasher(u64);
+
+impl StableHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    fn new() -> Self {
+        StableHasher(Self::OFFSET_BASIS)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    // The integers are written in little-endian and the sizes as 64 bits on every platform
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64)
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16)
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32)
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64)
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128)
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as i64 as u64)
+    }
+}
+
+macro_rules! key_impl_from_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: QueryKeyPart),+> From<($($name,)+)> for Key {
+            #[allow(non_snake_case)]
+            fn from(($($name,)+): ($($name,)+)) -> Self {
+                Self::from_parts(&[$(&$name),+])
+            }
+        }
+    };
+}
+
+key_impl_from_tuple!(A);
+key_impl_from_tuple!(A, B);
+key_impl_from_tuple!(A, B, C);
+key_impl_from_tuple!(A, B, C, D);
+key_impl_from_tuple!(A, B, C, D, E);
+
 /// Represents a type that identifies a query by key and type.
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct QueryKey {
@@ -90,6 +334,45 @@ impl QueryKey {
         }
     }
 
+    /// Registers a stable name for the type `T`, used to display the keys of the type
+    /// as `name:key` and parse them back, for example from a persistence layer.
+    ///
+    /// Registering the type again with other name replaces the previous name,
+    /// which no longer parses.
+    ///
+    /// The names are registered per thread, so the types must be registered in each thread
+    /// that displays or parses the keys, like the thread of a `SyncQueryClient`,
+    /// where they can be registered in the function passed to `SyncQueryClient::new`.
+    ///
+    /// # Panics
+    /// If the name contains a `:` or is already registered for other type.
+    pub fn register_type<T: 'static>(name: &str) {
+        assert!(!name.contains(':'), "key type names cannot contain `:`: `{name}`");
+
+        #[cfg(debug_assertions)]
+        {
+            TYPE_NAMES.with(|x| x.register::<T>());
+        }
+
+        let ty = TypeId::of::<T>();
+        KEY_TYPES.with(|x| {
+            let mut types = x.types.borrow_mut();
+            if let Some(other) = types.get(name) {
+                assert!(*other == ty, "key type name `{name}` is already registered");
+            }
+
+            let name: Rc<str> = Rc::from(name);
+            types.insert(name.clone(), ty);
+
+            // A renamed type don't parse with the previous name
+            if let Some(previous) = x.names.borrow_mut().insert(ty, name.clone()) {
+                if previous != name {
+                    types.remove(&previous);
+                }
+            }
+        });
+    }
+
     /// Returns `true` if the key is for the given type.
     pub fn is_type<T: 'static>(&self) -> bool {
         TypeId::of::<T>() == self.ty
@@ -104,14 +387,76 @@ impl QueryKey {
     pub fn type_id(&self) -> TypeId {
         self.ty
     }
+
+    // Returns this key with the given prefix prepended, keeping the type.
+    pub(crate) fn with_prefix(&self, prefix: &str) -> QueryKey {
+        QueryKey {
+            key: Key::from(format!("{prefix}{}", self.key)),
+            ty: self.ty,
+        }
+    }
+
+    // Returns this key without the given prefix, or `None` if don't starts with the prefix.
+    pub(crate) fn strip_prefix(&self, prefix: &str) -> Option<QueryKey> {
+        let key = self.key.strip_prefix(prefix)?;
+        Some(QueryKey {
+            key: Key::from(key),
+            ty: self.ty,
+        })
+    }
 }
 
+/// Displays the key as `name:key` if the type was registered with `QueryKey::register_type`,
+/// which can be parsed back with `FromStr`, otherwise only the key is displayed.
 impl Display for QueryKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", &self.key)
+        let name = KEY_TYPES.with(|x| x.names.borrow().get(&self.ty).cloned());
+        match name {
+            Some(name) => write!(f, "{name}:{}", &self.key),
+            None => write!(f, "{}", &self.key),
+        }
+    }
+}
+
+/// Parses a key displayed as `name:key`, the type is resolved from the name
+/// registered with `QueryKey::register_type`.
+impl FromStr for QueryKey {
+    type Err = ParseQueryKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, key) = s.split_once(':').ok_or(ParseQueryKeyError::MissingType)?;
+        let ty = KEY_TYPES
+            .with(|x| x.types.borrow().get(name).copied())
+            .ok_or_else(|| ParseQueryKeyError::UnknownType(name.to_owned()))?;
+
+        Ok(QueryKey {
+            key: Key::from(key),
+            ty,
+        })
+    }
+}
+
+/// An error returned when parsing a `QueryKey`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseQueryKeyError {
+    /// The text don't have the `name:` of the type.
+    MissingType,
+
+    /// The name of the type was not registered with `QueryKey::register_type`.
+    UnknownType(String),
+}
+
+impl Display for ParseQueryKeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseQueryKeyError::MissingType => write!(f, "missing the type of the key"),
+            ParseQueryKeyError::UnknownType(name) => write!(f, "unknown key type `{name}`"),
+        }
     }
 }
 
+impl std::error::Error for ParseQueryKeyError {}
+
 impl Debug for QueryKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut debug_struct = f.debug_struct("QueryKey");
@@ -129,6 +474,97 @@ impl Debug for QueryKey {
     }
 }
 
+/// A key bound to the type of its value at compile time.
+///
+/// Defining the keys in a single place, the hooks accepting a `TypedQueryKey`
+/// only compile with fetchers returning values of the type of the key.
+///
+/// # Example
+/// ```
+/// use yew_query_core::{QueryKey, TypedQueryKey};
+///
+/// fn todo_key(id: u64) -> TypedQueryKey<String> {
+///     TypedQueryKey::new(("todos", id))
+/// }
+///
+/// let key: QueryKey = todo_key(1).into();
+/// assert!(key.is_type::<String>());
+/// ```
+pub struct TypedQueryKey<T> {
+    key: Key,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: 'static> TypedQueryKey<T> {
+    /// Constructs a `TypedQueryKey` for values of type `T`.
+    pub fn new(key: impl Into<Key>) -> Self {
+        TypedQueryKey {
+            key: key.into(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the key of this typed key.
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    /// Returns the `QueryKey` of this typed key.
+    pub fn query_key(&self) -> QueryKey {
+        QueryKey::of::<T>(self.key.clone())
+    }
+}
+
+impl<T: 'static> From<TypedQueryKey<T>> for QueryKey {
+    fn from(key: TypedQueryKey<T>) -> Self {
+        key.query_key()
+    }
+}
+
+impl<T: 'static> From<&'_ TypedQueryKey<T>> for QueryKey {
+    fn from(key: &'_ TypedQueryKey<T>) -> Self {
+        key.query_key()
+    }
+}
+
+impl<T> Clone for TypedQueryKey<T> {
+    fn clone(&self) -> Self {
+        TypedQueryKey {
+            key: self.key.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> PartialEq for TypedQueryKey<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<T> Eq for TypedQueryKey<T> {}
+
+impl<T> Hash for TypedQueryKey<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
+}
+
+impl<T> Display for TypedQueryKey<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", &self.key)
+    }
+}
+
+impl<T> Debug for TypedQueryKey<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedQueryKey")
+            .field("key", &self.key)
+            .field("ty", &type_name::<T>())
+            .finish()
+    }
+}
+
 #[cfg(debug_assertions)]
 mod x {
     use std::{
@@ -161,3 +597,127 @@ mod x {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Hashed, Key, ParseQueryKeyError, QueryKey, QueryKeyPart, TypedQueryKey};
+
+    #[derive(Clone, Copy)]
+    enum Status {
+        Open,
+        Closed,
+    }
+
+    impl QueryKeyPart for Status {
+        fn write_key_part(&self, out: &mut String) {
+            out.push_str(match self {
+                Status::Open => "open",
+                Status::Closed => "closed",
+            });
+        }
+    }
+
+    #[test]
+    fn key_from_parts_test() {
+        assert_eq!(&*Key::from(("todos", 42_u32)), "todos/0000000042");
+        assert_eq!(&*Key::from(("todos", Status::Open, true)), "todos/open/true");
+        assert_eq!(&*Key::from(("todos", Status::Closed)), "todos/closed");
+
+        // The separators inside a part are escaped
+        assert_eq!(&*Key::from(("a/b",)), "a\\/b");
+        assert_ne!(Key::from(("a/b",)), Key::from(("a", "b")));
+        assert_ne!(Key::from(("a\\", "b")), Key::from(("a\\/b",)));
+
+        // A string part can't be confused with a hashed part
+        let hash = Key::hashed("rust");
+        assert_ne!(Key::from((&*hash,)), hash);
+    }
+
+    #[test]
+    fn key_part_ordering_test() {
+        assert!(Key::from(("todos", 9_u64)) < Key::from(("todos", 10_u64)));
+        assert!(Key::from(("todos", u64::MAX - 1)) < Key::from(("todos", u64::MAX)));
+
+        let numbers = [i64::MIN, -100, -10, -9, -1, 0, 1, 9, 10, i64::MAX];
+        let keys: Vec<Key> = numbers.iter().map(|x| Key::from(("n", *x))).collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(keys, sorted);
+
+        assert_eq!(&*Key::from((i8::MIN,)), "-871");
+        assert_eq!(&*Key::from((-1_i8,)), "-998");
+    }
+
+    #[derive(Hash)]
+    struct Search<'a> {
+        query: &'a str,
+        tags: Vec<&'a str>,
+        page: usize,
+    }
+
+    #[test]
+    fn hashed_key_test() {
+        let search = Search {
+            query: "rust",
+            tags: vec!["async", "wasm"],
+            page: 2,
+        };
+
+        let key = Key::from(("search", Hashed(&search)));
+        assert!(key.starts_with("search/#"));
+        assert_eq!(key.len(), "search/#".len() + 16);
+        assert_eq!(key, Key::from(("search", Hashed(&search))));
+
+        // The hash is stable between runs and platforms
+        assert_eq!(&*Key::hashed("rust"), "#a4dcfa6a4ba59408");
+        assert_ne!(Key::hashed(&1_u32), Key::hashed(&2_u32));
+    }
+
+    #[test]
+    fn typed_query_key_test() {
+        let key = TypedQueryKey::<Vec<u64>>::new(("todos", 1_u8));
+        assert_eq!(&**key.key(), "todos/001");
+
+        let query_key = QueryKey::from(&key);
+        assert!(query_key.is_type::<Vec<u64>>());
+        assert_eq!(query_key, QueryKey::of::<Vec<u64>>("todos/001"));
+        assert_eq!(key.clone(), key);
+    }
+
+    #[test]
+    fn parse_query_key_test() {
+        #[derive(Debug)]
+        struct Todo;
+
+        QueryKey::register_type::<Todo>("todo");
+
+        let key = QueryKey::of::<Todo>("todos/1:2");
+        assert_eq!(key.to_string(), "todo:todos/1:2");
+        assert_eq!(key.to_string().parse::<QueryKey>(), Ok(key));
+
+        assert_eq!(
+            "user:1".parse::<QueryKey>(),
+            Err(ParseQueryKeyError::UnknownType("user".to_owned()))
+        );
+        assert_eq!("todos".parse::<QueryKey>(), Err(ParseQueryKeyError::MissingType));
+
+        // The keys of types not registered only display the key
+        assert_eq!(QueryKey::of::<u32>("todos").to_string(), "todos");
+    }
+
+    #[test]
+    fn rename_key_type_test() {
+        struct Task;
+
+        QueryKey::register_type::<Task>("job");
+        QueryKey::register_type::<Task>("task");
+
+        let key = QueryKey::of::<Task>("tasks");
+        assert_eq!(key.to_string(), "task:tasks");
+        assert_eq!("task:tasks".parse::<QueryKey>(), Ok(key));
+        assert_eq!(
+            "job:tasks".parse::<QueryKey>(),
+            Err(ParseQueryKeyError::UnknownType("job".to_owned()))
+        );
+    }
+}
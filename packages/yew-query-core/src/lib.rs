@@ -1,21 +1,56 @@
 mod cache;
+mod cancellation;
 mod client;
+mod filter;
+mod focus;
+mod invalidation;
 mod key;
+mod memory;
+mod meta;
+mod notify;
 mod observer;
+mod online;
 mod options;
 mod query;
+mod snapshot;
+mod spawner;
 mod state;
+mod stats;
+mod suspension;
+mod timer;
+mod transaction;
+mod watch;
 
-pub use {cache::*, client::*, key::*, observer::*, options::*, query::*, state::*};
+pub use {
+    cache::*, cancellation::*, client::*, filter::*, focus::*, invalidation::*, key::*, memory::*,
+    meta::*, notify::*, observer::*, online::*, options::*, query::*, snapshot::*, spawner::*,
+    state::*, stats::*, suspension::*, timer::*, transaction::*, watch::*,
+};
 
 //
 pub mod fetcher;
+pub mod middleware;
 pub mod retry;
 
+//
+#[cfg(all(feature = "sync", not(target_arch = "wasm32")))]
+mod sync;
+
+#[cfg(all(feature = "sync", not(target_arch = "wasm32")))]
+pub use sync::*;
+
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+mod blocking;
+
 //
 pub mod error;
 pub use error::Error;
 
 //
+pub(crate) mod dedupe;
+pub(crate) mod limiter;
+#[cfg(feature = "perf-marks")]
+pub(crate) mod perf;
+pub(crate) mod status;
 pub(crate) mod time;
 pub(crate) mod futures;
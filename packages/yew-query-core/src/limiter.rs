@@ -0,0 +1,268 @@
+use crate::{timer::TimerHandle, FetchPriority, QueryKey};
+use futures::channel::oneshot;
+use instant::{Duration, Instant};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    rc::Rc,
+};
+
+pub(crate) type OnQueued = Rc<dyn Fn(bool)>;
+
+struct LimiterInner {
+    max: usize,
+    running: usize,
+    queue: VecDeque<(FetchPriority, oneshot::Sender<FetchPermit>)>,
+}
+
+// Limits the number of fetches running at the same time in a client,
+// the fetches over the limit wait in a queue until a running fetch completes.
+#[derive(Clone)]
+pub(crate) struct FetchLimiter(Rc<RefCell<LimiterInner>>);
+
+impl PartialEq for FetchLimiter {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl FetchLimiter {
+    pub fn new(max: usize) -> Self {
+        assert!(
+            max > 0,
+            "the max number of concurrent fetches must be greater than 0"
+        );
+
+        FetchLimiter(Rc::new(RefCell::new(LimiterInner {
+            max,
+            running: 0,
+            queue: VecDeque::new(),
+        })))
+    }
+
+    // Waits until a fetch can run, `on_queued` is called with `true` if the fetch
+    // must wait in the queue and with `false` when leaves the queue.
+    //
+    // The fetches with higher priority leave the queue first,
+    // the fetches with the same priority leave in the order they entered.
+    pub async fn acquire(
+        &self,
+        priority: FetchPriority,
+        on_queued: Option<&OnQueued>,
+    ) -> FetchPermit {
+        let receiver = {
+            let mut inner = self.0.borrow_mut();
+            if inner.running < inner.max {
+                inner.running += 1;
+                return FetchPermit(Some(self.clone()));
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            let idx = inner.queue.partition_point(|(x, _)| *x >= priority);
+            inner.queue.insert(idx, (priority, sender));
+            receiver
+        };
+
+        if let Some(on_queued) = on_queued {
+            on_queued(true);
+        }
+
+        // The limiter is owned by the permits, so the sender is never dropped
+        let permit = receiver.await.expect("fetch limiter was dropped");
+
+        if let Some(on_queued) = on_queued {
+            on_queued(false);
+        }
+
+        permit
+    }
+
+    fn release(&self) {
+        let mut inner = self.0.borrow_mut();
+
+        // The permit is passed to the next fetch in the queue, a cancelled fetch
+        // already dropped its receiver, and the permit returns to this loop
+        while let Some((_, sender)) = inner.queue.pop_front() {
+            match sender.send(FetchPermit(Some(self.clone()))) {
+                Ok(()) => return,
+                Err(mut permit) => {
+                    permit.0.take();
+                }
+            }
+        }
+
+        inner.running -= 1;
+    }
+
+    #[cfg(test)]
+    fn running(&self) -> usize {
+        self.0.borrow().running
+    }
+}
+
+impl Debug for FetchLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.0.borrow();
+        f.debug_struct("FetchLimiter")
+            .field("max", &inner.max)
+            .field("running", &inner.running)
+            .field("queued", &inner.queue.len())
+            .finish()
+    }
+}
+
+// Allows a fetch to run, the next fetch in the queue runs when dropped.
+pub(crate) struct FetchPermit(Option<FetchLimiter>);
+
+impl Drop for FetchPermit {
+    fn drop(&mut self) {
+        if let Some(limiter) = self.0.take() {
+            limiter.release();
+        }
+    }
+}
+
+// Keeps the time each key can be fetched again, used for the min interval
+// between the fetches of a key.
+#[derive(Clone, Default)]
+pub(crate) struct RateLimiter(Rc<RefCell<HashMap<QueryKey, Instant>>>);
+
+impl PartialEq for RateLimiter {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl RateLimiter {
+    // Waits until the key can be fetched, then reserves the interval after for this fetch.
+    pub async fn wait(&self, key: &QueryKey, interval: Duration, timer: &TimerHandle) {
+        let mut waited_for = None;
+
+        loop {
+            let now = Instant::now();
+            let (next_fetch, delay) = {
+                let mut next_fetches = self.0.borrow_mut();
+                let next_fetch = next_fetches.get(key).copied();
+
+                // Other fetch waiting for the same time takes the slot and we wait again,
+                // we don't wait twice for the same time in case the timer is not the clock
+                match next_fetch {
+                    Some(at) if at > now && waited_for != Some(at) => (at, at - now),
+                    _ => {
+                        let start = next_fetch.map_or(now, |at| at.max(now));
+                        next_fetches.retain(|_, at| *at > now);
+                        next_fetches.insert(key.clone(), start + interval);
+                        return;
+                    }
+                }
+            };
+
+            waited_for = Some(next_fetch);
+            timer.sleep(delay).await;
+        }
+    }
+}
+
+impl Debug for RateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("keys", &self.0.borrow().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FetchLimiter;
+    use crate::FetchPriority;
+    use futures::FutureExt;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn fetch_limiter_test() {
+        let limiter = FetchLimiter::new(2);
+        let queued = Rc::new(RefCell::new(Vec::new()));
+        let on_queued = {
+            let queued = queued.clone();
+            Rc::new(move |x| queued.borrow_mut().push(x)) as super::OnQueued
+        };
+
+        let a = limiter
+            .acquire(FetchPriority::NORMAL, Some(&on_queued))
+            .now_or_never()
+            .unwrap();
+        let b = limiter
+            .acquire(FetchPriority::NORMAL, Some(&on_queued))
+            .now_or_never()
+            .unwrap();
+        assert!(queued.borrow().is_empty());
+
+        // The fetches over the limit wait for a permit
+        let mut c = limiter
+            .acquire(FetchPriority::NORMAL, Some(&on_queued))
+            .boxed_local();
+        let mut d = limiter.acquire(FetchPriority::NORMAL, None).boxed_local();
+        assert!((&mut c).now_or_never().is_none());
+        assert!((&mut d).now_or_never().is_none());
+        assert_eq!(*queued.borrow(), vec![true]);
+
+        // A cancelled fetch don't keep its place in the queue
+        drop(d);
+        drop(a);
+        let c = c.now_or_never().unwrap();
+        assert_eq!(*queued.borrow(), vec![true, false]);
+        assert_eq!(limiter.running(), 2);
+
+        drop(b);
+        drop(c);
+        assert_eq!(limiter.running(), 0);
+    }
+
+    #[test]
+    fn fetch_priority_test() {
+        let limiter = FetchLimiter::new(1);
+        let order = Rc::new(RefCell::new(Vec::new()));
+        let running = limiter
+            .acquire(FetchPriority::NORMAL, None)
+            .now_or_never()
+            .unwrap();
+
+        let mut fetches = [
+            (1, FetchPriority::LOW),
+            (2, FetchPriority::NORMAL),
+            (3, FetchPriority::HIGH),
+            (4, FetchPriority::NORMAL),
+        ]
+        .map(|(id, priority)| {
+            let limiter = limiter.clone();
+            let order = order.clone();
+            async move {
+                let _permit = limiter.acquire(priority, None).await;
+                order.borrow_mut().push(id);
+            }
+            .boxed_local()
+        })
+        .map(Some);
+
+        let mut poll_all = || {
+            for fetch in fetches.iter_mut() {
+                if let Some(fut) = fetch {
+                    if fut.now_or_never().is_some() {
+                        *fetch = None;
+                    }
+                }
+            }
+        };
+
+        poll_all();
+        assert!(order.borrow().is_empty());
+
+        drop(running);
+        for _ in 0..4 {
+            poll_all();
+        }
+
+        assert_eq!(*order.borrow(), vec![3, 2, 4, 1]);
+    }
+}
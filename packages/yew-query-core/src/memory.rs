@@ -0,0 +1,55 @@
+use instant::Duration;
+
+/// Evicts the inactive queries of a `QueryClient` when the heap grows over a limit.
+///
+/// The heap of a wasm module never shrinks, so once over the limit the queries are trimmed
+/// on each check. Outside wasm or without the `web` feature the heap size is unknown
+/// and this has no effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryPressure {
+    pub(crate) max_heap_size: usize,
+    pub(crate) target: usize,
+    pub(crate) check_interval: Duration,
+}
+
+impl MemoryPressure {
+    /// Constructs a new `MemoryPressure` that keeps at most `target` queries
+    /// when the heap is larger than `max_heap_size` bytes.
+    pub fn new(max_heap_size: usize, target: usize) -> Self {
+        MemoryPressure {
+            max_heap_size,
+            target,
+            check_interval: Duration::from_secs(10),
+        }
+    }
+
+    /// Sets how often the heap size is checked, by default every 10 seconds.
+    pub fn check_interval(mut self, check_interval: Duration) -> Self {
+        self.check_interval = check_interval;
+        self
+    }
+
+    pub(crate) fn is_over_limit(&self) -> bool {
+        match heap_size() {
+            Some(size) => size > self.max_heap_size,
+            None => false,
+        }
+    }
+}
+
+/// Returns the size in bytes of the wasm heap.
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+pub fn heap_size() -> Option<usize> {
+    use wasm_bindgen::JsCast;
+
+    let memory = wasm_bindgen::memory().dyn_into::<js_sys::WebAssembly::Memory>().ok()?;
+    let buffer = memory.buffer().dyn_into::<js_sys::ArrayBuffer>().ok()?;
+    Some(buffer.byte_length() as usize)
+}
+
+/// Returns the size in bytes of the wasm heap, outside wasm or without the `web` feature
+/// is always `None`.
+#[cfg(not(all(feature = "web", target_arch = "wasm32")))]
+pub fn heap_size() -> Option<usize> {
+    None
+}
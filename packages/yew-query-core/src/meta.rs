@@ -0,0 +1,50 @@
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+};
+
+/// Arbitrary user metadata attached to a query.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct QueryMeta(HashMap<String, Value>);
+
+impl QueryMeta {
+    /// Constructs an empty `QueryMeta`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds the given entry to the metadata.
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.0.insert(key.into(), value.into());
+        self
+    }
+
+    /// Returns a new `QueryMeta` with the entries of this and the other metadata,
+    /// the entries of `other` take precedence.
+    pub fn merge(&self, other: &QueryMeta) -> QueryMeta {
+        let mut meta = self.clone();
+        meta.extend(other.iter().map(|(k, v)| (k.clone(), v.clone())));
+        meta
+    }
+}
+
+impl Deref for QueryMeta {
+    type Target = HashMap<String, Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for QueryMeta {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<HashMap<String, Value>> for QueryMeta {
+    fn from(map: HashMap<String, Value>) -> Self {
+        QueryMeta(map)
+    }
+}
@@ -0,0 +1,249 @@
+use crate::{
+    error::{Error, QueryError},
+    fetcher::{BoxFetcher, Fetch, FetchContext},
+    options::ValidatorHandle,
+};
+use futures::{
+    future::{LocalBoxFuture, Shared},
+    Future, FutureExt, TryFutureExt,
+};
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    fmt::Debug,
+    rc::Rc,
+};
+
+/// The type-erased value returned by a fetch while passing through the middlewares.
+pub type FetchOutput = Box<dyn Any>;
+
+type BoxFetch = Rc<dyn Fn(FetchContext) -> LocalBoxFuture<'static, Result<FetchOutput, Error>>>;
+
+/// A function that wraps every fetch made by a `QueryClient`.
+///
+/// Middlewares are called in the order they were registered, each one decides
+/// when to call the rest of the chain using the given `Next`.
+pub trait Middleware {
+    /// Handles the fetch with the given context.
+    fn call(
+        &self,
+        ctx: FetchContext,
+        next: Next,
+    ) -> LocalBoxFuture<'static, Result<FetchOutput, Error>>;
+}
+
+impl<F, Fut> Middleware for F
+where
+    F: Fn(FetchContext, Next) -> Fut,
+    Fut: Future<Output = Result<FetchOutput, Error>> + 'static,
+{
+    fn call(
+        &self,
+        ctx: FetchContext,
+        next: Next,
+    ) -> LocalBoxFuture<'static, Result<FetchOutput, Error>> {
+        (self)(ctx, next).boxed_local()
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct MiddlewareHandle(Rc<dyn Middleware>);
+
+impl PartialEq for MiddlewareHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl MiddlewareHandle {
+    pub fn new<M>(middleware: M) -> Self
+    where
+        M: Middleware + 'static,
+    {
+        MiddlewareHandle(Rc::new(middleware))
+    }
+}
+
+impl Debug for MiddlewareHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Middleware")
+    }
+}
+
+/// The rest of the middleware chain, including the fetcher.
+#[derive(Clone)]
+pub struct Next {
+    middlewares: Rc<[MiddlewareHandle]>,
+    index: usize,
+    fetcher: BoxFetch,
+}
+
+impl Next {
+    /// Runs the next middleware of the chain or the fetcher if there is no more middlewares.
+    pub fn run(self, ctx: FetchContext) -> LocalBoxFuture<'static, Result<FetchOutput, Error>> {
+        match self.middlewares.get(self.index).cloned() {
+            Some(middleware) => {
+                let next = Next {
+                    middlewares: self.middlewares,
+                    index: self.index + 1,
+                    fetcher: self.fetcher,
+                };
+
+                middleware.0.call(ctx, next)
+            }
+            None => (self.fetcher)(ctx),
+        }
+    }
+}
+
+impl Debug for Next {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Next")
+            .field("remaining", &(self.middlewares.len() - self.index))
+            .finish()
+    }
+}
+
+type RefreshFuture = Shared<LocalBoxFuture<'static, Result<(), Error>>>;
+
+/// A middleware that refreshes the authentication when a fetch is unauthorized.
+///
+/// When a fetch fails with an error classified as unauthorized, the refresh future
+/// is executed and the original fetch is replayed once. Concurrent fetches that fail
+/// while a refresh is in progress wait for the same refresh instead of starting a new one,
+/// and the fetches started before the last refresh completed reuse its result.
+#[derive(Clone)]
+pub struct AuthRefresh {
+    is_unauthorized: Rc<dyn Fn(&Error) -> bool>,
+    refresh: Rc<dyn Fn() -> LocalBoxFuture<'static, Result<(), Error>>>,
+    pending: Rc<RefCell<Option<RefreshFuture>>>,
+    generation: Rc<Cell<u64>>,
+}
+
+impl AuthRefresh {
+    /// Constructs a new `AuthRefresh` using a function to classify the unauthorized errors
+    /// and the function that refresh the authentication.
+    pub fn new<C, R, Fut, E>(is_unauthorized: C, refresh: R) -> Self
+    where
+        C: Fn(&Error) -> bool + 'static,
+        R: Fn() -> Fut + 'static,
+        Fut: Future<Output = Result<(), E>> + 'static,
+        E: Into<Error> + 'static,
+    {
+        AuthRefresh {
+            is_unauthorized: Rc::new(is_unauthorized),
+            refresh: Rc::new(move || refresh().map_err(Into::into).boxed_local()),
+            pending: Default::default(),
+            generation: Default::default(),
+        }
+    }
+
+    // Returns the refresh in progress, or the last refresh if completed after the fetch
+    // of the given generation started, otherwise starts a new one.
+    fn refresh(&self, generation: u64) -> RefreshFuture {
+        let mut pending = self.pending.borrow_mut();
+
+        match pending.as_ref() {
+            Some(fut) if fut.peek().is_none() || self.generation.get() > generation => fut.clone(),
+            _ => {
+                let refresh = (self.refresh)();
+                let generation = self.generation.clone();
+                let fut = async move {
+                    let ret = refresh.await;
+                    generation.set(generation.get() + 1);
+                    ret
+                }
+                .boxed_local()
+                .shared();
+
+                *pending = Some(fut.clone());
+                fut
+            }
+        }
+    }
+}
+
+impl Middleware for AuthRefresh {
+    fn call(
+        &self,
+        ctx: FetchContext,
+        next: Next,
+    ) -> LocalBoxFuture<'static, Result<FetchOutput, Error>> {
+        let this = self.clone();
+
+        Box::pin(async move {
+            let generation = this.generation.get();
+            match next.clone().run(ctx.clone()).await {
+                Err(err) if (this.is_unauthorized)(&err) => {}
+                ret => return ret,
+            }
+
+            this.refresh(generation).await?;
+            next.run(ctx).await
+        })
+    }
+}
+
+impl Debug for AuthRefresh {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AuthRefresh")
+    }
+}
+
+// Wraps the fetcher with the validator of the values, if any.
+pub(crate) fn apply_validator<F, T>(fetcher: F, validator: Option<ValidatorHandle>) -> BoxFetcher<T>
+where
+    F: Fetch<T> + 'static,
+    T: 'static,
+{
+    let Some(validator) = validator else {
+        return BoxFetcher::with_context(move |ctx| fetcher.get(ctx));
+    };
+
+    BoxFetcher::with_context(move |ctx| {
+        let validator = validator.clone();
+        fetcher.get(ctx).map(move |ret| {
+            let value = ret?;
+            match validator.validate(&value) {
+                Ok(()) => Ok(value),
+                Err(err) => Err(Error::new(QueryError::Validation(err))),
+            }
+        })
+    })
+}
+
+// Wraps the fetcher with the given middlewares.
+pub(crate) fn apply_middlewares<F, T>(fetcher: F, middlewares: &[MiddlewareHandle]) -> BoxFetcher<T>
+where
+    F: Fetch<T> + 'static,
+    T: 'static,
+{
+    if middlewares.is_empty() {
+        return BoxFetcher::with_context(move |ctx| fetcher.get(ctx));
+    }
+
+    let fetcher: BoxFetch = Rc::new(move |ctx| {
+        fetcher
+            .get(ctx)
+            .map_ok(|x| Box::new(x) as FetchOutput)
+            .boxed_local()
+    });
+
+    let middlewares: Rc<[MiddlewareHandle]> = middlewares.into();
+
+    BoxFetcher::with_context(move |ctx| {
+        let next = Next {
+            middlewares: middlewares.clone(),
+            index: 0,
+            fetcher: fetcher.clone(),
+        };
+
+        next.run(ctx).map(|ret| {
+            ret.and_then(|x| {
+                x.downcast::<T>()
+                    .map(|x| *x)
+                    .map_err(|_| QueryError::type_mismatch::<T>().into())
+            })
+        })
+    })
+}
@@ -0,0 +1,124 @@
+use std::cell::RefCell;
+
+thread_local! {
+    static SCHEDULER: RefCell<NotifyScheduler> = RefCell::new(NotifyScheduler::default());
+}
+
+type Notification = Box<dyn FnOnce()>;
+
+// Holds the notifications sent while a batch is running.
+#[derive(Default)]
+struct NotifyScheduler {
+    depth: usize,
+    queue: Vec<(Option<usize>, Notification)>,
+}
+
+/// Runs the function batching the notifications of the queries changed inside it.
+///
+/// The notifications are delivered when the outermost batch ends,
+/// and each subscriber only receives the last event sent to it.
+pub fn batch<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    SCHEDULER.with(|scheduler| scheduler.borrow_mut().depth += 1);
+
+    let ret = f();
+
+    let queue = SCHEDULER.with(|scheduler| {
+        let mut scheduler = scheduler.borrow_mut();
+        scheduler.depth -= 1;
+
+        if scheduler.depth == 0 {
+            std::mem::take(&mut scheduler.queue)
+        } else {
+            Vec::new()
+        }
+    });
+
+    // Notifications may start other batches
+    for (_, notification) in queue {
+        notification();
+    }
+
+    ret
+}
+
+/// Returns `true` if the notifications are being batched.
+pub fn is_batching() -> bool {
+    SCHEDULER.with(|scheduler| scheduler.borrow().depth > 0)
+}
+
+// Runs the notification or queues it if a batch is running,
+// replacing the notification queued with the same id.
+pub(crate) fn schedule<F>(id: usize, notification: F)
+where
+    F: FnOnce() + 'static,
+{
+    let notification = SCHEDULER.with(|scheduler| {
+        let mut scheduler = scheduler.borrow_mut();
+        if scheduler.depth == 0 {
+            return Some(notification);
+        }
+
+        let queue = &mut scheduler.queue;
+        queue.retain(|(x, _)| *x != Some(id));
+        queue.push((Some(id), Box::new(notification)));
+        None
+    });
+
+    if let Some(notification) = notification {
+        notification();
+    }
+}
+
+// Runs the notification or queues it until the batch ends, if a batch is running,
+// unlike `schedule` all the notifications queued are delivered.
+pub(crate) fn defer<F>(notification: F)
+where
+    F: FnOnce() + 'static,
+{
+    let notification = SCHEDULER.with(|scheduler| {
+        let mut scheduler = scheduler.borrow_mut();
+        if scheduler.depth == 0 {
+            return Some(notification);
+        }
+
+        scheduler.queue.push((None, Box::new(notification)));
+        None
+    });
+
+    if let Some(notification) = notification {
+        notification();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{batch, is_batching, schedule};
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn batch_notifications_test() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let push = |id: usize, value: &'static str| {
+            let events = events.clone();
+            schedule(id, move || events.borrow_mut().push(value));
+        };
+
+        push(1, "a");
+        assert_eq!(*events.borrow(), vec!["a"]);
+
+        batch(|| {
+            push(1, "b");
+            push(2, "c");
+
+            batch(|| push(1, "d"));
+            assert!(is_batching());
+            assert_eq!(*events.borrow(), vec!["a"]);
+        });
+
+        assert!(!is_batching());
+        assert_eq!(*events.borrow(), vec!["a", "c", "d"]);
+    }
+}
@@ -1,12 +1,18 @@
-use futures::Future;
-use prokio::spawn_local;
-use std::{marker::PhantomData, rc::Rc};
+use instant::Instant;
+use std::{
+    cell::{Cell, RefCell},
+    marker::PhantomData,
+    rc::Rc,
+};
 
 use crate::{
     client::QueryClient,
+    fetcher::{BoxFetcher, Fetch, Progress},
     key::{Key, QueryKey},
-    state::QueryState,
-    Error, QueryChanged, QueryOptions,
+    query::Subscription,
+    retry::RetryProgress,
+    state::{FetchStatus, QueryState},
+    FetchCause, QueryChanged, QueryOptions,
 };
 
 /// An event emitted when executing a query.
@@ -17,14 +23,47 @@ pub struct QueryChangeEvent<T> {
     /// Whether if is fetching the data.
     pub is_fetching: bool,
 
+    /// Whether the fetch is waiting for other fetches to complete before start,
+    /// see `QueryClientBuilder::max_concurrent_fetches`.
+    pub is_queued: bool,
+
     /// The last value emitted.
     pub value: Option<Rc<T>>,
+
+    /// The progress of the retries, if the query is retrying.
+    pub retry: Option<RetryProgress>,
+
+    /// The progress reported by the fetcher, if any.
+    pub progress: Option<Progress>,
+
+    /// The last time the query resolved to a value.
+    pub data_updated_at: Option<Instant>,
+
+    /// The last time the query failed.
+    pub error_updated_at: Option<Instant>,
+
+    /// The time of the change.
+    pub timestamp: Instant,
+
+    /// What started the fetch that caused the change, `None` if the change is not from a fetch.
+    pub cause: Option<FetchCause>,
+}
+
+impl<T> QueryChangeEvent<T> {
+    /// Returns whether the query is fetching, see `FetchStatus`.
+    ///
+    /// Only `Paused` while queued, the pause of the client and the online status
+    /// are not part of the event, see `QueryClient::fetch_status`.
+    pub fn fetch_status(&self) -> FetchStatus {
+        FetchStatus::new(self.is_fetching, self.is_queued)
+    }
 }
 
 #[derive(Debug)]
 pub enum ObserveTarget {
     Fetch,
     Refetch,
+    JoinOrRefetch,
 }
 
 /// A mechanism for track the state of a query.
@@ -32,6 +71,8 @@ pub struct QueryObserver<T> {
     client: QueryClient,
     options: Option<QueryOptions>,
     key: QueryKey,
+    subscription: Rc<RefCell<Option<Subscription>>>,
+    enabled: Rc<Cell<bool>>,
     _marker: PhantomData<T>,
 }
 
@@ -52,22 +93,61 @@ where
             client,
             key,
             options,
+            subscription: Default::default(),
+            enabled: Rc::new(Cell::new(true)),
             _marker: PhantomData,
         }
     }
 
+    /// Returns the key of the observed query.
+    pub fn key(&self) -> &QueryKey {
+        &self.key
+    }
+
+    /// Returns `true` if this observer is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    /// Enables or disables this observer.
+    ///
+    /// A disabled observer don't fetch nor receive the changes of the query,
+    /// other observers of the same query are not affected.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.set(enabled);
+
+        if let Some(subscription) = &*self.subscription.borrow() {
+            subscription.set_enabled(enabled);
+        }
+    }
+
     /// Returns `true` if is fetching.
     pub fn is_fetching(&self) -> bool {
         let key = &self.key;
         self.client.is_fetching(key)
     }
 
+    /// Returns `true` if the fetch is waiting in the queue of the client to start.
+    pub fn is_queued(&self) -> bool {
+        self.client.is_queued(&self.key)
+    }
+
+    /// Returns whether the query is fetching, see `FetchStatus`.
+    pub fn fetch_status(&self) -> FetchStatus {
+        self.client.fetch_status(&self.key)
+    }
+
     /// Returns `true` if the query is stale.
     pub fn is_stale(&self) -> bool {
         let key = &self.key;
         self.client.is_stale(key)
     }
 
+    /// Returns the time when the value becomes stale, if the value expires.
+    pub fn stale_at(&self) -> Option<Instant> {
+        self.client.stale_at(&self.key)
+    }
+
     /// Returns the last value emitted.
     pub fn last_value(&self) -> Option<Rc<T>> {
         let key = &self.key;
@@ -81,6 +161,18 @@ where
         value
     }
 
+    /// Returns the last time the query resolved to a value.
+    pub fn data_updated_at(&self) -> Option<Instant> {
+        let key = &self.key;
+        self.client.get_query(key).and_then(|q| q.data_updated_at())
+    }
+
+    /// Returns the last time the query failed.
+    pub fn error_updated_at(&self) -> Option<Instant> {
+        let key = &self.key;
+        self.client.get_query(key).and_then(|q| q.error_updated_at())
+    }
+
     /// Returns the last state.
     pub fn last_state(&self) -> Option<QueryState> {
         let key = &self.key;
@@ -89,13 +181,38 @@ where
     }
 
     /// Adds a callback for observing the given query.
-    pub fn observe<F, Fut, E, C>(&self, target: ObserveTarget, fetch: F, callback: C)
+    ///
+    /// Each observer holds a single subscription to the query, which replaces
+    /// the previous one and is removed when the observer is dropped.
+    /// Does nothing if the observer is disabled.
+    pub fn observe<F, C>(&self, target: ObserveTarget, fetch: F, callback: C)
     where
-        F: Fn() -> Fut + 'static,
-        Fut: Future<Output = Result<T, E>> + 'static,
-        E: Into<Error> + 'static,
+        F: Fetch<T> + 'static,
+        C: Fn(QueryChangeEvent<T>) + Clone + 'static,
+    {
+        let cause = match target {
+            ObserveTarget::Fetch => FetchCause::Mount,
+            ObserveTarget::Refetch | ObserveTarget::JoinOrRefetch => FetchCause::Manual,
+        };
+
+        self.observe_with_cause(target, cause, fetch, callback);
+    }
+
+    /// Adds a callback for observing the given query, if fetched the changes are notified with the given cause.
+    pub fn observe_with_cause<F, C>(
+        &self,
+        target: ObserveTarget,
+        cause: FetchCause,
+        fetch: F,
+        callback: C,
+    ) where
+        F: Fetch<T> + 'static,
         C: Fn(QueryChangeEvent<T>) + Clone + 'static,
     {
+        if !self.is_enabled() {
+            return;
+        }
+
         let key = &self.key;
 
         {
@@ -103,65 +220,154 @@ where
             let state = client.get_query_state(key).unwrap_or(QueryState::Idle);
             let last_value = self.last_value();
             let is_fetching = client.is_fetching(key);
+            let is_queued = client.is_queued(key);
 
             // Set initial state
             callback(QueryChangeEvent {
                 state,
                 is_fetching,
+                is_queued,
                 value: last_value,
+                retry: None,
+                progress: None,
+                data_updated_at: self.data_updated_at(),
+                error_updated_at: self.error_updated_at(),
+                timestamp: Instant::now(),
+                cause: None,
             });
         }
 
         let key = key.clone();
         let client = self.client.clone();
         let options = self.options.clone();
+        let subscription = Rc::downgrade(&self.subscription);
+        let enabled = self.enabled.clone();
+        let fetcher_id = fetch.fetcher_id();
+        let fetch = BoxFetcher::with_context(move |ctx| fetch.get(ctx)).with_id(fetcher_id);
 
-        spawn_local(async move {
+        self.client.spawn(async move {
             let mut client = client;
-            let should_update = !client.is_stale(&key) || matches!(target, ObserveTarget::Refetch);
+            let should_update = !client.is_stale(&key) || !matches!(target, ObserveTarget::Fetch);
+            let on_change = {
+                let callback = callback.clone();
+                let enabled = enabled.clone();
+                move |event: QueryChanged| {
+                    if !enabled.get() {
+                        return;
+                    }
+
+                    let value = event.value.and_then(|x| x.downcast::<T>().ok());
+                    callback(QueryChangeEvent {
+                        state: event.state,
+                        is_fetching: event.is_fetching,
+                        is_queued: event.is_queued,
+                        value,
+                        retry: event.retry,
+                        progress: event.progress,
+                        data_updated_at: event.data_updated_at,
+                        error_updated_at: event.error_updated_at,
+                        timestamp: event.timestamp,
+                        cause: event.cause,
+                    });
+                }
+            };
+
+            // Queries that are not cached don't have subscribers, the changes are sent directly
+            let resolved_options = client.resolve_options(options.as_ref());
+            let query = match target {
+                _ if resolved_options.cache_time.is_none() => None,
+                ObserveTarget::Fetch => Some(client.get_or_create_query(
+                    key.clone(),
+                    fetch.clone(),
+                    resolved_options,
+                    None,
+                )),
+                ObserveTarget::Refetch | ObserveTarget::JoinOrRefetch => {
+                    client.get_query(&key).map(|q| q.clone())
+                }
+            };
+
+            let on_change = match (&query, subscription.upgrade()) {
+                (Some(query), Some(subscription)) => {
+                    let query_subscription = query.subscribe(on_change);
+                    query_subscription.set_enabled(enabled.get());
+                    *subscription.borrow_mut() = Some(query_subscription);
+                    query.set_next_cause(Some(cause));
+                    None
+                }
+                // The observer was dropped
+                (Some(_), None) => return,
+                (None, _) => Some(Rc::new(on_change) as Rc<dyn Fn(QueryChanged)>),
+            };
 
             let ret = match target {
                 ObserveTarget::Fetch => {
-                    let on_change = {
-                        let callback = callback.clone();
-                        move |event: QueryChanged| {
-                            let value = event.value.map(|x| x.downcast::<T>().unwrap());
-                            callback(QueryChangeEvent {
-                                state: event.state,
-                                is_fetching: event.is_fetching,
-                                value,
-                            });
-                        }
-                    };
-
                     client
                         .fetch_query_with_options_and_observe(
-                            key,
+                            key.clone(),
                             fetch,
                             options.as_ref(),
-                            Some(Rc::new(on_change)),
+                            on_change,
                         )
                         .await
                 }
-                ObserveTarget::Refetch => client.refetch_query(key).await,
+                ObserveTarget::Refetch => client.refetch_query(key.clone()).await,
+                ObserveTarget::JoinOrRefetch => client.refetch_query_with(key.clone(), false).await,
             };
 
+            // The query may not be fetched if was in cache
+            if let Some(query) = &query {
+                query.set_next_cause(None);
+            }
+
             // The `Query` will notify each state change, but while cache we will not receive any updates,
             // in that cache we notify the current state of the query from the observer
-            if should_update {
+            if should_update && enabled.get() {
+                let query = client.get_query(&key).map(|q| q.clone());
+                let data_updated_at = query.as_ref().and_then(|q| q.data_updated_at());
+                let error_updated_at = query.as_ref().and_then(|q| q.error_updated_at());
+
                 match ret {
                     Ok(value) => callback(QueryChangeEvent {
                         state: QueryState::Ready,
                         is_fetching: false,
+                        is_queued: false,
                         value: Some(value),
+                        retry: None,
+                        progress: None,
+                        data_updated_at: data_updated_at.or_else(|| Some(Instant::now())),
+                        error_updated_at,
+                        timestamp: Instant::now(),
+                        cause: Some(cause),
                     }),
-                    Err(err) => callback(QueryChangeEvent {
-                        state: QueryState::Failed(err.into()),
-                        is_fetching: false,
-                        value: None,
-                    }),
+                    Err(err) => {
+                        // Keep the last good value alongside the error
+                        let value = query
+                            .as_ref()
+                            .and_then(|x| x.last_value())
+                            .and_then(|x| x.downcast::<T>().ok());
+
+                        callback(QueryChangeEvent {
+                            state: QueryState::Failed(err),
+                            is_fetching: false,
+                            is_queued: false,
+                            value,
+                            retry: None,
+                            progress: None,
+                            data_updated_at,
+                            error_updated_at: error_updated_at.or_else(|| Some(Instant::now())),
+                            timestamp: Instant::now(),
+                            cause: Some(cause),
+                        })
+                    }
                 }
             }
         });
     }
 }
+
+impl<T> Drop for QueryObserver<T> {
+    fn drop(&mut self) {
+        self.subscription.borrow_mut().take();
+    }
+}
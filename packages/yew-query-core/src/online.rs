@@ -0,0 +1,105 @@
+use crate::status::ManualStatus;
+use std::{fmt::Debug, rc::Rc};
+
+/// Provides the connectivity status of a `QueryClient`.
+///
+/// Allows environments without a browser, like desktop shells or tests,
+/// to supply their own online and offline signal.
+pub trait OnlineManager {
+    /// Returns `true` if the network is available.
+    fn is_online(&self) -> bool;
+
+    /// Registers a listener called with the new status each time the connectivity changes.
+    ///
+    /// Returns an id to remove the listener.
+    fn add_listener(&self, listener: Rc<dyn Fn(bool)>) -> usize;
+
+    /// Removes the listener with the given id.
+    fn remove_listener(&self, id: usize) -> bool;
+}
+
+#[derive(Clone)]
+pub(crate) struct OnlineManagerHandle(pub Rc<dyn OnlineManager>);
+
+impl Debug for OnlineManagerHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "OnlineManager")
+    }
+}
+
+/// An `OnlineManager` which status is set manually, starts online.
+///
+/// The clones share the same status and listeners.
+#[derive(Clone)]
+pub struct ManualOnlineManager(ManualStatus);
+
+impl ManualOnlineManager {
+    /// Constructs a `ManualOnlineManager` with the given status.
+    pub fn new(online: bool) -> Self {
+        ManualOnlineManager(ManualStatus::new(online))
+    }
+
+    /// Sets the connectivity status, the listeners are notified if the status changed.
+    pub fn set_online(&self, online: bool) {
+        self.0.set(online);
+    }
+}
+
+impl Default for ManualOnlineManager {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+impl OnlineManager for ManualOnlineManager {
+    fn is_online(&self) -> bool {
+        self.0.get()
+    }
+
+    fn add_listener(&self, listener: Rc<dyn Fn(bool)>) -> usize {
+        self.0.add_listener(listener)
+    }
+
+    fn remove_listener(&self, id: usize) -> bool {
+        self.0.remove_listener(id)
+    }
+}
+
+impl Debug for ManualOnlineManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ManualOnlineManager")
+            .field("online", &self.0.get())
+            .field("listeners", &self.0.listener_count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ManualOnlineManager, OnlineManager};
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn manual_online_manager_test() {
+        let manager = ManualOnlineManager::default();
+        let events = Rc::new(RefCell::new(Vec::new()));
+
+        let id = {
+            let events = events.clone();
+            manager.add_listener(Rc::new(move |online| events.borrow_mut().push(online)))
+        };
+
+        assert!(manager.is_online());
+
+        manager.set_online(false);
+        manager.set_online(false);
+        assert!(!manager.is_online());
+
+        manager.set_online(true);
+        assert_eq!(*events.borrow(), vec![false, true]);
+
+        assert!(manager.remove_listener(id));
+        manager.set_online(false);
+        assert_eq!(*events.borrow(), vec![false, true]);
+    }
+}
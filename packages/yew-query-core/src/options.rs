@@ -1,12 +1,186 @@
-use crate::retry::Retry;
-use instant::Duration;
+use crate::{
+    dedupe::RequestDeduper,
+    error::{Error, ErrorContext, ErrorReporter, ValidationError},
+    key::Key,
+    limiter::{FetchLimiter, RateLimiter},
+    middleware::MiddlewareHandle,
+    retry::Retry,
+    timer::TimerHandle,
+    QueryMeta,
+};
+use instant::{Duration, Instant};
+use std::{any::Any, fmt::Debug, rc::Rc};
+
+#[derive(Clone)]
+pub(crate) struct ErrorReporterHandle(Rc<dyn ErrorReporter>);
+
+impl PartialEq for ErrorReporterHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl ErrorReporterHandle {
+    pub fn new<R>(reporter: R) -> Self
+    where
+        R: ErrorReporter + 'static,
+    {
+        ErrorReporterHandle(Rc::new(reporter))
+    }
+
+    pub fn report(&self, error: &Error, context: &ErrorContext) {
+        self.0.report(error, context)
+    }
+}
+
+impl Debug for ErrorReporterHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ErrorReporter")
+    }
+}
+
+type EqualityFn = dyn Fn(&dyn Any, &dyn Any) -> bool;
+
+#[derive(Clone)]
+pub(crate) struct EqualityHandle(Rc<EqualityFn>);
+
+impl PartialEq for EqualityHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl EqualityHandle {
+    pub fn new<T: PartialEq + 'static>() -> Self {
+        EqualityHandle(Rc::new(|a, b| {
+            match (a.downcast_ref::<T>(), b.downcast_ref::<T>()) {
+                (Some(a), Some(b)) => a == b,
+                _ => false,
+            }
+        }))
+    }
+
+    pub fn eq(&self, a: &dyn Any, b: &dyn Any) -> bool {
+        (self.0)(a, b)
+    }
+}
+
+impl Debug for EqualityHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Equality")
+    }
+}
+
+type ValidatorFn = dyn Fn(&dyn Any) -> Result<(), ValidationError>;
+
+#[derive(Clone)]
+pub(crate) struct ValidatorHandle(Rc<ValidatorFn>);
+
+impl PartialEq for ValidatorHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl ValidatorHandle {
+    pub fn new<T, F>(validate: F) -> Self
+    where
+        T: 'static,
+        F: Fn(&T) -> Result<(), ValidationError> + 'static,
+    {
+        // The values of other types are not validated
+        ValidatorHandle(Rc::new(move |value| match value.downcast_ref::<T>() {
+            Some(value) => validate(value),
+            None => Ok(()),
+        }))
+    }
+
+    pub fn validate(&self, value: &dyn Any) -> Result<(), ValidationError> {
+        (self.0)(value)
+    }
+}
+
+impl Debug for ValidatorHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Validator")
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct InitialDataHandle(pub Rc<dyn Any>);
+
+impl PartialEq for InitialDataHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Debug for InitialDataHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "InitialData")
+    }
+}
+
+/// The priority of the fetches of a query when the client limits the concurrent fetches,
+/// see `QueryClientBuilder::max_concurrent_fetches`.
+///
+/// The queued fetches with higher priority start first, so the data visible in the page
+/// can load before the prefetches and the background polls.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FetchPriority(i32);
+
+impl FetchPriority {
+    /// For the fetches that can wait, like prefetches and background polls.
+    pub const LOW: FetchPriority = FetchPriority(-100);
+
+    /// The priority of the queries by default.
+    pub const NORMAL: FetchPriority = FetchPriority(0);
+
+    /// For the data needed first, like the data visible in the page.
+    pub const HIGH: FetchPriority = FetchPriority(100);
+
+    /// Constructs a `FetchPriority` with the given value, higher values start first.
+    pub const fn new(value: i32) -> Self {
+        FetchPriority(value)
+    }
+
+    /// Returns the value of this priority.
+    pub fn value(&self) -> i32 {
+        self.0
+    }
+}
+
+impl From<i32> for FetchPriority {
+    fn from(value: i32) -> Self {
+        FetchPriority(value)
+    }
+}
 
 /// Options for a query.
-#[derive(Debug, Default, Clone)]
+///
+/// Two options are equal if they have the same values, the functions, like the retry,
+/// are compared by reference.
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct QueryOptions {
     pub(crate) cache_time: Option<Duration>,
     pub(crate) refetch_time: Option<Duration>,
     pub(crate) retry: Option<Retry>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) error_reporter: Option<ErrorReporterHandle>,
+    pub(crate) meta: QueryMeta,
+    pub(crate) middlewares: Vec<MiddlewareHandle>,
+    pub(crate) partition: Option<Rc<str>>,
+    pub(crate) equality: Option<EqualityHandle>,
+    pub(crate) initial_data: Option<InitialDataHandle>,
+    pub(crate) initial_data_updated_at: Option<Instant>,
+    pub(crate) timer: Option<TimerHandle>,
+    pub(crate) validator: Option<ValidatorHandle>,
+    pub(crate) fetch_limiter: Option<FetchLimiter>,
+    pub(crate) priority: Option<FetchPriority>,
+    pub(crate) min_fetch_interval: Option<Duration>,
+    pub(crate) rate_limiter: Option<RateLimiter>,
+    pub(crate) request_key: Option<Key>,
+    pub(crate) deduper: Option<RequestDeduper>,
 }
 
 impl QueryOptions {
@@ -36,4 +210,129 @@ impl QueryOptions {
         self.retry = Some(Retry::new(retry));
         self
     }
+
+    /// Adds an entry to the metadata of a query.
+    pub fn meta(mut self, key: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.meta.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets the max time a fetch can take before failing with `QueryError::Timeout`.
+    pub fn timeout(mut self, duration: Duration) -> Self {
+        self.timeout = Some(duration);
+        self
+    }
+
+    /// Sets the priority of the fetches of the query when the client limits
+    /// the concurrent fetches, see `FetchPriority`.
+    pub fn priority(mut self, priority: impl Into<FetchPriority>) -> Self {
+        self.priority = Some(priority.into());
+        self
+    }
+
+    /// Sets the min time between the start of two fetches of the query,
+    /// a fetch triggered before waits until the interval elapses.
+    ///
+    /// Protects rate-limited APIs from bursts of invalidations, focus and reconnect events.
+    pub fn min_fetch_interval(mut self, interval: Duration) -> Self {
+        self.min_fetch_interval = Some(interval);
+        self
+    }
+
+    /// Sets the identity of the request made by the fetcher, for example the url and its params.
+    ///
+    /// Queries of the same type with the same request key share the fetch in progress,
+    /// even if they have different query keys, so an aliased resource is fetched once.
+    ///
+    /// The shared fetch runs with the fetcher, options and validators of the query that started it,
+    /// and is only cancelled when all the queries waiting for it are cancelled.
+    pub fn request_key(mut self, request_key: impl Into<Key>) -> Self {
+        self.request_key = Some(request_key.into());
+        self
+    }
+
+    /// Sets the partition of the cache where the query is stored,
+    /// see `QueryClient::clear_partition`.
+    pub fn partition(mut self, partition: impl Into<Rc<str>>) -> Self {
+        self.partition = Some(partition.into());
+        self
+    }
+
+    /// Keeps the cached value when a refetch returns an equal value,
+    /// so the observers receive the same `Rc` and are not notified of a new value.
+    pub fn structural_sharing<T: PartialEq + 'static>(mut self) -> Self {
+        self.equality = Some(EqualityHandle::new::<T>());
+        self
+    }
+
+    /// Sets a validator that checks the values returned by the fetcher,
+    /// a rejected value fails the fetch with `QueryError::Validation` and is not retried.
+    ///
+    /// Allows to differentiate malformed data sent by the server from the network errors.
+    pub fn validate<T, F>(mut self, validate: F) -> Self
+    where
+        T: 'static,
+        F: Fn(&T) -> Result<(), ValidationError> + 'static,
+    {
+        self.validator = Some(ValidatorHandle::new(validate));
+        self
+    }
+
+    /// Returns these options using the values of `defaults` for the options not set.
+    pub fn merge(&self, defaults: &QueryOptions) -> QueryOptions {
+        let mut middlewares = defaults.middlewares.clone();
+        middlewares.extend(self.middlewares.iter().cloned());
+
+        QueryOptions {
+            cache_time: self.cache_time.or(defaults.cache_time),
+            refetch_time: self.refetch_time.or(defaults.refetch_time),
+            retry: self.retry.clone().or_else(|| defaults.retry.clone()),
+            timeout: self.timeout.or(defaults.timeout),
+            error_reporter: self
+                .error_reporter
+                .clone()
+                .or_else(|| defaults.error_reporter.clone()),
+            meta: defaults.meta.merge(&self.meta),
+            middlewares,
+            partition: self
+                .partition
+                .clone()
+                .or_else(|| defaults.partition.clone()),
+            equality: self.equality.clone().or_else(|| defaults.equality.clone()),
+            initial_data: self.initial_data.clone(),
+            initial_data_updated_at: self.initial_data_updated_at,
+            timer: self.timer.clone().or_else(|| defaults.timer.clone()),
+            validator: self
+                .validator
+                .clone()
+                .or_else(|| defaults.validator.clone()),
+            fetch_limiter: self
+                .fetch_limiter
+                .clone()
+                .or_else(|| defaults.fetch_limiter.clone()),
+            priority: self.priority.or(defaults.priority),
+            min_fetch_interval: self.min_fetch_interval.or(defaults.min_fetch_interval),
+            rate_limiter: self
+                .rate_limiter
+                .clone()
+                .or_else(|| defaults.rate_limiter.clone()),
+            request_key: self.request_key.clone(),
+            deduper: self.deduper.clone().or_else(|| defaults.deduper.clone()),
+        }
+    }
+
+    /// Sets the value of the query when is created, the value is stored in the cache
+    /// and is not fetched again until stale.
+    pub fn initial_data<T: 'static>(mut self, data: T) -> Self {
+        self.initial_data = Some(InitialDataHandle(Rc::new(data)));
+        self
+    }
+
+    /// Sets the time the initial data was updated, by default the time the query is created.
+    ///
+    /// Used to check if the initial data is stale, for example for data embedded in the page.
+    pub fn initial_data_updated_at(mut self, updated_at: Instant) -> Self {
+        self.initial_data_updated_at = Some(updated_at);
+        self
+    }
 }
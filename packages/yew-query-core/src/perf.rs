@@ -0,0 +1,93 @@
+pub(crate) use platform::FetchMark;
+
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+mod platform {
+    use crate::QueryKey;
+    use std::cell::Cell;
+    use web_sys::Performance;
+
+    thread_local! {
+        static NEXT_ID: Cell<u64> = Cell::new(0);
+    }
+
+    fn performance() -> Option<Performance> {
+        web_sys::window()?.performance()
+    }
+
+    // Marks the start and the end of a fetch with `performance.mark`,
+    // and measures the fetch with the name of the query key.
+    pub struct FetchMark {
+        name: String,
+        start: String,
+    }
+
+    impl FetchMark {
+        pub fn start(key: &QueryKey) -> Self {
+            // Concurrent fetches of the same key need their own marks
+            let id = NEXT_ID.with(|x| x.replace(x.get() + 1));
+            let name = format!("fetch {key}");
+            let start = format!("{name} #{id}");
+
+            if let Some(performance) = performance() {
+                performance.mark(&start).ok();
+            }
+
+            FetchMark { name, start }
+        }
+
+        pub fn end(self, is_ok: bool) {
+            let Some(performance) = performance() else {
+                return;
+            };
+
+            let end = format!("{} end", self.start);
+            let name = match is_ok {
+                true => self.name,
+                false => format!("{} (failed)", self.name),
+            };
+
+            performance.mark(&end).ok();
+            performance
+                .measure_with_start_mark_and_end_mark(&name, &self.start, &end)
+                .ok();
+
+            // Only the measures are kept
+            performance.clear_marks_with_mark_name(&self.start);
+            performance.clear_marks_with_mark_name(&end);
+        }
+    }
+}
+
+#[cfg(not(all(feature = "web", target_arch = "wasm32")))]
+mod platform {
+    use crate::QueryKey;
+    use instant::Instant;
+    use tracing::Span;
+
+    // Times a fetch with a `tracing` span named after the query key,
+    // outside the browser or without the `web` feature.
+    pub struct FetchMark {
+        span: Span,
+        started_at: Instant,
+    }
+
+    impl FetchMark {
+        pub fn start(key: &QueryKey) -> Self {
+            FetchMark {
+                span: tracing::debug_span!("fetch", key = %key),
+                started_at: Instant::now(),
+            }
+        }
+
+        pub fn end(self, is_ok: bool) {
+            let elapsed = self.started_at.elapsed();
+            self.span.in_scope(|| {
+                tracing::debug!(
+                    elapsed_ms = elapsed.as_secs_f64() * 1000.0,
+                    is_ok,
+                    "fetch ended"
+                );
+            });
+        }
+    }
+}
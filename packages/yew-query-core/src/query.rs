@@ -1,19 +1,34 @@
-use super::{error::QueryError, fetcher::BoxFetcher};
+use super::{
+    error::QueryError,
+    fetcher::{
+        BoxFetcher, Fetch, FetchResponse, FetcherId, OnPartial, OnProgress, Progress, Validators,
+    },
+};
 use crate::{
-    client::fetch_with_retry, retry::Retry, state::QueryState, time::interval::Interval, Error,
+    cancellation::CancellationToken,
+    client::fetch_with_retry,
+    limiter::OnQueued,
+    middleware::{apply_middlewares, apply_validator},
+    notify,
+    retry::{OnRetry, RetryProgress},
+    spawner::SpawnerHandle,
+    state::{FetchStatus, QueryState},
+    stats::{FetchHistory, FetchOutcome, FetchRecord},
+    suspension::is_refetch_suspended,
+    time::interval::Interval,
+    Error, QueryKey, QueryMeta, QueryOptions, QuerySnapshot,
 };
 use futures::{
-    future::{ok, LocalBoxFuture, Shared},
-    Future, FutureExt, TryFutureExt,
+    future::{err, ok, LocalBoxFuture, Shared},
+    FutureExt, TryFutureExt,
 };
-use instant::Instant;
-use prokio::spawn_local;
+use instant::{Duration, Instant};
 use std::{
     any::{Any, TypeId},
+    cell::Cell,
     fmt::Debug,
     rc::Rc,
-    sync::{Arc, RwLock},
-    time::Duration,
+    sync::{Arc, RwLock, Weak},
 };
 
 #[derive(Clone)]
@@ -24,11 +39,70 @@ impl Debug for OnQueryChangeHandler {
     }
 }
 
+/// What started the fetch of a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FetchCause {
+    /// An observer of the query was mounted.
+    Mount,
+
+    /// The refetch interval of the query elapsed.
+    Interval,
+
+    /// The window was focused.
+    WindowFocus,
+
+    /// The network was reconnected.
+    Reconnect,
+
+    /// The query was invalidated by an `InvalidationMessage`.
+    Invalidation,
+
+    /// The query was fetched explicitly.
+    Manual,
+}
+
 #[derive(Clone)]
 pub struct QueryChanged {
     pub value: Option<Rc<dyn Any>>,
     pub state: QueryState,
     pub is_fetching: bool,
+    /// Whether the fetch is waiting in the queue of the client to start.
+    pub is_queued: bool,
+    pub retry: Option<RetryProgress>,
+    pub progress: Option<Progress>,
+    pub data_updated_at: Option<Instant>,
+    pub error_updated_at: Option<Instant>,
+    /// The time of the change.
+    pub timestamp: Instant,
+    /// What started the fetch that caused the change, `None` if the change is not from a fetch.
+    pub cause: Option<FetchCause>,
+}
+
+impl QueryChanged {
+    /// Returns whether the query is fetching, see `FetchStatus`.
+    ///
+    /// Only `Paused` while queued, the pause of the client and the online status
+    /// are not part of the event, see `QueryClient::fetch_status`.
+    pub fn fetch_status(&self) -> FetchStatus {
+        FetchStatus::new(self.is_fetching, self.is_queued)
+    }
+}
+
+impl Default for QueryChanged {
+    fn default() -> Self {
+        QueryChanged {
+            value: None,
+            state: QueryState::default(),
+            is_fetching: false,
+            is_queued: false,
+            retry: None,
+            progress: None,
+            data_updated_at: None,
+            error_updated_at: None,
+            timestamp: Instant::now(),
+            cause: None,
+        }
+    }
 }
 
 impl Debug for QueryChanged {
@@ -43,76 +117,220 @@ impl Debug for QueryChanged {
             })
             .field("state", &self.state)
             .field("is_fetching", &self.is_fetching)
+            .field("is_queued", &self.is_queued)
+            .field("retry", &self.retry)
+            .field("progress", &self.progress)
+            .field("data_updated_at", &self.data_updated_at)
+            .field("error_updated_at", &self.error_updated_at)
+            .field("timestamp", &self.timestamp)
+            .field("cause", &self.cause)
             .finish()
     }
 }
 
+#[derive(Debug)]
+struct Listener {
+    id: usize,
+    enabled: bool,
+    handler: OnQueryChangeHandler,
+}
+
 #[derive(Debug)]
 struct Inner {
     fetcher: BoxFetcher<Rc<dyn Any>>,
-    retrier: Option<Retry>,
-    cache_time: Option<Duration>,
-    refetch_time: Option<Duration>,
-    updated_at: Option<Instant>,
+    options: QueryOptions,
+    data_updated_at: Option<Instant>,
+    error_updated_at: Option<Instant>,
     last_value: Option<Rc<dyn Any>>,
     future_or_value: Shared<LocalBoxFuture<'static, Result<Rc<dyn Any>, Error>>>,
     interval: Option<Interval>,
     state: QueryState,
+    signal: CancellationToken,
+    validators: Option<Validators>,
+    fresh_for: Option<Duration>,
+    invalidated: bool,
     on_change: Option<OnQueryChangeHandler>,
+    on_cache_change: Option<OnQueryChangeHandler>,
+    listeners: Vec<Listener>,
+    next_listener_id: usize,
+    cause: Option<FetchCause>,
+    next_cause: Option<FetchCause>,
+    paused: Option<Rc<Cell<bool>>>,
+    spawner: SpawnerHandle,
+    background_fetches: usize,
+    queued: bool,
+    history: FetchHistory,
+}
+
+impl Inner {
+    // Returns the handlers to notify of a change
+    fn handlers(&self) -> Vec<OnQueryChangeHandler> {
+        self.on_change
+            .iter()
+            .chain(self.on_cache_change.iter())
+            .chain(
+                self.listeners
+                    .iter()
+                    .filter(|x| x.enabled)
+                    .map(|x| &x.handler),
+            )
+            .cloned()
+            .collect()
+    }
+}
+
+fn notify_handlers(handlers: Vec<OnQueryChangeHandler>, event: QueryChanged) {
+    for handler in handlers {
+        // Each handler only receives the last event of a batch
+        let id = Rc::as_ptr(&handler.0) as *const () as usize;
+        let event = event.clone();
+        notify::schedule(id, move || (handler.0)(event));
+    }
+}
+
+// Returns a resolved future for a query that was not fetched yet.
+fn not_ready() -> Shared<LocalBoxFuture<'static, Result<Rc<dyn Any>, Error>>> {
+    let fut = err(Error::new(QueryError::NotReady))
+        .boxed_local()
+        .shared();
+
+    // A shared future is only resolved after polled, otherwise the query looks as fetching
+    fut.clone().now_or_never();
+    fut
+}
+
+// Returns a resolved future with the given value.
+fn resolved(value: Rc<dyn Any>) -> Shared<LocalBoxFuture<'static, Result<Rc<dyn Any>, Error>>> {
+    let fut = ok(value)
+        .boxed_local()
+        .shared();
+
+    // Polled for the same reason as `not_ready`
+    fut.clone().now_or_never();
+    fut
 }
 
 /// Represents a query.
 #[derive(Debug, Clone)]
 pub struct Query {
+    key: QueryKey,
     type_id: TypeId,
     inner: Arc<RwLock<Inner>>,
 }
 
 impl Query {
     /// Constructs a new `Query`
-    pub fn new<F, Fut, T, E>(
+    pub fn new<F, T>(
+        key: QueryKey,
         f: F,
-        retrier: Option<Retry>,
-        cache_time: Option<Duration>,
-        refetch_time: Option<Duration>,
+        options: QueryOptions,
         on_change: Option<Rc<dyn Fn(QueryChanged)>>,
     ) -> Self
     where
-        F: Fn() -> Fut + 'static,
-        Fut: Future<Output = Result<T, E>> + 'static,
+        F: Fetch<T> + 'static,
         T: 'static,
-        E: Into<Error> + 'static,
     {
         let type_id = TypeId::of::<T>();
-        let fetcher = BoxFetcher::new(move || f().map_ok(|x| Rc::new(x) as Rc<dyn Any>));
-        let future_or_value = fetch_with_retry(fetcher.clone(), retrier.clone())
-            .boxed_local()
-            .shared();
+        let fetcher_id = f.fetcher_id();
+        let f = apply_validator(f, options.validator.clone());
+        let f = apply_middlewares(f, &options.middlewares);
+        let fetcher =
+            BoxFetcher::with_context(move |ctx| f.get(ctx).map_ok(|x| Rc::new(x) as Rc<dyn Any>))
+                .with_id(fetcher_id);
 
         if let Some(on_change) = &on_change {
             on_change(QueryChanged {
                 value: None,
                 state: QueryState::Idle,
                 is_fetching: false,
+                ..Default::default()
             });
         }
 
         let on_change = on_change.map(OnQueryChangeHandler);
+        Self::with_fetcher(key, type_id, fetcher, options, on_change)
+    }
+
+    // Sets the initial data of the options as the value of this query, if any,
+    // the handlers are notified so must be called after the query is in the cache.
+    pub(crate) fn set_initial_data<T: 'static>(&self) -> Result<(), QueryError> {
+        let mut inner = self.inner.write().expect("failed to write in query");
+        let Some(initial_data) = inner.options.initial_data.clone() else {
+            return Ok(());
+        };
+
+        let value = initial_data.0;
+        if !value.is::<T>() {
+            return Err(QueryError::type_mismatch::<T>());
+        }
+
+        inner.future_or_value = resolved(value.clone());
+        inner.last_value = Some(value.clone());
+        inner.state = QueryState::Ready;
+        inner.data_updated_at = Some(
+            inner
+                .options
+                .initial_data_updated_at
+                .unwrap_or_else(Instant::now),
+        );
 
+        let handlers = inner.handlers();
+        let event = QueryChanged {
+            value: Some(value),
+            state: QueryState::Ready,
+            is_fetching: false,
+            data_updated_at: inner.data_updated_at,
+            ..Default::default()
+        };
+
+        drop(inner);
+        notify_handlers(handlers, event);
+        Ok(())
+    }
+
+    // Constructs a new `Query` with a type-erased fetcher.
+    fn with_fetcher(
+        key: QueryKey,
+        type_id: TypeId,
+        fetcher: BoxFetcher<Rc<dyn Any>>,
+        options: QueryOptions,
+        on_change: Option<OnQueryChangeHandler>,
+    ) -> Self {
+        // The query is not fetched until `fetch` is called
+        let future_or_value = not_ready();
+
+        #[allow(clippy::arc_with_non_send_sync)]
         let inner = Arc::new(RwLock::new(Inner {
             fetcher,
-            retrier,
-            cache_time,
-            refetch_time,
+            options,
             future_or_value,
             state: QueryState::Idle,
             last_value: None,
-            updated_at: None,
+            data_updated_at: None,
+            error_updated_at: None,
             interval: None,
+            signal: CancellationToken::new(),
+            validators: None,
+            fresh_for: None,
+            invalidated: false,
             on_change,
+            on_cache_change: None,
+            listeners: Vec::new(),
+            next_listener_id: 0,
+            cause: None,
+            next_cause: None,
+            paused: None,
+            spawner: SpawnerHandle::default(),
+            background_fetches: 0,
+            queued: false,
+            history: FetchHistory::default(),
         }));
 
-        Query { type_id, inner }
+        Query {
+            key,
+            type_id,
+            inner,
+        }
     }
 
     fn assert_type<T: 'static>(&self) -> Result<(), QueryError> {
@@ -123,11 +341,35 @@ impl Query {
         Ok(())
     }
 
+    /// Returns the key of this `Query`.
+    pub fn key(&self) -> &QueryKey {
+        &self.key
+    }
+
+    /// Returns the metadata of this `Query`.
+    pub fn meta(&self) -> QueryMeta {
+        self.inner.read().unwrap().options.meta.clone()
+    }
+
+    /// Returns the partition of the cache where this `Query` is stored, if any.
+    pub fn partition(&self) -> Option<Rc<str>> {
+        self.inner.read().unwrap().options.partition.clone()
+    }
+
     /// Returns the type if of this `Query`.
     pub fn type_id(&self) -> TypeId {
         self.type_id
     }
 
+    /// Returns the identity of the fetcher this query was created with.
+    pub fn fetcher_id(&self) -> FetcherId {
+        self.inner
+            .read()
+            .expect("failed to read query")
+            .fetcher
+            .fetcher_id()
+    }
+
     /// Returns the state of this query.
     pub fn state(&self) -> QueryState {
         self.inner.read().unwrap().state.clone()
@@ -148,13 +390,9 @@ impl Query {
 
         let value = fut.await;
         match value {
-            Ok(x) => {
-                let ret = x
-                    .downcast::<T>()
-                    .map_err(|_| QueryError::type_mismatch::<T>().into());
-
-                ret
-            }
+            Ok(x) => x
+                .downcast::<T>()
+                .map_err(|_| QueryError::type_mismatch::<T>().into()),
             Err(err) => Err(err),
         }
     }
@@ -164,6 +402,40 @@ impl Query {
         self.inner.read().unwrap().future_or_value.peek().is_none()
     }
 
+    /// Returns `true` if the fetch of this query is waiting in the queue of the client to start,
+    /// see `QueryClientBuilder::max_concurrent_fetches`.
+    pub fn is_queued(&self) -> bool {
+        self.inner.read().unwrap().queued
+    }
+
+    /// Returns whether this query is fetching, see `FetchStatus`.
+    ///
+    /// Is `Paused` while the client of the query is paused, see `QueryClient::fetch_status`
+    /// to also include the online status.
+    pub fn fetch_status(&self) -> FetchStatus {
+        FetchStatus::new(self.is_fetching(), self.is_queued()).or_paused(self.is_paused())
+    }
+
+    /// Returns the last fetches of this query.
+    pub fn fetch_history(&self) -> FetchHistory {
+        self.inner.read().unwrap().history.clone()
+    }
+
+    /// Returns the last time this query resolved to a value.
+    pub fn data_updated_at(&self) -> Option<Instant> {
+        self.inner.read().unwrap().data_updated_at
+    }
+
+    /// Returns the last time this query failed.
+    pub fn error_updated_at(&self) -> Option<Instant> {
+        self.inner.read().unwrap().error_updated_at
+    }
+
+    /// Returns the validators stored with the value of this query.
+    pub fn validators(&self) -> Option<Validators> {
+        self.inner.read().unwrap().validators.clone()
+    }
+
     /// Return the last cache value of this query.
     pub fn last_value(&self) -> Option<Rc<dyn Any>> {
         self.inner.read().unwrap().last_value.clone()
@@ -173,27 +445,107 @@ impl Query {
     pub async fn fetch<T: 'static>(&mut self) -> Result<Rc<T>, Error> {
         self.assert_type::<T>()?;
 
+        let ret = self.fetch_value().await?;
+        let ret = ret
+            .downcast::<T>()
+            .map_err(|_| QueryError::type_mismatch::<T>())?;
+
+        Ok(ret)
+    }
+
+    // Executes the fetcher without checking the type of the value.
+    pub(crate) async fn fetch_value(&mut self) -> Result<Rc<dyn Any>, Error> {
+        let cause = self.inner.write().unwrap().next_cause.take();
+        self.fetch_with_cause(cause.unwrap_or(FetchCause::Manual))
+            .await
+    }
+
+    // Executes the fetcher, the changes during the fetch are notified with the given cause.
+    pub(crate) async fn fetch_with_cause(
+        &mut self,
+        cause: FetchCause,
+    ) -> Result<Rc<dyn Any>, Error> {
+        self.inner.write().unwrap().cause = Some(cause);
+        let ret = self.execute().await;
+
+        // The changes after the fetch don't have a cause
+        if !self.is_fetching() {
+            self.inner.write().unwrap().cause = None;
+        }
+
+        ret
+    }
+
+    // Sets the cause of the next fetch of this query.
+    pub(crate) fn set_next_cause(&self, cause: Option<FetchCause>) {
+        self.inner.write().unwrap().next_cause = cause;
+    }
+
+    async fn execute(&mut self) -> Result<Rc<dyn Any>, Error> {
         // Only when is empty will be loading, otherwise may use the cache last value.
         if self.last_value().is_none() {
             self.on_change(QueryChanged {
                 is_fetching: true,
                 state: QueryState::Loading,
                 value: None,
+                ..Default::default()
             });
         }
 
+        let on_progress = progress_handler(Arc::downgrade(&self.inner));
+        let on_partial = partial_handler(Arc::downgrade(&self.inner), self.type_id);
+        let on_queued = queued_handler(Arc::downgrade(&self.inner));
+        let response =
+            FetchResponse::new(Some(on_progress), Some(on_partial)).with_on_queued(Some(on_queued));
+        let started_at = Instant::now();
+        let cause = self
+            .inner
+            .read()
+            .unwrap()
+            .cause
+            .unwrap_or(FetchCause::Manual);
         let fut = {
             let mut inner = self.inner.write().expect("failed to write in query");
 
             let fetcher = inner.fetcher.clone();
-            let retrier = inner.retrier.clone();
-            let fut = fetch_with_retry(fetcher, retrier.clone())
-                .boxed_local()
-                .shared();
+            let options = inner.options.clone();
+            let dedupe = options.request_key.clone().zip(options.deduper.clone());
+            let on_retry = retry_handler(Arc::downgrade(&self.inner));
+            let signal = CancellationToken::new();
+            let start = {
+                let key = self.key.clone();
+                let validators = inner.validators.clone();
+                let response = response.clone();
+                move |signal| {
+                    fetch_with_retry(
+                        key,
+                        fetcher,
+                        options,
+                        Some(on_retry),
+                        signal,
+                        validators,
+                        response,
+                    )
+                    .boxed_local()
+                }
+            };
+
+            // The queries with the same request key share the fetch in progress
+            let fut = match dedupe {
+                Some((request, deduper)) => {
+                    deduper.join_or_start(request, self.type_id, &signal, start)
+                }
+                None => start(signal.clone()),
+            }
+            .shared();
 
             // Updates the inner future
             inner.future_or_value = fut.clone();
-            if inner.on_change.is_some() {
+            inner.signal = signal;
+            if inner.on_change.is_some()
+                || inner.on_cache_change.is_some()
+                || !inner.listeners.is_empty()
+            {
                 let value = inner.last_value.clone();
                 let state = inner.state.clone();
                 drop(inner);
@@ -202,6 +554,7 @@ impl Query {
                     is_fetching: true,
                     state,
                     value,
+                    ..Default::default()
                 });
             }
 
@@ -209,8 +562,88 @@ impl Query {
         };
 
         // Await and which updates the inner future
-        let value = match fut.await {
+        let ret = fut.await;
+        {
+            let info = response.take();
+            let mut inner = self.inner.write().expect("failed to write in query");
+
+            // A cancelled fetch may leave the queue without starting
+            inner.queued = false;
+
+            let outcome = match &ret {
+                Ok(_) => Some(FetchOutcome::Success),
+                Err(err) if err.is_not_modified() => Some(FetchOutcome::NotModified),
+                Err(err) if err.is_cancelled() => None,
+                Err(err) => Some(FetchOutcome::Failed(err.clone())),
+            };
+
+            if let Some(outcome) = outcome {
+                inner.history.push(FetchRecord {
+                    started_at,
+                    duration: started_at.elapsed(),
+                    cause,
+                    outcome,
+                });
+            }
+
+            if let Some(validators) = info.validators {
+                inner.validators = Some(validators);
+            }
+
+            if ret.is_ok() || matches!(&ret, Err(err) if err.is_not_modified()) {
+                inner.invalidated = false;
+            }
+
+            // A new value without freshness hint uses the cache time of the options
+            match (&ret, info.fresh_for) {
+                (_, Some(fresh_for)) => inner.fresh_for = Some(fresh_for),
+                (Ok(_), None) => inner.fresh_for = None,
+                _ => {}
+            }
+        }
+
+        let value = match ret {
             Ok(x) => x,
+            Err(err) if err.is_not_modified() && self.last_value().is_some() => {
+                // The cached value is still valid, we only refresh the update time
+                let mut inner = self.inner.write().expect("failed to write in query");
+                let last_value = inner.last_value.clone().unwrap();
+                inner.future_or_value = resolved(last_value.clone());
+                last_value
+            }
+            Err(err) if err.is_cancelled() => {
+                // A cancelled fetch keeps the current state and value of the query
+                let mut inner = self.inner.write().expect("failed to write in query");
+
+                // Other fetch started after the cancellation, the state is updated by that fetch
+                if !inner.signal.is_cancelled() {
+                    return Err(err);
+                }
+
+                if let Some(last_value) = inner.last_value.clone() {
+                    inner.future_or_value = resolved(last_value);
+                }
+
+                if inner.state.is_loading() {
+                    inner.state = QueryState::Idle;
+                }
+
+                let value = inner.last_value.clone();
+                let state = inner.state.clone();
+                drop(inner);
+
+                self.send_event(
+                    QueryChanged {
+                        is_fetching: false,
+                        state,
+                        value,
+                        ..Default::default()
+                    },
+                    false,
+                );
+
+                return Err(err);
+            }
             Err(err) => {
                 let inner = self.inner.read().expect("failed to write in query");
                 let value = inner.last_value.clone();
@@ -220,6 +653,7 @@ impl Query {
                     is_fetching: false,
                     state: QueryState::Failed(err.clone()),
                     value,
+                    ..Default::default()
                 });
 
                 return Err(err);
@@ -227,26 +661,225 @@ impl Query {
         };
 
         // refetch
-        self.queue_refetch::<T>();
+        self.queue_refetch();
 
-        let ret = value
-            .downcast::<T>()
-            .map_err(|_| QueryError::type_mismatch::<T>())?;
+        // An equal value keeps the cached one, only the update time changes
+        if let Some(last_value) = self.equal_last_value(&value) {
+            let mut inner = self.inner.write().expect("failed to write in query");
+            inner.future_or_value = resolved(last_value.clone());
+            inner.data_updated_at = Some(Instant::now());
+            drop(inner);
+
+            self.send_event(
+                QueryChanged {
+                    is_fetching: false,
+                    state: QueryState::Ready,
+                    value: Some(last_value.clone()),
+                    ..Default::default()
+                },
+                false,
+            );
+
+            return Ok(last_value);
+        }
 
         self.on_change(QueryChanged {
             is_fetching: false,
             state: QueryState::Ready,
-            value: Some(ret.clone()),
+            value: Some(value.clone()),
+            ..Default::default()
         });
 
-        Ok(ret)
+        Ok(value)
+    }
+
+    // Returns the cached value if is equal to the given value and the query uses structural sharing.
+    fn equal_last_value(&self, value: &Rc<dyn Any>) -> Option<Rc<dyn Any>> {
+        let inner = self.inner.read().unwrap();
+        let equality = inner.options.equality.as_ref()?;
+        let last_value = inner.last_value.as_ref()?;
+
+        if inner.state.is_ready() && equality.eq(last_value.as_ref(), value.as_ref()) {
+            Some(last_value.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if this query is being observed.
+    pub fn has_observers(&self) -> bool {
+        self.observer_count() > 0
+    }
+
+    /// Returns `false` if all the subscriptions to this query are disabled.
+    ///
+    /// A query without subscriptions is enabled.
+    pub fn is_enabled(&self) -> bool {
+        let inner = self.inner.read().unwrap();
+        inner.on_change.is_some()
+            || inner.listeners.is_empty()
+            || inner.listeners.iter().any(|x| x.enabled)
+    }
+
+    /// Returns the number of observers and subscriptions attached to this query.
+    pub fn observer_count(&self) -> usize {
+        let inner = self.inner.read().unwrap();
+        inner.on_change.iter().count() + inner.listeners.len()
+    }
+
+    /// Returns a copy of the current value and state of this query.
+    pub fn snapshot(&self) -> QuerySnapshot {
+        let inner = self.inner.read().unwrap();
+
+        QuerySnapshot {
+            key: self.key.clone(),
+            type_id: self.type_id,
+            fetcher: inner.fetcher.clone(),
+            options: inner.options.clone(),
+            value: inner.last_value.clone(),
+            state: inner.state.clone(),
+            data_updated_at: inner.data_updated_at,
+            error_updated_at: inner.error_updated_at,
+            validators: inner.validators.clone(),
+            fresh_for: inner.fresh_for,
+            invalidated: inner.invalidated,
+        }
+    }
+
+    // Constructs a new `Query` with the value and state of the snapshot.
+    pub(crate) fn from_snapshot(snapshot: &QuerySnapshot) -> Self {
+        let query = Self::with_fetcher(
+            snapshot.key.clone(),
+            snapshot.type_id,
+            snapshot.fetcher.clone(),
+            snapshot.options.clone(),
+            None,
+        );
+
+        query.restore(snapshot);
+        query
+    }
+
+    // Replaces the value and state of this query with the snapshot, and notifies the observers.
+    pub(crate) fn restore(&self, snapshot: &QuerySnapshot) {
+        let mut inner = self.inner.write().expect("failed to write in query");
+        inner.signal.cancel();
+
+        inner.future_or_value = match snapshot.value.clone() {
+            Some(value) => resolved(value),
+            None => not_ready(),
+        };
+
+        inner.last_value = snapshot.value.clone();
+        inner.state = snapshot.state.clone();
+        inner.data_updated_at = snapshot.data_updated_at;
+        inner.error_updated_at = snapshot.error_updated_at;
+        inner.validators = snapshot.validators.clone();
+        inner.fresh_for = snapshot.fresh_for;
+        inner.invalidated = snapshot.invalidated;
+
+        let handlers = inner.handlers();
+        let event = QueryChanged {
+            value: inner.last_value.clone(),
+            state: inner.state.clone(),
+            is_fetching: false,
+            is_queued: false,
+            retry: None,
+            progress: None,
+            data_updated_at: inner.data_updated_at,
+            error_updated_at: inner.error_updated_at,
+            timestamp: Instant::now(),
+            cause: None,
+        };
+
+        drop(inner);
+        notify_handlers(handlers, event);
+    }
+
+    /// Cancels the current fetch of this query, if any.
+    pub fn cancel(&self) {
+        self.inner.read().unwrap().signal.cancel();
+    }
+
+    /// Returns this query to its initial state, cancelling the current fetch if any.
+    ///
+    /// The value, error and update times are cleared, the fetcher, options and subscriptions are kept.
+    pub fn reset(&self) {
+        let mut inner = self.inner.write().expect("failed to write in query");
+        inner.signal.cancel();
+        inner.future_or_value = not_ready();
+        inner.state = QueryState::Idle;
+        inner.last_value = None;
+        inner.data_updated_at = None;
+        inner.error_updated_at = None;
+        inner.validators = None;
+        inner.fresh_for = None;
+        inner.invalidated = false;
+        inner.cause = None;
+        inner.next_cause = None;
+
+        let handlers = inner.handlers();
+        drop(inner);
+
+        notify_handlers(
+            handlers,
+            QueryChanged {
+                value: None,
+                state: QueryState::Idle,
+                is_fetching: false,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Returns the time when the value of this query becomes stale, if the value expires.
+    ///
+    /// An invalidated value is stale since it was updated.
+    pub fn stale_at(&self) -> Option<Instant> {
+        let inner = self.inner.read().unwrap();
+        let updated_at = inner.data_updated_at?;
+        if inner.invalidated {
+            return Some(updated_at);
+        }
+
+        let cache_time = inner.fresh_for.or(inner.options.cache_time)?;
+        Some(updated_at + cache_time)
+    }
+
+    /// Returns the interval used to refetch this query, if any.
+    pub fn refetch_time(&self) -> Option<Duration> {
+        self.inner.read().unwrap().options.refetch_time
+    }
+
+    /// Sets the interval used to refetch this query, `None` stops refetching.
+    ///
+    /// The interval starts counting from now.
+    pub fn set_refetch_time(&self, refetch_time: Option<Duration>) {
+        let mut inner = self.inner.write().unwrap();
+        inner.options.refetch_time = refetch_time;
+
+        if let Some(interval) = inner.interval.take() {
+            interval.cancel();
+        }
+
+        drop(inner);
+        self.queue_refetch();
+    }
+
+    /// Marks the value of this query as stale, the next fetch will not use the cached value.
+    pub fn invalidate(&self) {
+        self.inner.write().unwrap().invalidated = true;
     }
 
     /// Returns `true` if the value of the query is expired.
     pub fn is_stale(&self) -> bool {
         let inner = self.inner.read().unwrap();
-        let updated_at = inner.updated_at.clone();
-        let cache_time = inner.cache_time.clone();
+        if inner.invalidated {
+            return true;
+        }
+
+        let updated_at = inner.data_updated_at;
+        let cache_time = inner.fresh_for.or(inner.options.cache_time);
         drop(inner);
 
         let Some(updated_at) = updated_at else {
@@ -265,42 +898,130 @@ impl Query {
     /// Sets the value of this query.
     pub fn set_value<T: 'static>(&mut self, value: T) -> Result<(), QueryError> {
         self.assert_type::<T>()?;
+        self.set_value_rc(Rc::new(value));
+        Ok(())
+    }
+
+    /// Updates the value of this query in place.
+    ///
+    /// The value is only cloned if is referenced outside the cache, like by the handles
+    /// of the observers, otherwise is modified without copying, useful for large collections.
+    ///
+    /// Returns `QueryError::NotReady` if the query don't have a value yet.
+    pub fn update_value<T, F>(&mut self, f: F) -> Result<(), QueryError>
+    where
+        T: Clone + 'static,
+        F: FnOnce(&mut T),
+    {
+        self.assert_type::<T>()?;
 
-        let fut = ok(Rc::new(value) as Rc<dyn Any>).boxed_local().shared();
+        let value = {
+            let mut inner = self.inner.write().expect("failed to write in query");
+            let value = inner.last_value.take().ok_or(QueryError::NotReady);
+
+            // The resolved future also holds the value
+            if value.is_ok() && inner.future_or_value.peek().is_some() {
+                inner.future_or_value = not_ready();
+            }
+
+            value?
+        };
+
+        let mut value = value
+            .downcast::<T>()
+            .map_err(|_| QueryError::type_mismatch::<T>())?;
+
+        f(Rc::make_mut(&mut value));
+        self.set_value_rc(value);
+        Ok(())
+    }
+
+    fn set_value_rc(&mut self, value: Rc<dyn Any>) {
+        let fut = resolved(value);
         let value = futures::executor::block_on(fut.clone()).unwrap();
         {
             let mut inner = self.inner.write().expect("failed to write in query");
             inner.future_or_value = fut;
+            inner.invalidated = false;
         }
 
         self.on_change(QueryChanged {
             value: Some(value),
             state: QueryState::Ready,
             is_fetching: false,
+            ..Default::default()
         });
 
         // refetch
-        self.queue_refetch::<T>();
-        Ok(())
+        self.queue_refetch();
     }
 
-    fn send_event(&mut self, event: QueryChanged, notify_all: bool) {
+    fn send_event(&mut self, mut event: QueryChanged, notify_all: bool) {
         let mut inner = self.inner.write().expect("failed to write in query");
-        if let Some(handler) = inner.on_change.as_ref() {
-            (handler.0)(event.clone())
-        }
 
-        if !notify_all {
-            return;
+        if notify_all {
+            match &event.state {
+                QueryState::Ready => inner.data_updated_at = Some(Instant::now()),
+                QueryState::Failed(_) => inner.error_updated_at = Some(Instant::now()),
+                _ => {}
+            }
+
+            inner.last_value = event.value.clone();
+            inner.state = event.state.clone();
         }
 
-        let QueryChanged { value, state, .. } = event;
-        if matches!(state, QueryState::Ready) {
-            inner.updated_at = Some(Instant::now());
+        event.data_updated_at = inner.data_updated_at;
+        event.error_updated_at = inner.error_updated_at;
+        event.cause = event.cause.or(inner.cause);
+
+        // We release the lock before notifying
+        let handlers = inner.handlers();
+        drop(inner);
+
+        notify_handlers(handlers, event);
+    }
+
+    /// Subscribes to the changes of this query, the callback is called until
+    /// the returned `Subscription` is dropped.
+    pub fn subscribe<F>(&self, callback: F) -> Subscription
+    where
+        F: Fn(QueryChanged) + 'static,
+    {
+        let mut inner = self.inner.write().expect("failed to write in query");
+        let id = inner.next_listener_id;
+        inner.next_listener_id += 1;
+        inner.listeners.push(Listener {
+            id,
+            enabled: true,
+            handler: OnQueryChangeHandler(Rc::new(callback)),
+        });
+
+        Subscription {
+            inner: Arc::downgrade(&self.inner),
+            id,
         }
+    }
+
+    // Sets the handler that forwards the changes of this query to the cache listeners
+    pub(crate) fn set_cache_handler(&self, handler: Rc<dyn Fn(QueryChanged)>) {
+        let mut inner = self.inner.write().expect("failed to write in query");
+        inner.on_cache_change = Some(OnQueryChangeHandler(handler));
+    }
+
+    // Sets the flag of the client that pauses the refetch interval of this query.
+    pub(crate) fn set_paused_handle(&self, paused: Rc<Cell<bool>>) {
+        self.inner.write().unwrap().paused = Some(paused);
+    }
+
+    // Sets the spawner of the client used for the refetch interval of this query.
+    pub(crate) fn set_spawner(&self, spawner: SpawnerHandle) {
+        self.inner.write().unwrap().spawner = spawner;
+    }
 
-        inner.last_value = value;
-        inner.state = state;
+    // Returns `true` if the client of this query is paused.
+    fn is_paused(&self) -> bool {
+        let inner = self.inner.read().unwrap();
+        inner.paused.as_ref().map(|x| x.get()).unwrap_or(false)
     }
 
     fn on_change(&mut self, event: QueryChanged) {
@@ -311,25 +1032,48 @@ impl Query {
         self.send_event(event, true);
     }
 
-    fn queue_refetch<T: 'static>(&self) {
+    fn queue_refetch(&self) {
         let mut inner = self.inner.write().unwrap();
 
-        if let Some(refetch_time) = inner.refetch_time {
+        if let Some(refetch_time) = inner.options.refetch_time {
             if let Some(interval) = inner.interval.take() {
                 interval.cancel();
             };
 
+            let spawner = inner.spawner.clone();
+            let timer = inner.options.timer.clone().unwrap_or_default();
             drop(inner); // We don't need to hold the ownership anymore
 
-            let this = self.clone();
+            // The interval holds a weak reference, so dropping the last query stops it
+            let weak = Arc::downgrade(&self.inner);
+            let key = self.key.clone();
+            let type_id = self.type_id;
+            let task_spawner = spawner.clone();
+
+            let interval = Interval::new(&spawner, &timer, refetch_time, move || {
+                let Some(inner) = weak.upgrade() else {
+                    return;
+                };
+
+                let mut this = Query {
+                    key: key.clone(),
+                    type_id,
+                    inner,
+                };
+
+                // Only the enabled observers keep the query refetching
+                if !this.is_enabled() || this.is_paused() || is_refetch_suspended() {
+                    return;
+                }
 
-            let interval = Interval::new(refetch_time, move || {
-                let this = this.clone();
+                this.inner.write().unwrap().background_fetches += 1;
 
-                spawn_local(async move {
+                task_spawner.spawn(async move {
                     // We fetch and ignore the errors, on failure the inner state will be updated
-                    let mut this = this.clone();
-                    this.fetch::<T>().await.ok();
+                    this.fetch_with_cause(FetchCause::Interval).await.ok();
+
+                    // Released before the query is dropped, see `Drop for Query`
+                    this.inner.write().unwrap().background_fetches -= 1;
                 });
             });
 
@@ -339,13 +1083,193 @@ impl Query {
     }
 }
 
+// Notifies the retry progress of the query, we use a weak reference
+// because the handler is owned by the inner future of the query.
+fn retry_handler(inner: Weak<RwLock<Inner>>) -> OnRetry {
+    Rc::new(move |retry| {
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+
+        let inner = inner.read().expect("failed to read query");
+        let handlers = inner.handlers();
+
+        let event = QueryChanged {
+            value: inner.last_value.clone(),
+            state: inner.state.clone(),
+            is_fetching: true,
+            is_queued: inner.queued,
+            retry: Some(retry),
+            progress: None,
+            data_updated_at: inner.data_updated_at,
+            error_updated_at: inner.error_updated_at,
+            timestamp: Instant::now(),
+            cause: inner.cause,
+        };
+
+        drop(inner);
+        notify_handlers(handlers, event);
+    })
+}
+
+// Notifies when the fetch enters and leaves the queue of the client, see `retry_handler`.
+fn queued_handler(inner: Weak<RwLock<Inner>>) -> OnQueued {
+    Rc::new(move |queued| {
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+
+        let mut inner = inner.write().expect("failed to write in query");
+        inner.queued = queued;
+        let handlers = inner.handlers();
+
+        let event = QueryChanged {
+            value: inner.last_value.clone(),
+            state: inner.state.clone(),
+            is_fetching: true,
+            is_queued: queued,
+            retry: None,
+            progress: None,
+            data_updated_at: inner.data_updated_at,
+            error_updated_at: inner.error_updated_at,
+            timestamp: Instant::now(),
+            cause: inner.cause,
+        };
+
+        drop(inner);
+        notify_handlers(handlers, event);
+    })
+}
+
+// Notifies the progress of the fetch, see `retry_handler`.
+fn progress_handler(inner: Weak<RwLock<Inner>>) -> OnProgress {
+    Rc::new(move |progress| {
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+
+        let inner = inner.read().expect("failed to read query");
+        let handlers = inner.handlers();
+
+        let event = QueryChanged {
+            value: inner.last_value.clone(),
+            state: inner.state.clone(),
+            is_fetching: true,
+            is_queued: inner.queued,
+            retry: None,
+            progress: Some(progress),
+            data_updated_at: inner.data_updated_at,
+            error_updated_at: inner.error_updated_at,
+            timestamp: Instant::now(),
+            cause: inner.cause,
+        };
+
+        drop(inner);
+        notify_handlers(handlers, event);
+    })
+}
+
+// Updates the value of the query with a partial value while still fetching, see `retry_handler`.
+fn partial_handler(inner: Weak<RwLock<Inner>>, type_id: TypeId) -> OnPartial {
+    Rc::new(move |value: Rc<dyn Any>| {
+        // Ignore values that don't match the type of the query
+        if (*value).type_id() != type_id {
+            return;
+        }
+
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+
+        let mut inner = inner.write().expect("failed to write in query");
+        inner.last_value = Some(value.clone());
+        inner.state = QueryState::Ready;
+        inner.data_updated_at = Some(Instant::now());
+
+        let handlers = inner.handlers();
+
+        let event = QueryChanged {
+            value: Some(value),
+            state: QueryState::Ready,
+            is_fetching: true,
+            is_queued: inner.queued,
+            retry: None,
+            progress: None,
+            data_updated_at: inner.data_updated_at,
+            error_updated_at: inner.error_updated_at,
+            timestamp: Instant::now(),
+            cause: inner.cause,
+        };
+
+        drop(inner);
+        notify_handlers(handlers, event);
+    })
+}
+
+/// A subscription to the changes of a `Query`, returned by `Query::subscribe`.
+///
+/// The callback is removed from the query when the subscription is dropped.
+#[must_use = "the subscription is cancelled when dropped"]
+pub struct Subscription {
+    inner: Weak<RwLock<Inner>>,
+    id: usize,
+}
+
+impl Subscription {
+    // Returns `true` if this subscription listens to the given query.
+    pub(crate) fn is_subscribed_to(&self, query: &Query) -> bool {
+        std::ptr::eq(self.inner.as_ptr(), Arc::as_ptr(&query.inner))
+    }
+
+    /// Cancels this subscription.
+    pub fn unsubscribe(self) {}
+
+    /// Enables or disables this subscription, a disabled subscription don't receive
+    /// the changes of the query and don't keep the query refetching.
+    pub fn set_enabled(&self, enabled: bool) {
+        if let Some(inner) = self.inner.upgrade() {
+            let mut inner = inner.write().expect("failed to write in query");
+            if let Some(listener) = inner.listeners.iter_mut().find(|x| x.id == self.id) {
+                listener.enabled = enabled;
+            }
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.upgrade() {
+            let mut inner = inner.write().expect("failed to write in query");
+            inner.listeners.retain(|x| x.id != self.id);
+        }
+    }
+}
+
+impl Debug for Subscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscription").field("id", &self.id).finish()
+    }
+}
+
 impl Drop for Query {
     fn drop(&mut self) {
-        if Arc::strong_count(&self.inner) != 1 {
-            return;
+        let count = Arc::strong_count(&self.inner);
+        if count != 1 {
+            // The fetches started by the interval don't keep the query alive,
+            // if the inner state is in use the query is still alive
+            let Ok(inner) = self.inner.try_read() else {
+                return;
+            };
+
+            if count - inner.background_fetches != 1 {
+                return;
+            }
         }
 
+        // Stops the interval and the network activity of the fetch in progress, if any
         let mut inner = self.inner.write().unwrap();
+        inner.signal.cancel();
+
         if let Some(interval) = inner.interval.take() {
             interval.cancel();
         }
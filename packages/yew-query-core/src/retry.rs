@@ -1,11 +1,46 @@
+use instant::Instant;
 use std::{fmt::Debug, rc::Rc, time::Duration};
 
 type DurationIterator = Box<dyn Iterator<Item = Duration>>;
 
+pub(crate) type OnRetry = Rc<dyn Fn(RetryProgress)>;
+
+/// The progress of retrying a failed fetch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryProgress {
+    attempt: usize,
+    retry_at: Instant,
+}
+
+impl RetryProgress {
+    pub(crate) fn new(attempt: usize, delay: Duration) -> Self {
+        RetryProgress {
+            attempt,
+            retry_at: Instant::now() + delay,
+        }
+    }
+
+    /// Returns the number of the next retry attempt, starting from `1`.
+    pub fn attempt(&self) -> usize {
+        self.attempt
+    }
+
+    /// Returns the time remaining until the next retry.
+    pub fn retry_in(&self) -> Duration {
+        self.retry_at.saturating_duration_since(Instant::now())
+    }
+}
+
 /// Boxes a retry iterator.
 #[derive(Clone)]
 pub struct Retry(Rc<dyn Fn() -> DurationIterator>);
 
+impl PartialEq for Retry {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
 impl Retry {
     /// Constructs a new `Retry`.
     pub fn new<F, I>(f: F) -> Self
@@ -49,6 +84,7 @@ mod tests {
     use super::Retry;
 
     #[test]
+    #[allow(clippy::manual_repeat_n)]
     fn retry_sleep_test() {
         let retry = Retry::new(move || std::iter::repeat(Duration::from_millis(100)).take(3));
         let start = Instant::now();
@@ -0,0 +1,142 @@
+use crate::{
+    fetcher::{BoxFetcher, Validators},
+    QueryKey, QueryOptions, QueryState,
+};
+use instant::{Duration, Instant};
+use std::{
+    any::{Any, TypeId},
+    fmt::Debug,
+    rc::Rc,
+};
+
+/// A copy of the value and state of a query.
+#[derive(Clone)]
+pub struct QuerySnapshot {
+    pub(crate) key: QueryKey,
+    pub(crate) type_id: TypeId,
+    pub(crate) fetcher: BoxFetcher<Rc<dyn Any>>,
+    pub(crate) options: QueryOptions,
+    pub(crate) value: Option<Rc<dyn Any>>,
+    pub(crate) state: QueryState,
+    pub(crate) data_updated_at: Option<Instant>,
+    pub(crate) error_updated_at: Option<Instant>,
+    pub(crate) validators: Option<Validators>,
+    pub(crate) fresh_for: Option<Duration>,
+    pub(crate) invalidated: bool,
+}
+
+impl QuerySnapshot {
+    /// Returns the key of the query.
+    pub fn key(&self) -> &QueryKey {
+        &self.key
+    }
+
+    /// Returns the value of the query, if any and is of type `T`.
+    pub fn value<T: 'static>(&self) -> Option<Rc<T>> {
+        self.value.clone()?.downcast::<T>().ok()
+    }
+
+    /// Returns the state of the query.
+    pub fn state(&self) -> &QueryState {
+        &self.state
+    }
+
+    /// Returns the last time the query resolved to a value.
+    pub fn data_updated_at(&self) -> Option<Instant> {
+        self.data_updated_at
+    }
+
+    /// Returns the last time the query failed.
+    pub fn error_updated_at(&self) -> Option<Instant> {
+        self.error_updated_at
+    }
+
+    // Returns `true` if the value or state are different from the other snapshot.
+    fn is_changed(&self, other: &QuerySnapshot) -> bool {
+        let same_value = match (&self.value, &other.value) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+
+        !same_value
+            || std::mem::discriminant(&self.state) != std::mem::discriminant(&other.state)
+            || self.data_updated_at != other.data_updated_at
+            || self.error_updated_at != other.error_updated_at
+    }
+}
+
+impl Debug for QuerySnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuerySnapshot")
+            .field("key", &self.key)
+            .field("state", &self.state)
+            .field("data_updated_at", &self.data_updated_at)
+            .field("error_updated_at", &self.error_updated_at)
+            .finish()
+    }
+}
+
+/// A copy of the value and state of all the queries of a cache.
+#[derive(Debug, Clone, Default)]
+pub struct CacheSnapshot {
+    pub(crate) queries: Vec<QuerySnapshot>,
+}
+
+impl CacheSnapshot {
+    /// Returns the snapshot of the query with the given key.
+    pub fn get(&self, key: &QueryKey) -> Option<&QuerySnapshot> {
+        self.queries.iter().find(|x| &x.key == key)
+    }
+
+    /// Returns an iterator over the snapshots of the queries.
+    pub fn iter(&self) -> impl Iterator<Item = &QuerySnapshot> {
+        self.queries.iter()
+    }
+
+    /// Returns the number of queries in the snapshot.
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    /// Returns `true` if the snapshot has no queries.
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+
+    /// Returns the changes from this snapshot to the other.
+    pub fn diff(&self, other: &CacheSnapshot) -> Vec<SnapshotDiff> {
+        let mut diff = Vec::new();
+
+        for before in self.queries.iter() {
+            match other.get(&before.key) {
+                Some(after) if before.is_changed(after) => {
+                    diff.push(SnapshotDiff::Changed(before.key.clone()))
+                }
+                Some(_) => {}
+                None => diff.push(SnapshotDiff::Removed(before.key.clone())),
+            }
+        }
+
+        for after in other.queries.iter() {
+            if self.get(&after.key).is_none() {
+                diff.push(SnapshotDiff::Added(after.key.clone()));
+            }
+        }
+
+        diff
+    }
+}
+
+/// A change between two `CacheSnapshot`s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotDiff {
+    /// The query was added.
+    Added(QueryKey),
+
+    /// The query was removed.
+    Removed(QueryKey),
+
+    /// The value or state of the query changed.
+    Changed(QueryKey),
+}
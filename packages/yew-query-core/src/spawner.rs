@@ -0,0 +1,47 @@
+use futures::{future::LocalBoxFuture, Future};
+use std::{fmt::Debug, rc::Rc};
+
+/// Runs the background tasks of a `QueryClient`, like the refetch intervals
+/// and the refetches started by events.
+///
+/// Allows using the core with other executors, the tasks are not `Send`
+/// so the executor must run them in the current thread.
+pub trait Spawner {
+    /// Spawns the future in the current thread.
+    fn spawn_local(&self, future: LocalBoxFuture<'static, ()>);
+}
+
+/// The default `Spawner`, uses `prokio` which runs the tasks with `wasm-bindgen-futures`
+/// in the browser and in a tokio `LocalSet` in native targets.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultSpawner;
+
+impl Spawner for DefaultSpawner {
+    fn spawn_local(&self, future: LocalBoxFuture<'static, ()>) {
+        prokio::spawn_local(future);
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct SpawnerHandle(pub Rc<dyn Spawner>);
+
+impl SpawnerHandle {
+    pub fn spawn<Fut>(&self, future: Fut)
+    where
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.0.spawn_local(Box::pin(future));
+    }
+}
+
+impl Default for SpawnerHandle {
+    fn default() -> Self {
+        SpawnerHandle(Rc::new(DefaultSpawner))
+    }
+}
+
+impl Debug for SpawnerHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Spawner")
+    }
+}
@@ -1,9 +1,10 @@
 use crate::Error;
 
 /// Represents the state of a query.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub enum QueryState {
     /// The query is stopped or not had started.
+    #[default]
     Idle,
 
     /// The query is loading the data for the first time.
@@ -37,3 +38,57 @@ impl QueryState {
         matches!(self, QueryState::Failed(_))
     }
 }
+
+/// Represents whether a query is fetching, independently of the `QueryState`.
+///
+/// A query with data can be fetching again in background, so the query is `Ready`
+/// and `Fetching` at the same time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FetchStatus {
+    /// The query is not fetching.
+    #[default]
+    Idle,
+
+    /// The query is fetching the data.
+    Fetching,
+
+    /// The query can't fetch, the fetch is waiting in the queue of the client to start,
+    /// see `QueryClientBuilder::max_concurrent_fetches`, or the refetches are paused
+    /// because the client is paused or offline, see `QueryClient::pause`.
+    Paused,
+}
+
+impl FetchStatus {
+    /// Constructs the status from whether a query is fetching and whether the fetch is queued.
+    pub fn new(is_fetching: bool, is_queued: bool) -> Self {
+        match (is_fetching, is_queued) {
+            (_, true) => FetchStatus::Paused,
+            (true, false) => FetchStatus::Fetching,
+            (false, false) => FetchStatus::Idle,
+        }
+    }
+
+    /// Returns `Paused` if the query is not fetching and the refetches are paused,
+    /// otherwise returns this status.
+    pub fn or_paused(self, paused: bool) -> Self {
+        match self {
+            FetchStatus::Idle if paused => FetchStatus::Paused,
+            status => status,
+        }
+    }
+
+    /// Returns `true` if the query is not fetching.
+    pub fn is_idle(&self) -> bool {
+        matches!(self, FetchStatus::Idle)
+    }
+
+    /// Returns `true` if the query is fetching the data.
+    pub fn is_fetching(&self) -> bool {
+        matches!(self, FetchStatus::Fetching)
+    }
+
+    /// Returns `true` if the query can't fetch.
+    pub fn is_paused(&self) -> bool {
+        matches!(self, FetchStatus::Paused)
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{Error, FetchCause, QueryKey};
+use instant::{Duration, Instant};
+use std::{any::Any, collections::VecDeque, fmt::Debug, rc::Rc};
+
+type EstimateFn = dyn Fn(&dyn Any) -> Option<usize>;
+
+// Estimates the size in bytes of the values of a type, see `QueryClientBuilder::size_estimator`.
+#[derive(Clone)]
+pub(crate) struct SizeEstimatorHandle(Rc<EstimateFn>);
+
+impl SizeEstimatorHandle {
+    pub fn new<T, F>(estimate: F) -> Self
+    where
+        T: 'static,
+        F: Fn(&T) -> usize + 'static,
+    {
+        // The values of other types are not estimated
+        SizeEstimatorHandle(Rc::new(move |value| {
+            value.downcast_ref::<T>().map(&estimate)
+        }))
+    }
+
+    pub fn estimate(&self, value: &dyn Any) -> Option<usize> {
+        (self.0)(value)
+    }
+}
+
+impl Debug for SizeEstimatorHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SizeEstimator")
+    }
+}
+
+/// Information about a query in the cache, see `QueryClient::cache_stats`.
+#[derive(Debug, Clone)]
+pub struct QueryStats {
+    /// The key of the query.
+    pub key: QueryKey,
+
+    /// The time since the query resolved to a value, `None` if the query has no value.
+    pub age: Option<Duration>,
+
+    /// The number of observers of the query.
+    pub observers: usize,
+
+    /// The approximate size in bytes of the value of the query,
+    /// `None` if there is no value or no estimator for the type of the value.
+    pub estimated_size: Option<usize>,
+}
+
+/// Information about the cache of a `QueryClient`, see `QueryClient::cache_stats`.
+#[derive(Debug, Clone)]
+pub struct CacheStats {
+    /// The queries in the cache.
+    pub queries: Vec<QueryStats>,
+
+    /// The approximate size in bytes of the values in the cache, only including the values
+    /// with an estimator, `None` if the client don't have estimators.
+    pub estimated_size: Option<usize>,
+}
+
+impl CacheStats {
+    /// Returns the number of queries in the cache.
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    /// Returns `true` if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+
+    /// Returns the number of queries being observed.
+    pub fn active_count(&self) -> usize {
+        self.queries.iter().filter(|x| x.observers > 0).count()
+    }
+}
+
+/// The result of a fetch in the `FetchHistory` of a query.
+#[derive(Debug, Clone)]
+pub enum FetchOutcome {
+    /// The fetch resolved to a value.
+    Success,
+
+    /// The fetch resolved to `QueryError::NotModified`, the cached value is still valid.
+    NotModified,
+
+    /// The fetch failed with the given error.
+    Failed(Error),
+}
+
+impl FetchOutcome {
+    /// Returns `true` if the fetch didn't fail.
+    pub fn is_ok(&self) -> bool {
+        !matches!(self, FetchOutcome::Failed(_))
+    }
+}
+
+/// A completed fetch of a query.
+#[derive(Debug, Clone)]
+pub struct FetchRecord {
+    /// The time the fetch started.
+    pub started_at: Instant,
+
+    /// The time the fetch took, including the retries and the time waiting to start.
+    pub duration: Duration,
+
+    /// What started the fetch.
+    pub cause: FetchCause,
+
+    /// The result of the fetch.
+    pub outcome: FetchOutcome,
+}
+
+/// The last fetches of a query, see `QueryClient::fetch_history`.
+///
+/// Only the last `FetchHistory::CAPACITY` fetches are kept, the cancelled fetches are not recorded.
+#[derive(Debug, Clone, Default)]
+pub struct FetchHistory {
+    records: VecDeque<FetchRecord>,
+}
+
+impl FetchHistory {
+    /// The max number of fetches kept in the history.
+    pub const CAPACITY: usize = 20;
+
+    // Adds a fetch to the history, removing the oldest if is full.
+    pub(crate) fn push(&mut self, record: FetchRecord) {
+        if self.records.len() == Self::CAPACITY {
+            self.records.pop_front();
+        }
+
+        self.records.push_back(record);
+    }
+
+    /// Returns the number of fetches in the history.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns `true` if the history is empty.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns an iterator over the fetches, from the oldest to the newest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &FetchRecord> + '_ {
+        self.records.iter()
+    }
+
+    /// Returns the last fetch.
+    pub fn last(&self) -> Option<&FetchRecord> {
+        self.records.back()
+    }
+
+    /// Returns the fraction of fetches that failed, `None` if the history is empty.
+    pub fn error_rate(&self) -> Option<f64> {
+        if self.records.is_empty() {
+            return None;
+        }
+
+        let failed = self.records.iter().filter(|x| !x.outcome.is_ok()).count();
+        Some(failed as f64 / self.records.len() as f64)
+    }
+
+    /// Returns the average duration of the fetches, `None` if the history is empty.
+    pub fn average_duration(&self) -> Option<Duration> {
+        if self.records.is_empty() {
+            return None;
+        }
+
+        let total = self.records.iter().map(|x| x.duration).sum::<Duration>();
+        Some(total / self.records.len() as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FetchHistory, FetchOutcome, FetchRecord};
+    use crate::{error::QueryError, Error, FetchCause};
+    use instant::{Duration, Instant};
+
+    fn record(millis: u64, outcome: FetchOutcome) -> FetchRecord {
+        FetchRecord {
+            started_at: Instant::now(),
+            duration: Duration::from_millis(millis),
+            cause: FetchCause::Manual,
+            outcome,
+        }
+    }
+
+    #[test]
+    fn fetch_history_test() {
+        let mut history = FetchHistory::default();
+        assert_eq!(history.error_rate(), None);
+        assert_eq!(history.average_duration(), None);
+
+        history.push(record(100, FetchOutcome::Success));
+        history.push(record(
+            300,
+            FetchOutcome::Failed(Error::new(QueryError::Timeout)),
+        ));
+        assert_eq!(history.error_rate(), Some(0.5));
+        assert_eq!(history.average_duration(), Some(Duration::from_millis(200)));
+
+        // The oldest fetches are removed when is full
+        for _ in 0..FetchHistory::CAPACITY {
+            history.push(record(50, FetchOutcome::NotModified));
+        }
+
+        assert_eq!(history.len(), FetchHistory::CAPACITY);
+        assert_eq!(history.error_rate(), Some(0.0));
+        assert_eq!(history.average_duration(), Some(Duration::from_millis(50)));
+    }
+}
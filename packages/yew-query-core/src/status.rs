@@ -0,0 +1,68 @@
+use std::{cell::RefCell, rc::Rc};
+
+type StatusListener = Rc<dyn Fn(bool)>;
+
+struct StatusInner {
+    value: bool,
+    next_id: usize,
+    listeners: Vec<(usize, StatusListener)>,
+}
+
+// A boolean status shared between clones which notifies its listeners when changes,
+// used by the managers which status is set manually.
+#[derive(Clone)]
+pub(crate) struct ManualStatus(Rc<RefCell<StatusInner>>);
+
+impl ManualStatus {
+    pub fn new(value: bool) -> Self {
+        ManualStatus(Rc::new(RefCell::new(StatusInner {
+            value,
+            next_id: 0,
+            listeners: Vec::new(),
+        })))
+    }
+
+    pub fn get(&self) -> bool {
+        self.0.borrow().value
+    }
+
+    pub fn set(&self, value: bool) {
+        let listeners = {
+            let mut inner = self.0.borrow_mut();
+            if inner.value == value {
+                return;
+            }
+
+            inner.value = value;
+            inner
+                .listeners
+                .iter()
+                .map(|(_, x)| x.clone())
+                .collect::<Vec<_>>()
+        };
+
+        // We release the borrow before notifying, listeners may add or remove listeners
+        for listener in listeners {
+            listener(value);
+        }
+    }
+
+    pub fn add_listener(&self, listener: StatusListener) -> usize {
+        let mut inner = self.0.borrow_mut();
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner.listeners.push((id, listener));
+        id
+    }
+
+    pub fn remove_listener(&self, id: usize) -> bool {
+        let mut inner = self.0.borrow_mut();
+        let len = inner.listeners.len();
+        inner.listeners.retain(|(x, _)| *x != id);
+        inner.listeners.len() != len
+    }
+
+    pub fn listener_count(&self) -> usize {
+        self.0.borrow().listeners.len()
+    }
+}
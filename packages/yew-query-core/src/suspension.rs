@@ -0,0 +1,61 @@
+use std::{cell::Cell, marker::PhantomData};
+
+thread_local! {
+    static SUSPENDED: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Suppresses the refetches started by events, like the refetch interval of the queries,
+/// until the returned guard is dropped.
+///
+/// Useful during route transitions, to not refetch the queries of the components about to unmount.
+/// The explicit fetches and refetches are not affected.
+pub fn suspend_refetches() -> RefetchSuspension {
+    SUSPENDED.with(|suspended| suspended.set(suspended.get() + 1));
+    RefetchSuspension {
+        _marker: PhantomData,
+    }
+}
+
+/// Returns `true` if the refetches started by events are suppressed, see `suspend_refetches`.
+pub fn is_refetch_suspended() -> bool {
+    SUSPENDED.with(|suspended| suspended.get() > 0)
+}
+
+/// A guard returned by `suspend_refetches`, the refetches are resumed when all the guards are dropped.
+#[must_use = "the refetches are resumed when the guard is dropped"]
+#[derive(Debug)]
+pub struct RefetchSuspension {
+    // The counter is per thread, so the guard can't be sent to other thread
+    _marker: PhantomData<*const ()>,
+}
+
+impl RefetchSuspension {
+    /// Resumes the refetches, same as dropping the guard.
+    pub fn resume(self) {}
+}
+
+impl Drop for RefetchSuspension {
+    fn drop(&mut self) {
+        SUSPENDED.with(|suspended| suspended.set(suspended.get() - 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_refetch_suspended, suspend_refetches};
+
+    #[test]
+    fn suspend_refetches_test() {
+        assert!(!is_refetch_suspended());
+
+        let first = suspend_refetches();
+        let second = suspend_refetches();
+        assert!(is_refetch_suspended());
+
+        first.resume();
+        assert!(is_refetch_suspended());
+
+        drop(second);
+        assert!(!is_refetch_suspended());
+    }
+}
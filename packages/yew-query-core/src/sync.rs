@@ -0,0 +1,198 @@
+use crate::{error::QueryError, fetcher::Fetch, Error, QueryClient, QueryKey};
+use futures::{
+    channel::{mpsc, oneshot},
+    future::LocalBoxFuture,
+    Future, FutureExt, StreamExt,
+};
+use std::fmt::Debug;
+use tokio::task::LocalSet;
+
+type Task = Box<dyn FnOnce(QueryClient) -> LocalBoxFuture<'static, ()> + Send>;
+
+/// A `Send + Sync` handle to a `QueryClient`, can be shared between the threads and tasks
+/// of multithreaded runtimes.
+///
+/// The `QueryClient` is not thread-safe, so it runs in a dedicated thread with its own executor
+/// and the calls to the handle are sent to that thread. The values are cloned out of the cache
+/// because the cached values are not `Send`.
+///
+/// The thread stops when the last handle is dropped.
+///
+/// # Example
+/// ```
+/// use std::{convert::Infallible, time::Duration};
+/// use yew_query_core::{QueryClient, SyncQueryClient};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let client = SyncQueryClient::new(|| {
+///     QueryClient::builder()
+///         .cache_time(Duration::from_secs(60))
+///         .build()
+/// });
+///
+/// let value = tokio::spawn({
+///     let client = client.clone();
+///     async move {
+///         client
+///             .fetch_query("number", || async { Ok::<_, Infallible>(42_i32) })
+///             .await
+///     }
+/// });
+///
+/// assert_eq!(value.await.unwrap().unwrap(), 42);
+/// assert_eq!(client.get_query_data::<i32>("number").await.unwrap(), 42);
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SyncQueryClient {
+    sender: mpsc::UnboundedSender<Task>,
+}
+
+impl SyncQueryClient {
+    /// Spawns the thread of the client, the `QueryClient` is constructed in that thread
+    /// using the given function.
+    pub fn new<F>(build: F) -> Self
+    where
+        F: FnOnce() -> QueryClient + Send + 'static,
+    {
+        let (sender, mut receiver) = mpsc::unbounded::<Task>();
+
+        std::thread::Builder::new()
+            .name("yew-query-client".to_owned())
+            .spawn(move || {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_time()
+                    .build()
+                    .expect("failed to build the runtime of the client");
+
+                LocalSet::new().block_on(&runtime, async move {
+                    let client = build();
+
+                    while let Some(task) = receiver.next().await {
+                        tokio::task::spawn_local(task(client.clone()));
+                    }
+                });
+            })
+            .expect("failed to spawn the thread of the client");
+
+        SyncQueryClient { sender }
+    }
+
+    /// Runs the function with the `QueryClient` in the thread of the client and returns the result.
+    ///
+    /// # Panics
+    /// If the thread of the client stopped, or the function panics.
+    pub async fn run<F, Fut, R>(&self, f: F) -> R
+    where
+        F: FnOnce(QueryClient) -> Fut + Send + 'static,
+        Fut: Future<Output = R> + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let task: Task = Box::new(move |client| {
+            async move {
+                // The receiver may be dropped if the caller is no longer waiting
+                tx.send(f(client).await).ok();
+            }
+            .boxed_local()
+        });
+
+        self.sender
+            .unbounded_send(task)
+            .expect("the thread of the client stopped");
+
+        rx.await.expect("the task of the client was cancelled")
+    }
+
+    /// Fetches and caches the query with the given key, returns a clone of the value.
+    pub async fn fetch_query<F, T>(&self, key: impl Into<String>, f: F) -> Result<T, Error>
+    where
+        F: Fetch<T> + Send + 'static,
+        T: Clone + Send + 'static,
+    {
+        let key = key.into();
+        self.run(move |mut client| async move {
+            let key = QueryKey::of::<T>(key);
+            let value = client.fetch_query(key, f).await?;
+            Ok(T::clone(&value))
+        })
+        .await
+    }
+
+    /// Returns a clone of the cached value of the query with the given key, if fresh.
+    pub async fn get_query_data<T>(&self, key: impl Into<String>) -> Result<T, QueryError>
+    where
+        T: Clone + Send + 'static,
+    {
+        let key = key.into();
+        self.run(move |client| async move {
+            let key = QueryKey::of::<T>(key);
+            let value = client.get_query_data::<T>(&key)?;
+            Ok(T::clone(&value))
+        })
+        .await
+    }
+
+    /// Marks as stale the query with the given key, returns `true` if the query exists.
+    pub async fn invalidate_query<T>(&self, key: impl Into<String>) -> bool
+    where
+        T: 'static,
+    {
+        let key = key.into();
+        self.run(move |client| async move {
+            let key = QueryKey::of::<T>(key);
+            client.invalidate_queries(key) > 0
+        })
+        .await
+    }
+}
+
+impl Debug for SyncQueryClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SyncQueryClient")
+            .field("running", &!self.sender.is_closed())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SyncQueryClient;
+    use crate::QueryClient;
+    use instant::Duration;
+    use std::{convert::Infallible, thread};
+
+    #[test]
+    fn sync_query_client_test() {
+        let client = SyncQueryClient::new(|| {
+            QueryClient::builder()
+                .cache_time(Duration::from_secs(1))
+                .build()
+        });
+
+        let handles = (0..4)
+            .map(|_| {
+                let client = client.clone();
+                thread::spawn(move || {
+                    futures::executor::block_on(client.fetch_query("color", || async {
+                        Ok::<_, Infallible>(String::from("red"))
+                    }))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap().unwrap(), "red");
+        }
+
+        futures::executor::block_on(async {
+            assert_eq!(
+                client.get_query_data::<String>("color").await.unwrap(),
+                "red"
+            );
+            assert!(client.invalidate_query::<String>("color").await);
+            assert!(client.get_query_data::<String>("color").await.is_err());
+        });
+    }
+}
@@ -0,0 +1,51 @@
+pub use platform::request_idle;
+
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+mod platform {
+    use crate::spawner::SpawnerHandle;
+    use instant::Duration;
+    use wasm_bindgen::{closure::Closure, JsCast};
+    use web_sys::IdleRequestOptions;
+
+    /// Runs the function when the browser is idle or after the timeout,
+    /// falls back to `setTimeout` if `requestIdleCallback` is not supported.
+    pub fn request_idle<F>(spawner: &SpawnerHandle, timeout: Duration, f: F)
+    where
+        F: FnOnce() + 'static,
+    {
+        let Some(window) = web_sys::window() else {
+            spawner.spawn(async move { f() });
+            return;
+        };
+
+        let callback = Closure::once_into_js(f);
+        let callback = callback.unchecked_ref::<js_sys::Function>();
+
+        let mut options = IdleRequestOptions::new();
+        options.timeout(timeout.as_millis().try_into().unwrap_or(u32::MAX));
+
+        if window
+            .request_idle_callback_with_options(callback, &options)
+            .is_err()
+        {
+            window
+                .set_timeout_with_callback_and_timeout_and_arguments_0(callback, 0)
+                .expect("failed to set timeout");
+        }
+    }
+}
+
+#[cfg(not(all(feature = "web", target_arch = "wasm32")))]
+mod platform {
+    use crate::spawner::SpawnerHandle;
+    use instant::Duration;
+
+    /// Runs the function in the next turn of the executor, there is no idle detection
+    /// outside the browser or without the `web` feature.
+    pub fn request_idle<F>(spawner: &SpawnerHandle, _timeout: Duration, f: F)
+    where
+        F: FnOnce() + 'static,
+    {
+        spawner.spawn(async move { f() });
+    }
+}
@@ -1,63 +1,9 @@
 pub use atomic::*;
 
-// FIXME: implementation is not cancelling the futures being executed
-// `client::tests::query_with_refetch_test` fails with this implementation
-#[allow(dead_code)]
-mod abortable {
-    use futures::{
-        stream::{AbortHandle, Abortable},
-        StreamExt,
-    };
-    use instant::Duration;
-    use prokio::spawn_local;
-
-    #[derive(Debug, Clone)]
-    pub struct Interval {
-        signal: AbortHandle,
-    }
-
-    impl Interval {
-        pub fn new<F>(duration: Duration, f: F) -> Self
-        where
-            F: Fn() + 'static,
-        {
-            let (signal, registration) = AbortHandle::new_pair();
-            let task = prokio::time::interval(duration);
-            let abortable = Abortable::new(task, registration);
-
-            spawn_local(async move {
-                tokio::pin!(abortable);
-
-                while let Some(_) = abortable.next().await {
-                    if !abortable.is_aborted() {
-                        f();
-                    }
-                }
-            });
-
-            Interval { signal }
-        }
-
-        pub fn cancel(mut self) {
-            self.clear_interval();
-        }
-
-        fn clear_interval(&mut self) {
-            self.signal.abort();
-        }
-    }
-
-    impl Drop for Interval {
-        fn drop(&mut self) {
-            self.clear_interval();
-        }
-    }
-}
-
 #[allow(dead_code)]
 mod atomic {
-    use instant::Duration;
-    use prokio::spawn_local;
+    use crate::{spawner::SpawnerHandle, timer::TimerHandle};
+    use instant::{Duration, Instant};
     use std::sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -69,22 +15,35 @@ mod atomic {
     }
 
     impl Interval {
-        pub fn new<F>(duration: Duration, f: F) -> Self
+        pub fn new<F>(
+            spawner: &SpawnerHandle,
+            timer: &TimerHandle,
+            duration: Duration,
+            f: F,
+        ) -> Self
         where
             F: Fn() + 'static,
         {
             let cancel = Arc::new(AtomicBool::new(false));
             
-            spawn_local({
+            spawner.spawn({
                 let cancel = cancel.clone();
+                let timer = timer.clone();
                 
                 async move {
+                    // The ticks follow a fixed schedule,
+                    // so the delays of the timer and the callback don't accumulate
+                    let mut next_tick = Instant::now() + duration;
+
                     while !cancel.load(Ordering::SeqCst) {
-                        prokio::time::sleep(duration).await;
+                        let delay = next_tick.saturating_duration_since(Instant::now());
+                        timer.sleep(delay).await;
 
                         if !cancel.load(Ordering::SeqCst) {
                             f();
                         }
+
+                        next_tick = next_tick_after(next_tick, duration, Instant::now());
                     }
                 }
             });
@@ -108,4 +67,37 @@ mod atomic {
             self.clear_interval();
         }
     }
+
+    // Returns the next tick of the schedule after `now`, the ticks missed are skipped
+    pub(super) fn next_tick_after(tick: Instant, duration: Duration, now: Instant) -> Instant {
+        let next_tick = tick + duration;
+        if next_tick > now || duration.is_zero() {
+            return next_tick.max(now);
+        }
+
+        let missed = (now - next_tick).as_nanos() / duration.as_nanos();
+        let missed = u32::try_from(missed).unwrap_or(u32::MAX);
+        next_tick + duration * missed.saturating_add(1)
+    }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::atomic::next_tick_after;
+    use instant::{Duration, Instant};
+
+    #[test]
+    fn next_tick_after_test() {
+        let start = Instant::now();
+        let second = Duration::from_secs(1);
+
+        // A late tick don't delay the schedule
+        let now = start + Duration::from_millis(1300);
+        assert_eq!(next_tick_after(start + second, second, now), start + second * 2);
+
+        // The missed ticks are skipped
+        let now = start + Duration::from_millis(3500);
+        assert_eq!(next_tick_after(start + second, second, now), start + second * 4);
+    }
+}
+
@@ -1 +1,2 @@
+pub mod idle;
 pub mod interval;
@@ -0,0 +1,121 @@
+use futures::{
+    future::{select, Either, LocalBoxFuture},
+    Future,
+};
+use instant::Duration;
+use std::{fmt::Debug, rc::Rc};
+
+/// Provides the timers of a `QueryClient`, used for the timeouts of the fetches,
+/// the delays between retries and the refetch intervals.
+///
+/// Allows custom runtimes and test clocks to drive the time of the queries.
+pub trait TimerBackend {
+    /// Returns a future that completes after the given duration.
+    ///
+    /// The durations are at most `i32::MAX` milliseconds, the limit of `setTimeout`,
+    /// longer sleeps are split in consecutive calls.
+    fn sleep(&self, duration: Duration) -> LocalBoxFuture<'static, ()>;
+}
+
+/// The default `TimerBackend`, uses the timers of `prokio` which are backed by `setTimeout`
+/// in wasm and by tokio in native targets.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultTimer;
+
+impl TimerBackend for DefaultTimer {
+    fn sleep(&self, duration: Duration) -> LocalBoxFuture<'static, ()> {
+        Box::pin(prokio::time::sleep(duration))
+    }
+}
+
+// The max delay of `setTimeout`, longer delays fire immediately
+const MAX_TIMER_DELAY: Duration = Duration::from_millis(i32::MAX as u64);
+
+#[derive(Clone)]
+pub(crate) struct TimerHandle(pub Rc<dyn TimerBackend>);
+
+impl PartialEq for TimerHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl TimerHandle {
+    pub fn sleep(&self, duration: Duration) -> LocalBoxFuture<'static, ()> {
+        if duration <= MAX_TIMER_DELAY {
+            return self.0.sleep(duration);
+        }
+
+        // Long timers, like a cache time of months, are chained to not fire early
+        let timer = self.0.clone();
+        Box::pin(async move {
+            let mut remaining = duration;
+            while !remaining.is_zero() {
+                let delay = remaining.min(MAX_TIMER_DELAY);
+                timer.sleep(delay).await;
+                remaining -= delay;
+            }
+        })
+    }
+
+    // Resolves to the output of the future, or `None` if the duration elapses first.
+    pub async fn timeout<Fut>(&self, duration: Duration, fut: Fut) -> Option<Fut::Output>
+    where
+        Fut: Future,
+    {
+        futures::pin_mut!(fut);
+
+        match select(fut, self.sleep(duration)).await {
+            Either::Left((ret, _)) => Some(ret),
+            Either::Right(_) => None,
+        }
+    }
+}
+
+impl Default for TimerHandle {
+    fn default() -> Self {
+        TimerHandle(Rc::new(DefaultTimer))
+    }
+}
+
+impl Debug for TimerHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Timer")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TimerBackend, TimerHandle, MAX_TIMER_DELAY};
+    use futures::future::LocalBoxFuture;
+    use instant::Duration;
+    use std::{cell::RefCell, rc::Rc};
+
+    struct TestTimer(Rc<RefCell<Vec<Duration>>>);
+
+    impl TimerBackend for TestTimer {
+        fn sleep(&self, duration: Duration) -> LocalBoxFuture<'static, ()> {
+            self.0.borrow_mut().push(duration);
+            Box::pin(futures::future::ready(()))
+        }
+    }
+
+    #[test]
+    fn chain_long_sleeps_test() {
+        let sleeps = Rc::new(RefCell::new(Vec::new()));
+        let timer = TimerHandle(Rc::new(TestTimer(sleeps.clone())));
+        let month = Duration::from_secs(60 * 60 * 24 * 30);
+
+        futures::executor::block_on(timer.sleep(Duration::from_secs(1)));
+        futures::executor::block_on(timer.sleep(month));
+
+        assert_eq!(
+            *sleeps.borrow(),
+            vec![
+                Duration::from_secs(1),
+                MAX_TIMER_DELAY,
+                month - MAX_TIMER_DELAY
+            ]
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{error::QueryError, QueryClient, QueryFilter, QueryKey};
+
+/// The changes to the cache made inside `QueryClient::batch`.
+///
+/// The changes are applied in order when called, but the observers and the cache listeners
+/// are notified once when the batch ends, with the last state of each query.
+#[derive(Debug)]
+pub struct QueryTransaction<'a> {
+    client: &'a mut QueryClient,
+}
+
+impl<'a> QueryTransaction<'a> {
+    pub(crate) fn new(client: &'a mut QueryClient) -> Self {
+        QueryTransaction { client }
+    }
+
+    /// Returns the client of this transaction.
+    pub fn client(&self) -> &QueryClient {
+        self.client
+    }
+
+    /// Sets the cached value of the query with the given key, see `QueryClient::set_query_data`.
+    pub fn set_query_data<T: 'static>(
+        &mut self,
+        key: QueryKey,
+        value: T,
+    ) -> Result<(), QueryError> {
+        self.client.set_query_data(key, value)
+    }
+
+    /// Updates the cached value in place, see `QueryClient::set_query_data_with`.
+    pub fn set_query_data_with<T, F>(&mut self, key: QueryKey, f: F) -> Result<(), QueryError>
+    where
+        T: Clone + 'static,
+        F: FnOnce(&mut T),
+    {
+        self.client.set_query_data_with(key, f)
+    }
+
+    /// Merges the update into the cached value using the reducer of the query,
+    /// see `QueryClient::apply_update`.
+    pub fn apply_update<U: 'static>(
+        &mut self,
+        key: &QueryKey,
+        update: U,
+    ) -> Result<(), QueryError> {
+        self.client.apply_update(key, update)
+    }
+
+    /// Marks as stale the queries matching the given filter,
+    /// returns the number of queries invalidated.
+    pub fn invalidate(&mut self, filter: impl Into<QueryFilter>) -> usize {
+        self.client.invalidate_queries(filter)
+    }
+
+    /// Removes the query with the given key from the cache.
+    pub fn remove_query_data(&mut self, key: &QueryKey) -> bool {
+        self.client.remove_query_data(key)
+    }
+
+    /// Returns the query with the given key to its initial state, see `QueryClient::reset_query`.
+    pub fn reset_query(&mut self, key: &QueryKey) -> bool {
+        self.client.reset_query(key)
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{
+    cache::QueryCacheEvent, client::QueryClient, key::QueryKey, observer::QueryChangeEvent,
+    query::Subscription, QueryChanged,
+};
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+    Stream, StreamExt,
+};
+use std::{
+    cell::{Cell, RefCell},
+    marker::PhantomData,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+/// A stream of the changes of a query, returned by `QueryClient::watch_query`.
+///
+/// If the query does not exist yet, the stream waits until the query is created,
+/// and if the query is removed the stream continues with the next query of the same key.
+pub struct QueryWatcher<T> {
+    client: QueryClient,
+    key: QueryKey,
+    subscription: Option<Subscription>,
+    sender: UnboundedSender<QueryChanged>,
+    receiver: UnboundedReceiver<QueryChanged>,
+    listener_id: usize,
+    replaced: Rc<Cell<bool>>,
+    waker: Rc<RefCell<Option<Waker>>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: 'static> QueryWatcher<T> {
+    pub(crate) fn new(client: QueryClient, key: QueryKey) -> Self {
+        let (sender, receiver) = unbounded();
+        let replaced = Rc::new(Cell::new(false));
+        let waker = Rc::new(RefCell::new(None::<Waker>));
+
+        // The query may be removed and created again, then we subscribe to the new query
+        let listener_id = client.add_cache_listener({
+            let key = key.clone();
+            let replaced = replaced.clone();
+            let waker = waker.clone();
+            move |event: &QueryCacheEvent| {
+                let is_replaced = match event {
+                    QueryCacheEvent::Added(x) | QueryCacheEvent::Removed(x) => *x == key,
+                    QueryCacheEvent::Cleared => true,
+                    QueryCacheEvent::Updated(..) => false,
+                };
+
+                if is_replaced {
+                    replaced.set(true);
+                    if let Some(waker) = waker.borrow().as_ref() {
+                        waker.wake_by_ref();
+                    }
+                }
+            }
+        });
+
+        QueryWatcher {
+            client,
+            key,
+            subscription: None,
+            sender,
+            receiver,
+            listener_id,
+            replaced,
+            waker,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the key of the query being watched.
+    pub fn key(&self) -> &QueryKey {
+        &self.key
+    }
+
+    // Drops the subscription if the query in the cache is not the subscribed query.
+    fn detach_if_replaced(&mut self) {
+        if !self.replaced.replace(false) {
+            return;
+        }
+
+        let query = self.client.get_scoped_query(&self.key).map(|q| q.clone());
+        let is_subscribed = match (&self.subscription, query) {
+            (Some(subscription), Some(query)) => subscription.is_subscribed_to(&query),
+            _ => false,
+        };
+
+        if !is_subscribed {
+            self.subscription = None;
+        }
+    }
+
+    // Starts listening to the query, returns `false` if the query does not exist.
+    fn attach(&mut self) -> bool {
+        let Some(query) = self.client.get_scoped_query(&self.key).map(|q| q.clone()) else {
+            return false;
+        };
+
+        // The current state of the query is the first event
+        self.sender
+            .unbounded_send(QueryChanged {
+                value: query.last_value(),
+                state: query.state(),
+                is_fetching: query.is_fetching(),
+                is_queued: query.is_queued(),
+                data_updated_at: query.data_updated_at(),
+                error_updated_at: query.error_updated_at(),
+                ..Default::default()
+            })
+            .ok();
+
+        let sender = self.sender.clone();
+        let subscription = query.subscribe(move |event| {
+            sender.unbounded_send(event).ok();
+        });
+
+        self.subscription = Some(subscription);
+        true
+    }
+}
+
+impl<T: 'static> Stream for QueryWatcher<T> {
+    type Item = QueryChangeEvent<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        *self.waker.borrow_mut() = Some(cx.waker().clone());
+        self.detach_if_replaced();
+
+        if self.subscription.is_none() && !self.attach() {
+            self.client.register_watcher(&self.key, cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        loop {
+            let event = match self.receiver.poll_next_unpin(cx) {
+                Poll::Ready(Some(event)) => event,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            let value = match event.value {
+                Some(value) => match value.downcast::<T>() {
+                    Ok(value) => Some(value),
+                    Err(_) => continue, // We ignore values of other types
+                },
+                None => None,
+            };
+
+            return Poll::Ready(Some(QueryChangeEvent {
+                state: event.state,
+                is_fetching: event.is_fetching,
+                is_queued: event.is_queued,
+                value,
+                retry: event.retry,
+                progress: event.progress,
+                data_updated_at: event.data_updated_at,
+                error_updated_at: event.error_updated_at,
+                timestamp: event.timestamp,
+                cause: event.cause,
+            }));
+        }
+    }
+}
+
+impl<T> Drop for QueryWatcher<T> {
+    fn drop(&mut self) {
+        self.client.remove_cache_listener(self.listener_id);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::listener::EventListener;
+use std::{cell::RefCell, rc::Rc};
+use yew_query_core::{FocusManager, OnlineManager};
+
+thread_local! {
+    static ONLINE_LISTENERS: RefCell<WindowListeners> = const { RefCell::new(WindowListeners::new()) };
+    static FOCUS_LISTENERS: RefCell<WindowListeners> = const { RefCell::new(WindowListeners::new()) };
+}
+
+// The window event listeners registered by a browser manager.
+struct WindowListeners {
+    next_id: usize,
+    listeners: Vec<(usize, Vec<EventListener>)>,
+}
+
+impl WindowListeners {
+    const fn new() -> Self {
+        WindowListeners {
+            next_id: 0,
+            listeners: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, listeners: Vec<EventListener>) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.listeners.push((id, listeners));
+        id
+    }
+
+    fn remove(&mut self, id: usize) -> bool {
+        let Some(index) = self.listeners.iter().position(|(x, _)| *x == id) else {
+            return false;
+        };
+
+        for listener in self.listeners.remove(index).1 {
+            listener.unsubscribe();
+        }
+
+        true
+    }
+}
+
+/// The default `OnlineManager` in the browser, uses the `navigator` status
+/// and the `online` and `offline` events of the window.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BrowserOnlineManager;
+
+impl OnlineManager for BrowserOnlineManager {
+    fn is_online(&self) -> bool {
+        web_sys::window()
+            .map(|window| window.navigator().on_line())
+            .unwrap_or(true)
+    }
+
+    fn add_listener(&self, listener: Rc<dyn Fn(bool)>) -> usize {
+        let online = {
+            let listener = listener.clone();
+            EventListener::window("online", move |_| listener(true))
+        };
+        let offline = EventListener::window("offline", move |_| listener(false));
+
+        ONLINE_LISTENERS.with(|listeners| listeners.borrow_mut().add(vec![online, offline]))
+    }
+
+    fn remove_listener(&self, id: usize) -> bool {
+        ONLINE_LISTENERS.with(|listeners| listeners.borrow_mut().remove(id))
+    }
+}
+
+/// The default `FocusManager` in the browser, uses the `focus` and `blur` events of the window.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BrowserFocusManager;
+
+impl FocusManager for BrowserFocusManager {
+    fn is_focused(&self) -> bool {
+        web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.has_focus().ok())
+            .unwrap_or(true)
+    }
+
+    fn add_listener(&self, listener: Rc<dyn Fn(bool)>) -> usize {
+        let focus = {
+            let listener = listener.clone();
+            EventListener::window("focus", move |_| listener(true))
+        };
+        let blur = EventListener::window("blur", move |_| listener(false));
+
+        FOCUS_LISTENERS.with(|listeners| listeners.borrow_mut().add(vec![focus, blur]))
+    }
+
+    fn remove_listener(&self, id: usize) -> bool {
+        FOCUS_LISTENERS.with(|listeners| listeners.borrow_mut().remove(id))
+    }
+}
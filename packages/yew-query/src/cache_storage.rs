@@ -0,0 +1,85 @@
+use crate::http::{get_json, HttpError};
+use futures::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Cache, Response};
+
+/// Returns the json value of the response stored for the url in the browser Cache Storage,
+/// usually populated by a service worker.
+///
+/// Returns `None` if the response is not in the cache or cannot be read.
+pub async fn match_cached_json<T>(cache_name: &str, url: &str) -> Option<T>
+where
+    T: DeserializeOwned,
+{
+    let text = match match_cached_text(cache_name, url).await {
+        Ok(text) => text?,
+        Err(err) => {
+            log::warn!("failed to read `{url}` from the cache `{cache_name}`: {err:?}");
+            return None;
+        }
+    };
+
+    match serde_json::from_str(&text) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            log::warn!("failed to decode `{url}` from the cache `{cache_name}`: {err}");
+            None
+        }
+    }
+}
+
+async fn match_cached_text(cache_name: &str, url: &str) -> Result<Option<String>, JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("window is not available"))?;
+    let caches = window.caches()?;
+    let cache = JsFuture::from(caches.open(cache_name))
+        .await?
+        .dyn_into::<Cache>()?;
+
+    let response = JsFuture::from(cache.match_with_str(url)).await?;
+    if response.is_undefined() {
+        return Ok(None);
+    }
+
+    let response = response.dyn_into::<Response>()?;
+    if !response.ok() {
+        return Ok(None);
+    }
+
+    let text = JsFuture::from(response.text()?).await?;
+    Ok(text.as_string())
+}
+
+/// Returns the json value stored for the url in the browser Cache Storage,
+/// or sends a `GET` request to the url if is not cached.
+pub async fn get_cached_json<T>(cache_name: &str, url: &str) -> Result<T, HttpError>
+where
+    T: DeserializeOwned,
+{
+    if let Some(value) = match_cached_json::<T>(cache_name, url).await {
+        return Ok(value);
+    }
+
+    get_json::<T>(url).await
+}
+
+/// Returns a fetcher that reads the json response of the url from the browser Cache Storage
+/// before sending a `GET` request, for offline-first queries.
+///
+/// The cache is usually populated by a service worker.
+pub fn cached_json_fetcher<T>(
+    cache_name: impl Into<String>,
+    url: impl Into<String>,
+) -> impl Fn() -> LocalBoxFuture<'static, Result<T, HttpError>> + Clone + 'static
+where
+    T: DeserializeOwned + 'static,
+{
+    let cache_name: String = cache_name.into();
+    let url: String = url.into();
+    move || {
+        let cache_name = cache_name.clone();
+        let url = url.clone();
+        Box::pin(async move { get_cached_json::<T>(&cache_name, &url).await })
+    }
+}
@@ -0,0 +1,9 @@
+mod query_bridge;
+mod query_devtools;
+mod query_error_boundary;
+mod query_loading_bar;
+
+pub use query_bridge::*;
+pub use query_devtools::*;
+pub use query_error_boundary::*;
+pub use query_loading_bar::*;
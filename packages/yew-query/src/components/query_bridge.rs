@@ -0,0 +1,81 @@
+use crate::{use_query_with_options, UseQueryHandle, UseQueryOptions};
+use futures::{future::LocalBoxFuture, Future, FutureExt};
+use std::rc::Rc;
+use yew::{function_component, AttrValue, Callback, Html, Properties};
+use yew_query_core::Error;
+
+type BoxFetchFn<T> = dyn Fn() -> LocalBoxFuture<'static, Result<T, Error>>;
+
+/// The function used by a `QueryBridge` to fetch the data.
+pub struct QueryFetcher<T>(Rc<BoxFetchFn<T>>);
+
+impl<T: 'static> QueryFetcher<T> {
+    /// Constructs a new `QueryFetcher` from a function returning a future.
+    pub fn new<F, Fut, E>(fetch: F) -> Self
+    where
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = Result<T, E>> + 'static,
+        E: Into<Error> + 'static,
+    {
+        QueryFetcher(Rc::new(move || {
+            fetch().map(|x| x.map_err(Into::into)).boxed_local()
+        }))
+    }
+}
+
+impl<T> Clone for QueryFetcher<T> {
+    fn clone(&self) -> Self {
+        QueryFetcher(self.0.clone())
+    }
+}
+
+impl<T> PartialEq for QueryFetcher<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Properties for a `QueryBridge`.
+#[derive(Properties)]
+pub struct QueryBridgeProps<T: 'static> {
+    /// The key of the query.
+    pub query_key: AttrValue,
+
+    /// The function used to fetch the data.
+    pub fetcher: QueryFetcher<T>,
+
+    /// Renders the handle of the query.
+    pub render: Callback<UseQueryHandle<T>, Html>,
+}
+
+impl<T> PartialEq for QueryBridgeProps<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.query_key == other.query_key
+            && self.fetcher == other.fetcher
+            && self.render == other.render
+    }
+}
+
+/// Renders the handle of a query using the `render` callback,
+/// allows to use the queries from struct components.
+///
+/// ```rust,ignore
+/// html! {
+///     <QueryBridge<User>
+///         query_key="user"
+///         fetcher={QueryFetcher::new(get_user)}
+///         render={Callback::from(|query: UseQueryHandle<User>| html! { ... })}
+///     />
+/// }
+/// ```
+#[function_component]
+pub fn QueryBridge<T>(props: &QueryBridgeProps<T>) -> Html
+where
+    T: 'static,
+{
+    let fetcher = props.fetcher.clone();
+    let options = UseQueryOptions::new(&*props.query_key, move || (fetcher.0)());
+    let query = use_query_with_options(options);
+
+    props.render.emit(query)
+}
@@ -0,0 +1,117 @@
+use yew::{function_component, html, Children, Html, Properties};
+
+/// Properties for a `QueryDevtoolsProvider`.
+#[derive(Properties, PartialEq)]
+pub struct QueryDevtoolsProviderProps {
+    /// Whether if the panel starts open.
+    #[prop_or_default]
+    pub initial_open: bool,
+
+    #[prop_or_default]
+    pub children: Children,
+}
+
+/// Renders its children and a panel to inspect the queries of the current `QueryClient`.
+///
+/// The panel is only compiled with the `devtools` feature, without it only the children
+/// are rendered, so the panel can be enabled only for some builds.
+#[function_component]
+pub fn QueryDevtoolsProvider(props: &QueryDevtoolsProviderProps) -> Html {
+    html! {
+        <>
+            { for props.children.iter() }
+            { devtools_panel(props.initial_open) }
+        </>
+    }
+}
+
+#[cfg(not(feature = "devtools"))]
+fn devtools_panel(_initial_open: bool) -> Html {
+    Html::default()
+}
+
+#[cfg(feature = "devtools")]
+fn devtools_panel(initial_open: bool) -> Html {
+    html! { <panel::QueryDevtoolsPanel {initial_open} /> }
+}
+
+#[cfg(feature = "devtools")]
+mod panel {
+    use crate::hooks::common::use_cache_selector;
+    use yew::{function_component, html, use_state, Callback, Html, Properties};
+    use yew_query_core::{QueryClient, QueryFilter, QueryKey, QueryState};
+
+    #[derive(Properties, PartialEq)]
+    pub struct QueryDevtoolsPanelProps {
+        pub initial_open: bool,
+    }
+
+    // A row of the panel.
+    #[derive(Clone, PartialEq)]
+    struct QueryRow {
+        key: QueryKey,
+        state: &'static str,
+        is_fetching: bool,
+    }
+
+    fn query_rows(client: &QueryClient) -> Vec<QueryRow> {
+        let snapshot = client.snapshot();
+        snapshot
+            .iter()
+            .map(|query| QueryRow {
+                key: query.key().clone(),
+                state: match query.state() {
+                    QueryState::Idle => "idle",
+                    QueryState::Loading => "loading",
+                    QueryState::Ready => "ready",
+                    QueryState::Failed(_) => "failed",
+                },
+                // The keys of the snapshot include the prefix of the client
+                is_fetching: client
+                    .cache()
+                    .borrow()
+                    .get(query.key())
+                    .is_some_and(|q| q.is_fetching()),
+            })
+            .collect()
+    }
+
+    #[function_component]
+    pub fn QueryDevtoolsPanel(props: &QueryDevtoolsPanelProps) -> Html {
+        let is_open = {
+            let initial_open = props.initial_open;
+            use_state(move || initial_open)
+        };
+
+        let rows = use_cache_selector(QueryFilter::All, |client, _| query_rows(client));
+
+        let toggle = {
+            let is_open = is_open.clone();
+            Callback::from(move |_| is_open.set(!*is_open))
+        };
+
+        let panel_style =
+            "position: fixed; bottom: 0; right: 0; max-height: 40vh; overflow: auto; \
+            background: #1e1e1e; color: #eee; font: 12px monospace; z-index: 9999;";
+
+        html! {
+            <div style={panel_style}>
+                <button onclick={toggle}>
+                    { format!("Queries ({})", rows.len()) }
+                </button>
+                if *is_open {
+                    <table>
+                        <tr><th>{"Key"}</th><th>{"State"}</th><th>{"Fetching"}</th></tr>
+                        { for rows.iter().map(|row| html! {
+                            <tr key={row.key.key().to_string()}>
+                                <td>{ row.key.key().to_string() }</td>
+                                <td>{ row.state }</td>
+                                <td>{ if row.is_fetching { "yes" } else { "no" } }</td>
+                            </tr>
+                        }) }
+                    </table>
+                }
+            </div>
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::use_query_client;
+use std::{cell::RefCell, rc::Rc};
+use yew::{
+    function_component, html, platform::spawn_local, use_force_update, use_mut_ref, Callback,
+    Children, ContextProvider, Html, Properties,
+};
+use yew_query_core::{Error, QueryFilter, QueryKey};
+
+// Receives the errors of the queries beneath a `QueryErrorBoundary`.
+#[derive(Clone, PartialEq)]
+pub(crate) struct QueryErrorBoundaryContext {
+    pub(crate) report: Callback<(QueryKey, Error)>,
+}
+
+/// The errors caught by a `QueryErrorBoundary`, passed to its fallback.
+#[derive(Clone)]
+pub struct QueryErrorInfo {
+    errors: Rc<[(QueryKey, Error)]>,
+    retry: Callback<()>,
+}
+
+impl QueryErrorInfo {
+    /// Returns the first error caught.
+    pub fn error(&self) -> &Error {
+        &self.errors[0].1
+    }
+
+    /// Returns the key and error of each failed query.
+    pub fn errors(&self) -> &[(QueryKey, Error)] {
+        &self.errors
+    }
+
+    /// Resets and refetches the failed queries, then renders the children again.
+    pub fn retry(&self) {
+        self.retry.emit(());
+    }
+
+    /// Returns a callback that retries the failed queries, see `retry`.
+    pub fn retry_callback<IN>(&self) -> Callback<IN> {
+        let retry = self.retry.clone();
+        Callback::from(move |_| retry.emit(()))
+    }
+}
+
+/// Properties for a `QueryErrorBoundary`.
+#[derive(Properties, PartialEq)]
+pub struct QueryErrorBoundaryProps {
+    /// Renders the errors caught instead of the children.
+    pub fallback: Callback<QueryErrorInfo, Html>,
+
+    #[prop_or_default]
+    pub children: Children,
+}
+
+/// Catches the errors of the queries beneath it that propagate their errors,
+/// see `UseQueryOptions::propagate_errors`, and renders the fallback instead of the children.
+#[function_component]
+pub fn QueryErrorBoundary(props: &QueryErrorBoundaryProps) -> Html {
+    let client = use_query_client()
+        .expect("expected QueryClient")
+        .client()
+        .clone();
+    let errors: Rc<RefCell<Vec<(QueryKey, Error)>>> = use_mut_ref(Vec::new);
+    let force_update = use_force_update();
+
+    let context = {
+        let errors = errors.clone();
+        let force_update = force_update.clone();
+
+        QueryErrorBoundaryContext {
+            report: Callback::from(move |(key, error): (QueryKey, Error)| {
+                let mut errors = errors.borrow_mut();
+                if errors.iter().any(|(x, _)| *x == key) {
+                    return;
+                }
+
+                errors.push((key, error));
+                force_update.force_update();
+            }),
+        }
+    };
+
+    if errors.borrow().is_empty() {
+        return html! {
+            <ContextProvider<QueryErrorBoundaryContext> {context}>
+                { for props.children.iter() }
+            </ContextProvider<QueryErrorBoundaryContext>>
+        };
+    }
+
+    let retry = {
+        let errors = errors.clone();
+        Callback::from(move |()| {
+            let keys = errors
+                .borrow_mut()
+                .drain(..)
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>();
+
+            for key in keys.iter() {
+                client.reset_query(key);
+            }
+
+            // The children are rendered again while the queries are refetched
+            let client = client.clone();
+            spawn_local(async move {
+                let filter = QueryFilter::predicate(move |key| keys.contains(key));
+                client.refetch_queries(filter).await;
+            });
+
+            force_update.force_update();
+        })
+    };
+
+    let info = QueryErrorInfo {
+        errors: errors.borrow().iter().cloned().collect(),
+        retry,
+    };
+
+    props.fallback.emit(info)
+}
@@ -0,0 +1,55 @@
+use crate::use_is_fetching;
+use yew::{function_component, html, AttrValue, Classes, Html, Properties};
+use yew_query_core::QueryFilter;
+
+/// Properties for a `QueryLoadingBar`.
+#[derive(Properties, Clone)]
+pub struct QueryLoadingBarProps {
+    /// The queries tracked by the bar, by default all the queries.
+    #[prop_or_else(QueryFilter::all)]
+    pub filter: QueryFilter,
+
+    /// The color of the bar, any css color.
+    #[prop_or(AttrValue::Static("#29d"))]
+    pub color: AttrValue,
+
+    /// The height of the bar in pixels.
+    #[prop_or(3)]
+    pub height: u32,
+
+    /// Additional classes for the bar element.
+    #[prop_or_default]
+    pub class: Classes,
+}
+
+impl PartialEq for QueryLoadingBarProps {
+    fn eq(&self, other: &Self) -> bool {
+        // Filters can't be compared, so the first one is kept
+        self.color == other.color && self.height == other.height && self.class == other.class
+    }
+}
+
+/// Renders a progress bar at the top of the page while any query is fetching.
+#[function_component]
+pub fn QueryLoadingBar(props: &QueryLoadingBarProps) -> Html {
+    let fetching = use_is_fetching(props.filter.clone());
+
+    if fetching == 0 {
+        return Html::default();
+    }
+
+    let style = format!(
+        "position: fixed; top: 0; left: 0; width: 100%; height: {}px; background: {}; z-index: 9999;",
+        props.height, props.color
+    );
+
+    html! {
+        <div
+            class={props.class.clone()}
+            style={style}
+            role="progressbar"
+            aria-busy="true"
+            aria-label="Loading"
+        />
+    }
+}
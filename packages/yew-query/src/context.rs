@@ -1,5 +1,6 @@
-use yew::{function_component, Children, ContextProvider, Properties};
-use yew_query_core::QueryClient;
+use std::rc::Rc;
+use yew::{function_component, use_context, use_memo, Children, ContextProvider, Properties};
+use yew_query_core::{QueryClient, QueryOptions};
 
 /// A context with the `QueryClient`.
 pub struct QueryClientContext {
@@ -36,6 +37,10 @@ impl PartialEq for QueryClientContextProps {
 }
 
 /// Declares a `QueryClient` for the app.
+///
+/// The providers can be nested, the hooks use the client of the nearest provider,
+/// so a part of the app can use its own client and cache.
+/// To use other client in a single query see `UseQueryOptions::client`.
 #[function_component]
 pub fn QueryClientProvider(props: &QueryClientContextProps) -> yew::Html {
     let context = QueryClientContext {
@@ -52,3 +57,87 @@ pub fn QueryClientProvider(props: &QueryClientContextProps) -> yew::Html {
 fn eq_query_client(a: &QueryClient, b: &QueryClient) -> bool {
     std::ptr::eq(a, b)
 }
+
+/// A context with the default options of the queries of a subtree.
+#[derive(Clone, Default)]
+pub struct QueryOptionsContext {
+    pub(crate) options: Rc<QueryOptions>,
+    pub(crate) refetch_on_mount: Option<bool>,
+    pub(crate) refetch_on_reconnect: Option<bool>,
+    pub(crate) refetch_on_window_focus: Option<bool>,
+}
+
+impl PartialEq for QueryOptionsContext {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.options, &other.options)
+            && self.refetch_on_mount == other.refetch_on_mount
+            && self.refetch_on_reconnect == other.refetch_on_reconnect
+            && self.refetch_on_window_focus == other.refetch_on_window_focus
+    }
+}
+
+/// Properties for a `QueryOptionsProvider`.
+#[derive(Properties)]
+pub struct QueryOptionsProviderProps {
+    /// The default options of the queries.
+    pub options: QueryOptions,
+
+    /// Whether if the queries refetch on mount by default.
+    #[prop_or_default]
+    pub refetch_on_mount: Option<bool>,
+
+    /// Whether if the queries refetch on reconnection by default.
+    #[prop_or_default]
+    pub refetch_on_reconnect: Option<bool>,
+
+    /// Whether if the queries refetch when the window is focused by default.
+    #[prop_or_default]
+    pub refetch_on_window_focus: Option<bool>,
+
+    #[prop_or_default]
+    pub children: Children,
+}
+
+impl PartialEq for QueryOptionsProviderProps {
+    fn eq(&self, other: &Self) -> bool {
+        self.options == other.options
+            && self.refetch_on_mount == other.refetch_on_mount
+            && self.refetch_on_reconnect == other.refetch_on_reconnect
+            && self.refetch_on_window_focus == other.refetch_on_window_focus
+            && self.children == other.children
+    }
+}
+
+/// Declares the default options for the queries of its children,
+/// the options of each query still take precedence.
+///
+/// The options of a parent `QueryOptionsProvider` are used for the options not set.
+#[function_component]
+pub fn QueryOptionsProvider(props: &QueryOptionsProviderProps) -> yew::Html {
+    let parent = use_context::<QueryOptionsContext>().unwrap_or_default();
+
+    // The context only changes with the options, so the consumers don't re-render on each render
+    let context = use_memo(
+        |(options, refetch_on_mount, refetch_on_reconnect, refetch_on_window_focus, parent)| {
+            QueryOptionsContext {
+                options: Rc::new(options.merge(&parent.options)),
+                refetch_on_mount: refetch_on_mount.or(parent.refetch_on_mount),
+                refetch_on_reconnect: refetch_on_reconnect.or(parent.refetch_on_reconnect),
+                refetch_on_window_focus: refetch_on_window_focus.or(parent.refetch_on_window_focus),
+            }
+        },
+        (
+            props.options.clone(),
+            props.refetch_on_mount,
+            props.refetch_on_reconnect,
+            props.refetch_on_window_focus,
+            parent,
+        ),
+    );
+
+    yew::html! {
+        <ContextProvider<QueryOptionsContext> context={(*context).clone()}>
+            { for props.children.iter() }
+        </ContextProvider<QueryOptionsContext>>
+    }
+}
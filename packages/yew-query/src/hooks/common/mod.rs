@@ -9,3 +9,9 @@ pub use use_on_window_focus::*;
 
 mod use_abort_controller;
 pub use use_abort_controller::*;
+
+mod use_delayed_value;
+pub use use_delayed_value::*;
+
+mod use_cache_selector;
+pub use use_cache_selector::*;
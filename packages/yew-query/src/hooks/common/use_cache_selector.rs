@@ -0,0 +1,68 @@
+use crate::use_query_client;
+use std::{cell::Cell, rc::Rc};
+use yew::{hook, platform::spawn_local, use_effect_with_deps, use_mut_ref, use_state_eq};
+use yew_query_core::{QueryCacheEvent, QueryClient, QueryFilter};
+
+/// Returns the value selected from the client for the queries matching the filter,
+/// selected again when any query of the cache changes.
+///
+/// The latest filter and selector are used on each change.
+#[hook]
+pub fn use_cache_selector<T, F>(filter: impl Into<QueryFilter>, select: F) -> T
+where
+    T: Clone + PartialEq + 'static,
+    F: Fn(&QueryClient, &QueryFilter) -> T + 'static,
+{
+    let client = use_query_client()
+        .expect("expected QueryClient")
+        .client()
+        .clone();
+
+    let latest = (
+        filter.into(),
+        Rc::new(select) as Rc<dyn Fn(&QueryClient, &QueryFilter) -> T>,
+    );
+    let selector = use_mut_ref(|| latest.clone());
+    *selector.borrow_mut() = latest;
+
+    let value = {
+        let (filter, select) = selector.borrow().clone();
+        let selected = select(&client, &filter);
+        use_state_eq(move || selected)
+    };
+
+    {
+        let value = value.clone();
+        use_effect_with_deps(
+            move |_| {
+                let pending = Rc::new(Cell::new(false));
+                let id = client.add_cache_listener({
+                    let client = client.clone();
+                    move |_: &QueryCacheEvent| {
+                        if pending.replace(true) {
+                            return;
+                        }
+
+                        // The cache may be borrowed while notifying, we update after
+                        let client = client.clone();
+                        let pending = pending.clone();
+                        let value = value.clone();
+                        let selector = selector.clone();
+                        spawn_local(async move {
+                            pending.set(false);
+                            let (filter, select) = selector.borrow().clone();
+                            value.set(select(&client, &filter));
+                        });
+                    }
+                });
+
+                move || {
+                    client.remove_cache_listener(id);
+                }
+            },
+            (),
+        );
+    }
+
+    (*value).clone()
+}
@@ -0,0 +1,81 @@
+use instant::{Duration, Instant};
+use yew::{
+    hook, platform::spawn_local, platform::time::sleep, use_effect_with_deps, use_mut_ref,
+    use_state,
+};
+
+/// How the changes of a value are delayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delay {
+    /// The changes are applied immediately.
+    None,
+
+    /// The change is applied after the value didn't change for the given duration.
+    Debounce(Duration),
+
+    /// The changes are applied at most once for the given duration.
+    Throttle(Duration),
+}
+
+/// Returns the last value applied after delaying its changes.
+#[hook]
+pub fn use_delayed_value<T>(value: T, delay: Delay) -> T
+where
+    T: Clone + PartialEq + 'static,
+{
+    let current = use_state(|| value.clone());
+    let generation = use_mut_ref(|| 0_u32);
+    let last_applied = use_mut_ref(|| None::<Instant>);
+
+    {
+        let current = current.clone();
+        use_effect_with_deps(
+            move |value| {
+                // A new value cancels the pending change
+                let id = {
+                    let mut generation = generation.borrow_mut();
+                    *generation = generation.wrapping_add(1);
+                    *generation
+                };
+
+                if *value == *current {
+                    return;
+                }
+
+                let wait = match delay {
+                    Delay::None => None,
+                    Delay::Debounce(duration) => Some(duration),
+                    Delay::Throttle(duration) => {
+                        let elapsed = last_applied.borrow().map(|x| x.elapsed());
+                        match elapsed {
+                            Some(elapsed) if elapsed < duration => Some(duration - elapsed),
+                            _ => None,
+                        }
+                    }
+                };
+
+                let Some(wait) = wait else {
+                    *last_applied.borrow_mut() = Some(Instant::now());
+                    current.set(value.clone());
+                    return;
+                };
+
+                let value = value.clone();
+                spawn_local(async move {
+                    sleep(wait).await;
+
+                    if *generation.borrow() == id {
+                        *last_applied.borrow_mut() = Some(Instant::now());
+                        current.set(value);
+                    }
+                });
+            },
+            value.clone(),
+        );
+    }
+
+    match delay {
+        Delay::None => value,
+        _ => (*current).clone(),
+    }
+}
@@ -1,17 +1,28 @@
-use crate::listener::EventListener;
-use yew::{use_effect_with_deps, hook};
+use crate::browser::BrowserOnlineManager;
+use std::rc::Rc;
+use yew::{hook, use_effect_with_deps};
+use yew_query_core::OnlineManager;
 
+/// Calls the function when the connection is restored, using the given `OnlineManager`
+/// or the browser status if not set.
 #[hook]
-pub fn use_on_online<F>(callback: F)
+pub fn use_on_online<F>(online_manager: Option<Rc<dyn OnlineManager>>, callback: F)
 where
     F: Fn() + 'static,
 {
     use_effect_with_deps(
         move |_| {
-            let listener = EventListener::window("online", move |_| callback());
+            let online_manager: Rc<dyn OnlineManager> =
+                online_manager.unwrap_or_else(|| Rc::new(BrowserOnlineManager));
+
+            let id = online_manager.add_listener(Rc::new(move |online| {
+                if online {
+                    callback();
+                }
+            }));
 
             move || {
-                listener.unsubscribe();
+                online_manager.remove_listener(id);
             }
         },
         (),
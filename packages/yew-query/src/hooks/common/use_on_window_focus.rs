@@ -1,9 +1,13 @@
 use super::use_is_first_render::use_is_first_render;
-use crate::listener::EventListener;
-use yew::{use_effect_with_deps, hook};
+use crate::browser::BrowserFocusManager;
+use std::rc::Rc;
+use yew::{hook, use_effect_with_deps};
+use yew_query_core::FocusManager;
 
+/// Calls the function when the application regains the focus, using the given `FocusManager`
+/// or the focus of the window if not set.
 #[hook]
-pub fn use_on_window_focus<F>(callback: F)
+pub fn use_on_window_focus<F>(focus_manager: Option<Rc<dyn FocusManager>>, callback: F)
 where
     F: Fn() + 'static,
 {
@@ -12,16 +16,19 @@ where
     use_effect_with_deps(
         move |first_render| {
             let first_render = *first_render;
-            let listener = EventListener::window("focus", move |_| {
-                if first_render {
+            let focus_manager: Rc<dyn FocusManager> =
+                focus_manager.unwrap_or_else(|| Rc::new(BrowserFocusManager));
+
+            let id = focus_manager.add_listener(Rc::new(move |focused| {
+                if first_render || !focused {
                     return;
                 }
 
                 callback();
-            });
+            }));
 
             move || {
-                listener.unsubscribe();
+                focus_manager.remove_listener(id);
             }
         },
         first_render,
@@ -1,6 +1,24 @@
 pub(crate) mod common;
+mod use_is_fetching;
+mod use_lazy_query;
 mod use_query_client;
 mod use_query;
+mod use_query_data_now;
+mod use_query_errors;
+mod use_query_value;
+mod use_query_when;
+mod use_refetch_queries;
+mod use_stream_query;
+mod use_suspend_refetches;
 
+pub use use_is_fetching::*;
+pub use use_lazy_query::*;
 pub use use_query::*;
+pub use use_query_data_now::*;
+pub use use_query_errors::*;
+pub use use_query_value::*;
+pub use use_query_when::*;
+pub use use_refetch_queries::*;
+pub use use_stream_query::*;
+pub use use_suspend_refetches::*;
 pub use use_query_client::*;
@@ -0,0 +1,13 @@
+use super::common::use_cache_selector;
+use yew::hook;
+use yew_query_core::QueryFilter;
+
+/// This hook returns the number of queries matching the filter that are fetching.
+///
+/// Is updated when any query of the cache changes, useful for showing a global loading indicator.
+#[hook]
+pub fn use_is_fetching(filter: impl Into<QueryFilter>) -> usize {
+    use_cache_selector(filter, |client, filter| {
+        client.fetching_count(filter.clone())
+    })
+}
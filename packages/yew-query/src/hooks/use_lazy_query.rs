@@ -0,0 +1,100 @@
+use super::{use_query_with_options, UseQueryHandle, UseQueryOptions};
+use futures::Future;
+use std::ops::Deref;
+use yew::{hook, use_state, Callback};
+use yew_query_core::{Error, Key};
+
+/// Handle returned by `use_lazy_query`.
+pub struct UseLazyQueryHandle<T> {
+    query: UseQueryHandle<T>,
+    trigger: Callback<()>,
+    is_triggered: bool,
+}
+
+impl<T> UseLazyQueryHandle<T> {
+    /// Fetches the query, the first call enables the query and next calls refetch it.
+    pub fn trigger(&self) {
+        self.trigger.emit(());
+    }
+
+    /// Returns `true` if the query was triggered.
+    pub fn is_triggered(&self) -> bool {
+        self.is_triggered
+    }
+
+    /// Returns the handle of the query.
+    pub fn query(&self) -> &UseQueryHandle<T> {
+        &self.query
+    }
+}
+
+impl<T> Deref for UseLazyQueryHandle<T> {
+    type Target = UseQueryHandle<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.query
+    }
+}
+
+impl<T> Clone for UseLazyQueryHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            query: self.query.clone(),
+            trigger: self.trigger.clone(),
+            is_triggered: self.is_triggered,
+        }
+    }
+}
+
+/// This hook registers a query that is not fetched until `UseLazyQueryHandle::trigger` is called.
+///
+/// Once triggered the query is cached and shares the fetches in progress like any other query.
+#[hook]
+pub fn use_lazy_query<F, Fut, K, T, E>(key: K, fetcher: F) -> UseLazyQueryHandle<T>
+where
+    F: Fn() -> Fut + 'static,
+    Fut: Future<Output = Result<T, E>> + 'static,
+    K: Into<Key>,
+    T: 'static,
+    E: Into<Error> + 'static,
+{
+    use_lazy_query_with_options(UseQueryOptions::new(key.into(), fetcher))
+}
+
+/// This hook registers a query with the given `UseQueryOptions` that is not fetched
+/// until `UseLazyQueryHandle::trigger` is called.
+///
+/// The query is enabled by the trigger, the `enabled` option is ignored.
+#[hook]
+pub fn use_lazy_query_with_options<Fut, T, E, U>(
+    options: UseQueryOptions<Fut, T, E, U>,
+) -> UseLazyQueryHandle<U>
+where
+    Fut: Future<Output = Result<T, E>> + 'static,
+    T: 'static,
+    E: Into<Error> + 'static,
+    U: 'static,
+{
+    let triggered = use_state(|| false);
+    let query = use_query_with_options(options.enabled(*triggered));
+
+    let trigger = {
+        let triggered = triggered.clone();
+        let query = query.clone();
+
+        // Enabling the query fetches it, after that we refetch
+        Callback::from(move |()| {
+            if *triggered {
+                query.refetch();
+            } else {
+                triggered.set(true);
+            }
+        })
+    };
+
+    UseLazyQueryHandle {
+        query,
+        trigger,
+        is_triggered: *triggered,
+    }
+}
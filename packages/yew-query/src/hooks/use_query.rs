@@ -1,30 +1,52 @@
 use super::use_query_client;
 use crate::{
-    common::{use_abort_controller, use_is_first_render, use_on_online, use_on_window_focus},
+    browser::BrowserOnlineManager,
+    common::{
+        use_abort_controller, use_delayed_value, use_is_first_render, use_on_online,
+        use_on_window_focus, Delay,
+    },
     utils::{id::Id, OptionExt},
+    QueryErrorBoundaryContext, QueryOptionsContext,
 };
 use futures::Future;
-use instant::Duration;
-use std::rc::Rc;
+use instant::{Duration, Instant};
+use std::{fmt::Debug, rc::Rc};
 use web_sys::AbortSignal;
-use yew::{hook, use_callback, use_effect_with_deps, use_state, Callback, UseStateHandle, use_memo};
+use yew::{hook, use_callback, use_context, use_effect_with_deps, use_state, Callback, UseStateHandle, use_memo, use_mut_ref};
 use yew_query_core::{
-    Error, Key, QueryChangeEvent, QueryKey, QueryObserver, QueryOptions, QueryState, ObserveTarget,
+    error::{QueryError, ValidationError},
+    fetcher::{BoxFetcher, FetchContext, FetcherId, Progress},
+    retry::RetryProgress,
+    is_refetch_suspended, Error, Key, QueryChangeEvent, QueryClient, QueryKey, QueryObserver, QueryOptions, QueryState, ObserveTarget, FetchCause,
+    FetchPriority, FetchStatus, OnlineManager, TypedQueryKey,
 };
 
+type SelectFn<T, U> = dyn Fn(&Rc<T>) -> Rc<U>;
+type SelectEqFn<U> = dyn Fn(&U, &U) -> bool;
+
 /// Options for a `use_query`.
-pub struct UseQueryOptions<Fut, T, E>
+pub struct UseQueryOptions<Fut, T, E, U = T>
 where
     Fut: Future<Output = Result<T, E>>,
     T: 'static,
     E: Into<Error> + 'static,
+    U: 'static,
 {
     key: Key,
-    fetch: Rc<dyn Fn(AbortSignal) -> Fut>,
-    enabled: bool,
-    refetch_on_mount: bool,
-    refetch_on_reconnect: bool,
-    refetch_on_window_focus: bool,
+    fetch: Rc<dyn Fn(FetchContext, AbortSignal) -> Fut>,
+    fetcher_id: FetcherId,
+    select: Rc<SelectFn<T, U>>,
+    select_eq: Option<Rc<SelectEqFn<U>>>,
+    enabled: QueryEnabled,
+    refetch_on_mount: Option<bool>,
+    refetch_on_reconnect: Option<bool>,
+    refetch_on_window_focus: Option<bool>,
+    notify_on_change_props: Option<Rc<[QueryProp]>>,
+    key_delay: Delay,
+    placeholder_data: Option<Rc<T>>,
+    keep_previous_data: bool,
+    propagate_errors: bool,
+    client: Option<QueryClient>,
     options: Option<QueryOptions>,
 }
 
@@ -39,6 +61,25 @@ where
     where
         F: Fn(AbortSignal) -> Fut + 'static,
         K: Into<Key>,
+    {
+        Self::with_context_and_signal(key, move |_, signal| fetch(signal))
+            .with_fetcher_id(FetcherId::of::<F>())
+    }
+
+    /// Constructs a new `UseQueryOptions` with a fetcher that receives the `FetchContext`.
+    pub fn with_context<K, F>(key: K, fetch: F) -> Self
+    where
+        F: Fn(FetchContext) -> Fut + 'static,
+        K: Into<Key>,
+    {
+        Self::with_context_and_signal(key, move |ctx, _| fetch(ctx))
+            .with_fetcher_id(FetcherId::of::<F>())
+    }
+
+    pub(crate) fn with_context_and_signal<K, F>(key: K, fetch: F) -> Self
+    where
+        F: Fn(FetchContext, AbortSignal) -> Fut + 'static,
+        K: Into<Key>,
     {
         let fetch = Rc::new(fetch);
         let key = key.into();
@@ -46,10 +87,19 @@ where
         UseQueryOptions {
             key,
             fetch,
-            enabled: true,
-            refetch_on_mount: true,
-            refetch_on_reconnect: true,
-            refetch_on_window_focus: true,
+            fetcher_id: FetcherId::of::<F>(),
+            select: Rc::new(|value: &Rc<T>| value.clone()),
+            select_eq: None,
+            enabled: QueryEnabled::Value(true),
+            refetch_on_mount: None,
+            refetch_on_reconnect: None,
+            refetch_on_window_focus: None,
+            notify_on_change_props: None,
+            key_delay: Delay::None,
+            placeholder_data: None,
+            keep_previous_data: false,
+            propagate_errors: false,
+            client: None,
             options: None,
         }
     }
@@ -60,7 +110,60 @@ where
         K: Into<Key>,
         F: Fn() -> Fut + 'static,
     {
-        Self::new_abortable(key, move |_| fetch())
+        Self::new_abortable(key, move |_| fetch()).with_fetcher_id(FetcherId::of::<F>())
+    }
+
+    /// Constructs a new `UseQueryOptions` for a `TypedQueryKey`,
+    /// the fetcher must return the type of the key.
+    pub fn typed<F>(key: TypedQueryKey<T>, fetch: F) -> Self
+    where
+        F: Fn() -> Fut + 'static,
+    {
+        Self::new(key.key().clone(), fetch)
+    }
+
+    // Sets the identity of the fetcher wrapped by the constructors.
+    pub(crate) fn with_fetcher_id(mut self, fetcher_id: FetcherId) -> Self {
+        self.fetcher_id = fetcher_id;
+        self
+    }
+}
+
+impl<Fut, T, E, U> UseQueryOptions<Fut, T, E, U>
+where
+    Fut: Future<Output = Result<T, E>>,
+    T: 'static,
+    E: Into<Error> + 'static,
+    U: 'static,
+{
+    /// Maps the data of the query, the cache stores the data returned by the fetcher
+    /// but the handle returns the selected value.
+    ///
+    /// The selector only runs when the data changes, and if the selected value is
+    /// equal to the previous one the handle keeps the previous value.
+    pub fn select<V, S>(self, select: S) -> UseQueryOptions<Fut, T, E, V>
+    where
+        S: Fn(&T) -> V + 'static,
+        V: PartialEq + 'static,
+    {
+        UseQueryOptions {
+            key: self.key,
+            fetch: self.fetch,
+            fetcher_id: self.fetcher_id,
+            select: Rc::new(move |value: &Rc<T>| Rc::new(select(value))),
+            select_eq: Some(Rc::new(|a: &V, b: &V| a == b)),
+            enabled: self.enabled,
+            refetch_on_mount: self.refetch_on_mount,
+            refetch_on_reconnect: self.refetch_on_reconnect,
+            refetch_on_window_focus: self.refetch_on_window_focus,
+            notify_on_change_props: self.notify_on_change_props,
+            key_delay: self.key_delay,
+            placeholder_data: self.placeholder_data,
+            keep_previous_data: self.keep_previous_data,
+            propagate_errors: self.propagate_errors,
+            client: self.client,
+            options: self.options,
+        }
     }
 
     /// Sets the cache duration for this specific query.
@@ -90,40 +193,382 @@ where
         self
     }
 
+    /// Adds an entry to the metadata of this specific query.
+    pub fn meta(mut self, key: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.options.get_or_insert_with(Default::default);
+        self.options.update(move |opts| opts.meta(key, value));
+        self
+    }
+
+    /// Sets the max time a fetch can take for this specific query.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.get_or_insert_with(Default::default);
+        self.options.update(move |opts| opts.timeout(timeout));
+        self
+    }
+
+    /// Sets the priority of the fetches of this specific query, see `QueryOptions::priority`.
+    pub fn priority(mut self, priority: impl Into<FetchPriority>) -> Self {
+        let priority = priority.into();
+        self.options.get_or_insert_with(Default::default);
+        self.options.update(move |opts| opts.priority(priority));
+        self
+    }
+
+    /// Sets the min time between the start of two fetches of this specific query,
+    /// see `QueryOptions::min_fetch_interval`.
+    pub fn min_fetch_interval(mut self, interval: Duration) -> Self {
+        self.options.get_or_insert_with(Default::default);
+        self.options.update(move |opts| opts.min_fetch_interval(interval));
+        self
+    }
+
+    /// Sets the identity of the request made by the fetcher, see `QueryOptions::request_key`.
+    pub fn request_key(mut self, request_key: impl Into<Key>) -> Self {
+        let request_key = request_key.into();
+        self.options.get_or_insert_with(Default::default);
+        self.options.update(move |opts| opts.request_key(request_key));
+        self
+    }
+
+    /// Sets the partition of the cache where this specific query is stored.
+    pub fn partition(mut self, partition: impl Into<Rc<str>>) -> Self {
+        self.options.get_or_insert_with(Default::default);
+        self.options.update(move |opts| opts.partition(partition));
+        self
+    }
+
+    /// Keeps the cached data when a refetch returns an equal value, see `QueryOptions::structural_sharing`.
+    pub fn structural_sharing(mut self) -> Self
+    where
+        T: PartialEq,
+    {
+        self.options.get_or_insert_with(Default::default);
+        self.options.update(move |opts| opts.structural_sharing::<T>());
+        self
+    }
+
+    /// Sets a validator that checks the data returned by the fetcher, see `QueryOptions::validate`.
+    pub fn validate<F>(mut self, validate: F) -> Self
+    where
+        F: Fn(&T) -> Result<(), ValidationError> + 'static,
+    {
+        self.options.get_or_insert_with(Default::default);
+        self.options.update(move |opts| opts.validate(validate));
+        self
+    }
+
+    /// Sets the data of this query when is created, see `QueryOptions::initial_data`.
+    pub fn initial_data(mut self, data: T) -> Self {
+        self.options.get_or_insert_with(Default::default);
+        self.options.update(move |opts| opts.initial_data(data));
+        self
+    }
+
+    /// Sets the time the initial data was updated, used to check if the initial data is stale.
+    pub fn initial_data_updated_at(mut self, updated_at: Instant) -> Self {
+        self.options.get_or_insert_with(Default::default);
+        self.options
+            .update(move |opts| opts.initial_data_updated_at(updated_at));
+        self
+    }
+
     /// Sets a value for enable for disable this query.
-    pub fn enabled(mut self, enabled: bool) -> Self {
-        self.enabled = enabled;
+    ///
+    /// Accepts a `bool` or a `UseStateHandle<bool>`, see `enabled_when` to use a function.
+    pub fn enabled(mut self, enabled: impl Into<QueryEnabled>) -> Self {
+        self.enabled = enabled.into();
+        self
+    }
+
+    /// Sets a function that enables or disables this query,
+    /// is evaluated on each render and before each fetch.
+    pub fn enabled_when<F>(mut self, enabled: F) -> Self
+    where
+        F: Fn() -> bool + 'static,
+    {
+        self.enabled = QueryEnabled::Fn(Rc::new(enabled));
         self
     }
 
     /// Sets a value indicating whether if refetch the data on mount.
     pub fn refetch_on_mount(mut self, refetch_on_mount: bool) -> Self {
-        self.refetch_on_mount = refetch_on_mount;
+        self.refetch_on_mount = Some(refetch_on_mount);
         self
     }
 
     /// Sets a value indicating whether if refetch on reconnection.
     pub fn refetch_on_reconnect(mut self, refetch_on_reconnect: bool) -> Self {
-        self.refetch_on_reconnect = refetch_on_reconnect;
+        self.refetch_on_reconnect = Some(refetch_on_reconnect);
         self
     }
 
     /// Sets a value indicating whether if refetch when window is focused.
     pub fn refetch_on_window_focus(mut self, refetch_on_window_focus: bool) -> Self {
-        self.refetch_on_window_focus = refetch_on_window_focus;
+        self.refetch_on_window_focus = Some(refetch_on_window_focus);
+        self
+    }
+
+    /// Waits until the key didn't change for the given duration before using it,
+    /// meanwhile the handle keeps the query of the previous key.
+    pub fn debounce(mut self, duration: Duration) -> Self {
+        self.key_delay = Delay::Debounce(duration);
+        self
+    }
+
+    /// Uses the changes of the key at most once for the given duration,
+    /// meanwhile the handle keeps the query of the previous key.
+    pub fn throttle(mut self, duration: Duration) -> Self {
+        self.key_delay = Delay::Throttle(duration);
+        self
+    }
+
+    /// Sets the data returned while the query has no data,
+    /// the placeholder is not stored in the cache.
+    pub fn placeholder_data(mut self, data: T) -> Self {
+        self.placeholder_data = Some(Rc::new(data));
+        self
+    }
+
+    /// Sets a value indicating whether if keep returning the data of the previous key
+    /// while the query of a new key has no data.
+    pub fn keep_previous_data(mut self, keep_previous_data: bool) -> Self {
+        self.keep_previous_data = keep_previous_data;
+        self
+    }
+
+    /// Sets a value indicating whether if the errors of this query are sent
+    /// to the nearest `QueryErrorBoundary`, which renders its fallback instead.
+    pub fn propagate_errors(mut self, propagate_errors: bool) -> Self {
+        self.propagate_errors = propagate_errors;
         self
     }
+
+    /// Sets the client used by this query instead of the client of the nearest `QueryClientProvider`.
+    pub fn client(mut self, client: QueryClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Sets the properties of the query that re-render the component when changed,
+    /// by default any change re-renders.
+    pub fn notify_on_change_props<I>(mut self, props: I) -> Self
+    where
+        I: IntoIterator<Item = QueryProp>,
+    {
+        self.notify_on_change_props = Some(props.into_iter().collect());
+        self
+    }
+}
+
+/// Whether if a query is enabled, see `UseQueryOptions::enabled`.
+#[derive(Clone)]
+pub enum QueryEnabled {
+    /// A constant value.
+    Value(bool),
+
+    /// The value of a state.
+    State(UseStateHandle<bool>),
+
+    /// A function evaluated each time is checked.
+    Fn(Rc<dyn Fn() -> bool>),
+}
+
+impl QueryEnabled {
+    /// Returns `true` if the query is enabled.
+    pub fn get(&self) -> bool {
+        match self {
+            QueryEnabled::Value(enabled) => *enabled,
+            QueryEnabled::State(enabled) => **enabled,
+            QueryEnabled::Fn(enabled) => enabled(),
+        }
+    }
+}
+
+impl From<bool> for QueryEnabled {
+    fn from(enabled: bool) -> Self {
+        QueryEnabled::Value(enabled)
+    }
+}
+
+impl From<UseStateHandle<bool>> for QueryEnabled {
+    fn from(enabled: UseStateHandle<bool>) -> Self {
+        QueryEnabled::State(enabled)
+    }
+}
+
+impl Debug for QueryEnabled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("QueryEnabled").field(&self.get()).finish()
+    }
+}
+
+/// A property of the result of a query, see `UseQueryOptions::notify_on_change_props`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryProp {
+    /// The data of the query and the last time it was updated.
+    Data,
+
+    /// The error of the query and the last time it failed.
+    Error,
+
+    /// The state of the query.
+    State,
+
+    /// Whether if the query is fetching.
+    IsFetching,
+
+    /// Whether if the fetch is waiting in the queue of the client.
+    IsQueued,
+
+    /// The fetch status of the query, see `FetchStatus`.
+    FetchStatus,
+
+    /// The progress of the retries.
+    Retry,
+
+    /// The progress reported by the fetcher.
+    Progress,
+}
+
+// The result of a query, kept in a single state so each change renders once.
+struct QueryResult<T> {
+    key: QueryKey,
+    state: QueryState,
+    value: Option<Rc<T>>,
+    is_fetching: bool,
+    is_queued: bool,
+    retry: Option<RetryProgress>,
+    progress: Option<Progress>,
+    data_updated_at: Option<Instant>,
+    error_updated_at: Option<Instant>,
+}
+
+impl<T> QueryResult<T> {
+    fn idle(key: QueryKey) -> Self {
+        QueryResult {
+            key,
+            state: QueryState::Idle,
+            value: None,
+            is_fetching: false,
+            is_queued: false,
+            retry: None,
+            progress: None,
+            data_updated_at: None,
+            error_updated_at: None,
+        }
+    }
+
+    // Constructs a result with the same state and the value mapped.
+    fn map<U>(&self, value: Option<Rc<U>>) -> QueryResult<U> {
+        QueryResult {
+            key: self.key.clone(),
+            state: self.state.clone(),
+            value,
+            is_fetching: self.is_fetching,
+            is_queued: self.is_queued,
+            retry: self.retry,
+            progress: self.progress,
+            data_updated_at: self.data_updated_at,
+            error_updated_at: self.error_updated_at,
+        }
+    }
+}
+
+impl<T> QueryResult<T> {
+    fn fetch_status(&self) -> FetchStatus {
+        FetchStatus::new(self.is_fetching, self.is_queued)
+    }
+
+    // Returns `true` if the given property is different in the other result.
+    fn is_changed(&self, other: &Self, prop: QueryProp) -> bool {
+        match prop {
+            QueryProp::Data => {
+                let same_value = match (&self.value, &other.value) {
+                    (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+                    (None, None) => true,
+                    _ => false,
+                };
+
+                !same_value || self.data_updated_at != other.data_updated_at
+            }
+            QueryProp::Error => {
+                self.state.is_failed() != other.state.is_failed()
+                    || self.error_updated_at != other.error_updated_at
+            }
+            QueryProp::State => {
+                std::mem::discriminant(&self.state) != std::mem::discriminant(&other.state)
+            }
+            QueryProp::IsFetching => self.is_fetching != other.is_fetching,
+            QueryProp::IsQueued => self.is_queued != other.is_queued,
+            QueryProp::FetchStatus => self.fetch_status() != other.fetch_status(),
+            QueryProp::Retry => self.retry != other.retry,
+            QueryProp::Progress => self.progress != other.progress,
+        }
+    }
+}
+
+impl<T> Clone for QueryResult<T> {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            state: self.state.clone(),
+            value: self.value.clone(),
+            is_fetching: self.is_fetching,
+            is_queued: self.is_queued,
+            retry: self.retry,
+            progress: self.progress,
+            data_updated_at: self.data_updated_at,
+            error_updated_at: self.error_updated_at,
+        }
+    }
+}
+
+impl<U> QueryResult<U> {
+    // Constructs a result from the event, with the value selected from the event value.
+    fn from_event<T>(key: QueryKey, event: QueryChangeEvent<T>, value: Option<Rc<U>>) -> Self {
+        QueryResult {
+            key,
+            state: event.state,
+            value,
+            is_fetching: event.is_fetching,
+            is_queued: event.is_queued,
+            retry: event.retry,
+            progress: event.progress,
+            data_updated_at: event.data_updated_at,
+            error_updated_at: event.error_updated_at,
+        }
+    }
+
+    // Constructs a result from the cached state of the query of the observer.
+    fn from_observer<T: 'static>(observer: &QueryObserver<T>, value: Option<Rc<U>>) -> Self {
+        QueryResult {
+            key: observer.key().clone(),
+            state: observer.last_state().unwrap_or(QueryState::Idle),
+            value,
+            is_fetching: observer.is_fetching(),
+            is_queued: observer.is_queued(),
+            retry: None,
+            progress: None,
+            data_updated_at: observer.data_updated_at(),
+            error_updated_at: observer.error_updated_at(),
+        }
+    }
 }
 
 /// Handle returned by `use_query`.
 pub struct UseQueryHandle<T> {
     id: Id,
     key: QueryKey,
-    fetch: Callback<ObserveTarget>,
+    fetch: Callback<(ObserveTarget, FetchCause)>,
     remove: Callback<()>,
-    is_fetching: UseStateHandle<bool>,
-    state: UseStateHandle<QueryState>,
-    value: UseStateHandle<Option<Rc<T>>>,
+    reset: Callback<()>,
+    polling: Callback<Option<Duration>>,
+    stale_at: Option<Instant>,
+    is_placeholder_data: bool,
+    is_previous_data: bool,
+    is_paused: bool,
+    result: Rc<QueryResult<T>>,
 }
 
 impl<T> UseQueryHandle<T> {
@@ -132,13 +577,15 @@ impl<T> UseQueryHandle<T> {
     }
 
     /// Returns the currently available data.
+    ///
+    /// If a refetch fails the last good data is still returned alongside the error.
     pub fn data(&self) -> Option<&T> {
-        self.value.as_deref()
+        self.result.value.as_deref()
     }
 
     /// Returns a error that ocurred during the fetching, if any.
     pub fn error(&self) -> Option<&Error> {
-        match &*self.state {
+        match &self.result.state {
             QueryState::Failed(err) => Some(err),
             _ => None,
         }
@@ -146,7 +593,7 @@ impl<T> UseQueryHandle<T> {
 
     /// Returns the current state of the query.
     pub fn state(&self) -> &QueryState {
-        &self.state
+        &self.result.state
     }
 
     /// Returns the key used to identify the query.
@@ -166,7 +613,42 @@ impl<T> UseQueryHandle<T> {
 
     /// Returns `true` if is fetching data.
     pub fn is_fetching(&self) -> bool {
-        *self.is_fetching
+        self.result.is_fetching
+    }
+
+    /// Returns `true` if the fetch is waiting in the queue of the client to start,
+    /// see `QueryClientBuilder::max_concurrent_fetches`.
+    pub fn is_queued(&self) -> bool {
+        self.result.is_queued
+    }
+
+    /// Returns whether the query is fetching, independently of the state of the query.
+    ///
+    /// Unlike `state` a query with data can be `FetchStatus::Fetching` while refetching,
+    /// and is `FetchStatus::Paused` while the client is paused or offline when rendered.
+    pub fn fetch_status(&self) -> FetchStatus {
+        self.result.fetch_status().or_paused(self.is_paused)
+    }
+
+    /// Returns `true` if the data is the placeholder of the options and not a result of the query.
+    pub fn is_placeholder_data(&self) -> bool {
+        self.is_placeholder_data
+    }
+
+    /// Returns `true` if the data is the result of the previous key,
+    /// while the query of the current key has no data.
+    pub fn is_previous_data(&self) -> bool {
+        self.is_previous_data
+    }
+
+    /// Returns `true` if is fetching in background, this is, while the data is available.
+    ///
+    /// Unlike `is_loading` the last data can still be shown while refetching.
+    pub fn is_refetching(&self) -> bool {
+        self.is_fetching()
+            && self.result.value.is_some()
+            && !self.is_placeholder_data
+            && !self.is_previous_data
     }
 
     /// Returns `true` if has an error.
@@ -179,20 +661,151 @@ impl<T> UseQueryHandle<T> {
         matches!(self.state(), QueryState::Ready)
     }
 
+    /// Returns the number of the retry attempt in progress, if the query is retrying.
+    pub fn retry_attempt(&self) -> Option<usize> {
+        self.result.retry.map(|x| x.attempt())
+    }
+
+    /// Returns the time remaining until the next retry, if the query is retrying.
+    pub fn retry_in(&self) -> Option<Duration> {
+        self.result.retry.map(|x| x.retry_in())
+    }
+
+    /// Returns the progress reported by the fetcher, if the query is fetching.
+    pub fn progress(&self) -> Option<Progress> {
+        self.result.progress
+    }
+
+    /// Returns the last time the query resolved to a value.
+    pub fn data_updated_at(&self) -> Option<Instant> {
+        self.result.data_updated_at
+    }
+
+    /// Returns the last time the query failed.
+    pub fn error_updated_at(&self) -> Option<Instant> {
+        self.result.error_updated_at
+    }
+
+    /// Returns `true` if the data is stale and would be refetched on the next fetch.
+    pub fn is_stale(&self) -> bool {
+        match self.stale_at {
+            Some(stale_at) => Instant::now() >= stale_at,
+            None => false,
+        }
+    }
+
+    /// Returns the time remaining until the data is stale, if the data expires.
+    pub fn stale_in(&self) -> Option<Duration> {
+        self.stale_at
+            .map(|stale_at| stale_at.saturating_duration_since(Instant::now()))
+    }
+
     /// Returns `true` if the query finished with either an error or value.
     pub fn is_completed(&self) -> bool {
         self.is_ready() || self.is_error()
     }
 
     /// Refetch ths data.
+    ///
+    /// If the query is fetching, the fetch in progress is cancelled and a new one is started.
     pub fn refetch(&self) {
-        self.fetch.emit(ObserveTarget::Refetch);
+        self.refetch_with(true);
+    }
+
+    /// Refetch the data, if the query is fetching and `cancel_refetch` is `false`
+    /// waits the fetch in progress instead of starting a new one.
+    pub fn refetch_with(&self, cancel_refetch: bool) {
+        let target = if cancel_refetch {
+            ObserveTarget::Refetch
+        } else {
+            ObserveTarget::JoinOrRefetch
+        };
+
+        self.fetch.emit((target, FetchCause::Manual));
     }
 
     /// Removes the query data.
     pub fn remove(&self) {
         self.remove.emit(());
     }
+
+    /// Returns the query to its initial state, clearing its data and error.
+    ///
+    /// Unlike `remove` the query is kept in the cache with its options.
+    pub fn reset(&self) {
+        self.reset.emit(());
+    }
+
+    /// Starts refetching the query on the given interval, replacing the refetch time of the options.
+    ///
+    /// Has no effect if the query is not in the cache.
+    pub fn start_polling(&self, interval: Duration) {
+        self.polling.emit(Some(interval));
+    }
+
+    /// Stops refetching the query on an interval.
+    pub fn stop_polling(&self) {
+        self.polling.emit(None);
+    }
+
+    /// Returns a handle with the same state and the data mapped by the given function,
+    /// useful to pass part of the data to a child component.
+    ///
+    /// The returned handle controls the same query.
+    pub fn map<U, F>(&self, f: F) -> UseQueryHandle<U>
+    where
+        F: FnOnce(&T) -> U,
+    {
+        let value = self.data().map(|data| Rc::new(f(data)));
+
+        UseQueryHandle {
+            id: self.id,
+            key: self.key.clone(),
+            fetch: self.fetch.clone(),
+            remove: self.remove.clone(),
+            reset: self.reset.clone(),
+            polling: self.polling.clone(),
+            stale_at: self.stale_at,
+            is_placeholder_data: self.is_placeholder_data,
+            is_previous_data: self.is_previous_data,
+            is_paused: self.is_paused,
+            result: Rc::new(self.result.map(value)),
+        }
+    }
+}
+
+impl<T> PartialEq for UseQueryHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        // The data is compared by reference, so `T` don't need to implement `PartialEq`
+        let props = [
+            QueryProp::Data,
+            QueryProp::Error,
+            QueryProp::State,
+            QueryProp::IsFetching,
+            QueryProp::IsQueued,
+            QueryProp::Retry,
+            QueryProp::Progress,
+        ];
+
+        self.key == other.key
+            && self.stale_at == other.stale_at
+            && self.is_placeholder_data == other.is_placeholder_data
+            && self.is_previous_data == other.is_previous_data
+            && self.is_paused == other.is_paused
+            && !props
+                .into_iter()
+                .any(|prop| self.result.is_changed(&other.result, prop))
+    }
+}
+
+impl<T> Debug for UseQueryHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UseQueryHandle")
+            .field("key", &self.key)
+            .field("state", &self.result.state)
+            .field("has_data", &self.result.value.is_some())
+            .finish()
+    }
 }
 
 impl<T> Clone for UseQueryHandle<T> {
@@ -202,9 +815,13 @@ impl<T> Clone for UseQueryHandle<T> {
             key: self.key.clone(),
             fetch: self.fetch.clone(),
             remove: self.remove.clone(),
-            is_fetching: self.is_fetching.clone(),
-            state: self.state.clone(),
-            value: self.value.clone(),
+            reset: self.reset.clone(),
+            polling: self.polling.clone(),
+            stale_at: self.stale_at,
+            is_placeholder_data: self.is_placeholder_data,
+            is_previous_data: self.is_previous_data,
+            is_paused: self.is_paused,
+            result: self.result.clone(),
         }
     }
 }
@@ -235,45 +852,186 @@ where
     use_query_with_options(UseQueryOptions::new_abortable(key.into(), fetcher))
 }
 
+/// This hook allows to observe the result and state of a future which receives the `FetchContext`.
+#[hook]
+pub fn use_query_with_context<F, Fut, K, T, E>(key: K, fetcher: F) -> UseQueryHandle<T>
+where
+    F: Fn(FetchContext) -> Fut + 'static,
+    Fut: Future<Output = Result<T, E>> + 'static,
+    K: Into<Key>,
+    T: 'static,
+    E: Into<Error> + 'static,
+{
+    use_query_with_options(UseQueryOptions::with_context(key.into(), fetcher))
+}
+
+/// This hook allows to observe the result and state of a future for a `TypedQueryKey`,
+/// the fetcher must return the type of the key.
+#[hook]
+pub fn use_typed_query<F, Fut, T, E>(key: TypedQueryKey<T>, fetcher: F) -> UseQueryHandle<T>
+where
+    F: Fn() -> Fut + 'static,
+    Fut: Future<Output = Result<T, E>> + 'static,
+    T: 'static,
+    E: Into<Error> + 'static,
+{
+    use_query_with_options(UseQueryOptions::typed(key, fetcher))
+}
+
+/// This hook allows to observe the result and state of a future which receives the `FetchContext`
+/// for a `TypedQueryKey`, the fetcher must return the type of the key.
+#[hook]
+pub fn use_typed_query_with_context<F, Fut, T, E>(
+    key: TypedQueryKey<T>,
+    fetcher: F,
+) -> UseQueryHandle<T>
+where
+    F: Fn(FetchContext) -> Fut + 'static,
+    Fut: Future<Output = Result<T, E>> + 'static,
+    T: 'static,
+    E: Into<Error> + 'static,
+{
+    use_query_with_options(UseQueryOptions::with_context(key.key().clone(), fetcher))
+}
+
 /// This hook allows to observe the result and state of a future using the given `UseQueryOptions`.
 #[hook]
-pub fn use_query_with_options<Fut, T, E>(options: UseQueryOptions<Fut, T, E>) -> UseQueryHandle<T>
+pub fn use_query_with_options<Fut, T, E, U>(options: UseQueryOptions<Fut, T, E, U>) -> UseQueryHandle<U>
 where
     Fut: Future<Output = Result<T, E>> + 'static,
     T: 'static,
     E: Into<Error> + 'static,
+    U: 'static,
 {
     let UseQueryOptions {
         key,
         fetch,
+        fetcher_id,
+        select,
+        select_eq,
         enabled,
         refetch_on_mount,
         refetch_on_reconnect,
         refetch_on_window_focus,
+        notify_on_change_props,
+        key_delay,
+        placeholder_data,
+        keep_previous_data,
+        propagate_errors,
+        client,
         options,
     } = options;
 
+    let key = use_delayed_value(key, key_delay);
+
+    // The options not set use the defaults of the nearest `QueryOptionsProvider`
+    let defaults = use_context::<QueryOptionsContext>().unwrap_or_default();
+    let options = Some(options.unwrap_or_default().merge(&defaults.options));
+    let refetch_on_mount = refetch_on_mount.or(defaults.refetch_on_mount).unwrap_or(true);
+    let refetch_on_reconnect = refetch_on_reconnect
+        .or(defaults.refetch_on_reconnect)
+        .unwrap_or(true);
+    let refetch_on_window_focus = refetch_on_window_focus
+        .or(defaults.refetch_on_window_focus)
+        .unwrap_or(true);
+
     let id = *use_memo(|_| Id::next(), ());
-    let client = use_query_client().expect("expected QueryClient");
+    // An explicit client takes precedence over the context
+    let context_client = use_query_client();
+    let client = match client {
+        Some(client) => client,
+        None => context_client.expect("expected QueryClient").client().clone(),
+    };
     let abort_controller = use_abort_controller();
-    let observer =
-        use_state(|| QueryObserver::<T>::with_options(client.clone(), key.clone(), options));
     let first_render = use_is_first_render();
     let query_key = QueryKey::of::<T>(key.clone());
 
-    let query_fetching = {
-        let is_fetching = observer.is_fetching();
-        use_state(|| is_fetching)
+    // A new key uses a new observer, the old one unsubscribes when dropped
+    let observer = {
+        let client = client.clone();
+        use_memo(
+            move |_| QueryObserver::<T>::with_options(client, key, options),
+            query_key.clone(),
+        )
     };
 
-    let query_state = {
-        let last_state = observer.last_state();
-        use_state(|| last_state.unwrap_or(QueryState::Idle))
+    // The latest value is checked again before fetching
+    let latest_enabled = use_mut_ref(|| enabled.clone());
+    *latest_enabled.borrow_mut() = enabled.clone();
+    let enabled = enabled.get();
+
+    // Disabling this observer don't affect other observers of the same query
+    observer.set_enabled(enabled);
+
+    // The last data and its selected value, the selector only runs when the data changes
+    let selected = use_mut_ref(|| None::<(Rc<T>, Rc<U>)>);
+    let select = {
+        let selected = selected.clone();
+        Rc::new(move |value: Option<Rc<T>>| -> Option<Rc<U>> {
+            let value = value?;
+            let mut selected = selected.borrow_mut();
+            if let Some((source, output)) = &*selected {
+                if Rc::ptr_eq(source, &value) {
+                    return Some(output.clone());
+                }
+            }
+
+            let output = select(&value);
+            let output = match (&*selected, &select_eq) {
+                (Some((_, last)), Some(eq)) if eq(last, &output) => last.clone(),
+                _ => output,
+            };
+
+            *selected = Some((value, output.clone()));
+            Some(output)
+        })
     };
 
-    let query_value = {
-        let last_value = observer.last_value();
-        use_state(move || last_value)
+    let query_result = {
+        let result = QueryResult::from_observer(&observer, select(observer.last_value()));
+        use_state(move || result)
+    };
+
+    // After the key changes the state still holds the result of the previous key,
+    // until updated we present the cached result of the new key
+    let is_new_key = query_result.key != query_key;
+    let result = if is_new_key {
+        QueryResult::from_observer(&observer, select(observer.last_value()))
+    } else {
+        (*query_result).clone()
+    };
+
+    // Without data we present the data of the previous key or the placeholder,
+    // these are never stored in the cache
+    let previous_data = use_mut_ref(|| None::<(QueryKey, Rc<U>)>);
+    let mut result = result;
+    let mut is_previous_data = false;
+    let mut is_placeholder_data = false;
+
+    match result.value.clone() {
+        Some(value) => *previous_data.borrow_mut() = Some((query_key.clone(), value)),
+        None => {
+            let previous = match &*previous_data.borrow() {
+                Some((key, value)) if keep_previous_data && *key != query_key => {
+                    Some(value.clone())
+                }
+                _ => None,
+            };
+
+            if let Some(previous) = previous {
+                result.value = Some(previous);
+                is_previous_data = true;
+            } else if let Some(placeholder) = placeholder_data {
+                result.value = select(Some(placeholder));
+                is_placeholder_data = true;
+            }
+        }
+    }
+
+    // The last result rendered, to check the changed properties
+    let rendered_result = {
+        let result = (*query_result).clone();
+        use_mut_ref(move || result)
     };
 
     // We use an id to ensure only set the last value
@@ -282,44 +1040,65 @@ where
     let is_stale = observer.is_stale();
 
     let do_fetch = {
-        let query_state = query_state.clone();
-        let query_value = query_value.clone();
-        let query_fetching = query_fetching.clone();
+        let query_result = query_result.clone();
+        let rendered_result = rendered_result.clone();
+        let select = select.clone();
+        let observer = observer.clone();
         let fetch = fetch.clone();
         let latest_id = latest_id.clone();
         let abort_controller = abort_controller.clone();
 
         use_callback(
-            move |target, deps| {
-                let enabled = deps.0;
+            move |(target, cause), deps| {
+                let (_, query_key) = deps.clone();
+
+                observer.set_enabled(latest_enabled.borrow().get());
                 
                 let self_id = latest_id.get().wrapping_add(1);
                 (*latest_id).set(self_id);
                 
-                let query_value = query_value.clone();
-                let query_state = query_state.clone();
-                let query_fetching = query_fetching.clone();
+                let query_result = query_result.clone();
+                let rendered_result = rendered_result.clone();
+                let notify_on_change_props = notify_on_change_props.clone();
+                let select = select.clone();
                 let latest_id = latest_id.clone();
                 
                 let signal = abort_controller.signal();
                 let fetch = fetch.clone();
-                let f = move || fetch(signal.clone());
+                let f = BoxFetcher::with_context(move |ctx| {
+                    let signal = signal.clone();
+                    let fut = fetch(ctx, signal.clone());
 
-                observer.observe(target, f, move |event| {
-                    if !enabled {
+                    async move {
+                        let ret = fut.await.map_err(Into::into);
+
+                        // Errors after an abort are reported as cancellations
+                        match ret {
+                            Err(_) if signal.aborted() => Err(Error::new(QueryError::Cancelled)),
+                            ret => ret,
+                        }
+                    }
+                })
+                .with_id(fetcher_id);
+
+                observer.observe_with_cause(target, cause, f, move |event| {
+                    if latest_id.get() != self_id {
                         return;
                     }
 
-                    let QueryChangeEvent {
-                        state,
-                        value,
-                        is_fetching,
-                    } = event;
+                    let value = select(event.value.clone());
+                    let result = QueryResult::from_event(query_key.clone(), event, value);
+                    let should_render = match &notify_on_change_props {
+                        Some(props) => {
+                            let rendered = rendered_result.borrow();
+                            props.iter().any(|prop| rendered.is_changed(&result, *prop))
+                        }
+                        None => true,
+                    };
 
-                    if latest_id.get() == self_id {
-                        query_value.set(value);
-                        query_state.set(state);
-                        query_fetching.set(is_fetching);
+                    if should_render {
+                        *rendered_result.borrow_mut() = result.clone();
+                        query_result.set(result);
                     }
                 });
             },
@@ -328,9 +1107,8 @@ where
     };
 
     let remove = {
-        let query_value = query_value.clone();
-        let query_state = query_state.clone();
-        let query_fetching = query_fetching.clone();
+        let query_result = query_result.clone();
+        let rendered_result = rendered_result.clone();
         let client = client.clone();
         let query_key = query_key.clone();
 
@@ -343,21 +1121,68 @@ where
                 (*latest_id).set(self_id);
 
                 client.remove_query_data(key);
-                query_state.set(QueryState::Idle);
-                query_value.set(None);
-                query_fetching.set(false);
+                *rendered_result.borrow_mut() = QueryResult::idle(key.clone());
+                query_result.set(QueryResult::idle(key.clone()));
+            },
+            (query_key.clone(),),
+        )
+    };
+
+    let reset = {
+        let query_result = query_result.clone();
+        let rendered_result = rendered_result.clone();
+        let client = client.clone();
+
+        use_callback(
+            move |(), (key,)| {
+                // The query keeps its observers, so later fetches still update the state
+                client.reset_query(key);
+                *rendered_result.borrow_mut() = QueryResult::idle(key.clone());
+                query_result.set(QueryResult::idle(key.clone()));
+            },
+            (query_key.clone(),),
+        )
+    };
+
+    let polling = {
+        let client = client.clone();
+
+        use_callback(
+            move |refetch_time, (key,)| {
+                client.set_refetch_time(key, refetch_time);
             },
             (query_key.clone(),),
         )
     };
 
+    // Sends the error to the nearest error boundary
+    {
+        let error_boundary = use_context::<QueryErrorBoundaryContext>();
+        let error = match &result.state {
+            QueryState::Failed(err) if propagate_errors => Some(err.clone()),
+            _ => None,
+        };
+
+        use_effect_with_deps(
+            move |(key, _, _)| {
+                if let (Some(error_boundary), Some(error)) = (error_boundary, error) {
+                    error_boundary.report.emit((key.clone(), error));
+                }
+            },
+            (query_key.clone(), result.state.is_failed(), result.error_updated_at),
+        );
+    }
+
     // Check enabled
     {
-        let query_state = query_state.clone();
+        let query_result = query_result.clone();
         use_effect_with_deps(
             move |enabled| {
                 if !enabled {
-                    query_state.set(QueryState::Idle);
+                    query_result.set(QueryResult {
+                        state: QueryState::Idle,
+                        ..(*query_result).clone()
+                    });
                 }
             },
             enabled,
@@ -370,24 +1195,25 @@ where
 
         use_effect_with_deps(
             move |_| {
-                if first_render || refetch_on_mount {
-                    do_fetch.emit(ObserveTarget::Fetch);
+                if first_render || refetch_on_mount || is_new_key {
+                    do_fetch.emit((ObserveTarget::Fetch, FetchCause::Mount));
                 }
 
                 move || {
                     abort_controller.abort();
                 }
             },
-            (is_stale,),
+            (is_stale, query_key.clone(), enabled),
         );
     }
 
     // On reconnect
     {
         let do_fetch = do_fetch.clone();
-        use_on_online(move || {
-            if refetch_on_reconnect {
-                do_fetch.emit(ObserveTarget::Refetch);
+        let client = client.clone();
+        use_on_online(client.online_manager(), move || {
+            if refetch_on_reconnect && !is_refetch_suspended() && !client.is_paused() {
+                do_fetch.emit((ObserveTarget::JoinOrRefetch, FetchCause::Reconnect));
             }
         });
     }
@@ -395,22 +1221,32 @@ where
     // On window focus
     {
         let do_fetch = do_fetch.clone();
-        use_on_window_focus(move || {
-            if refetch_on_window_focus {
-                do_fetch.emit(ObserveTarget::Refetch);
+        let client = client.clone();
+        use_on_window_focus(client.focus_manager(), move || {
+            if refetch_on_window_focus && !is_refetch_suspended() && !client.is_paused() {
+                do_fetch.emit((ObserveTarget::JoinOrRefetch, FetchCause::WindowFocus));
             }
         });
     }
 
     //
 
+    let is_offline = match client.online_manager() {
+        Some(online_manager) => !online_manager.is_online(),
+        None => !BrowserOnlineManager.is_online(),
+    };
+
     UseQueryHandle {
         id,
         key: query_key,
         remove,
+        reset,
+        polling,
+        stale_at: observer.stale_at(),
+        is_placeholder_data,
+        is_previous_data,
+        is_paused: client.is_paused() || is_offline,
         fetch: do_fetch,
-        state: query_state,
-        value: query_value,
-        is_fetching: query_fetching,
+        result: Rc::new(result),
     }
 }
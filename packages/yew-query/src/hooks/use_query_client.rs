@@ -1,10 +1,110 @@
 use crate::context::QueryClientContext;
-use yew::{hook, use_context};
-use yew_query_core::QueryClient;
+use std::{ops::Deref, rc::Rc};
+use yew::{hook, platform::spawn_local, use_context};
+use yew_query_core::{error::QueryError, fetcher::Fetch, Key, QueryClient, QueryFilter, QueryKey};
 
-/// Returns the current `QueryClient`.
+/// Handle returned by `use_query_client`.
+///
+/// Provides convenience methods over the `QueryClient` that don't require a mutable reference.
+#[derive(Debug, Clone)]
+pub struct UseQueryClientHandle {
+    client: QueryClient,
+}
+
+impl UseQueryClientHandle {
+    /// Returns the underlying `QueryClient`.
+    pub fn client(&self) -> &QueryClient {
+        &self.client
+    }
+
+    /// Marks as stale the queries matching the given filter.
+    ///
+    /// Returns the number of queries invalidated.
+    pub fn invalidate(&self, filter: impl Into<QueryFilter>) -> usize {
+        self.client.invalidate_queries(filter)
+    }
+
+    /// Invalidates and refetches in the background the queries matching the given filter.
+    pub fn refetch(&self, filter: impl Into<QueryFilter>) {
+        let client = self.client.clone();
+        let filter = filter.into();
+
+        spawn_local(async move {
+            client.refetch_queries(filter).await;
+        });
+    }
+
+    /// Fetches in the background the query with the given key and stores the result in the cache.
+    pub fn prefetch<K, F, T>(&self, key: K, fetcher: F)
+    where
+        K: Into<Key>,
+        F: Fetch<T> + 'static,
+        T: 'static,
+    {
+        let mut client = self.client.clone();
+        let key = QueryKey::of::<T>(key);
+
+        spawn_local(async move {
+            // The errors are stored in the state of the query
+            client.fetch_query(key, fetcher).await.ok();
+        });
+    }
+
+    /// Returns the cached data of the query with the given key, if fresh.
+    pub fn get_data<K, T>(&self, key: K) -> Option<Rc<T>>
+    where
+        K: Into<Key>,
+        T: 'static,
+    {
+        let key = QueryKey::of::<T>(key);
+        self.client.get_query_data::<T>(&key).ok()
+    }
+
+    /// Sets the cached data of the query with the given key.
+    pub fn set_data<K, T>(&self, key: K, value: T) -> Result<(), QueryError>
+    where
+        K: Into<Key>,
+        T: 'static,
+    {
+        let key = QueryKey::of::<T>(key);
+
+        // The cache is shared between the clones of the client
+        let mut client = self.client.clone();
+        client.set_query_data(key, value)
+    }
+
+    /// Removes the queries stored in the given partition of the cache.
+    ///
+    /// Returns the number of queries removed.
+    pub fn clear_partition(&self, partition: &str) -> usize {
+        let mut client = self.client.clone();
+        client.clear_partition(partition)
+    }
+
+    /// Merges the given update into the cached data of the query with the given key,
+    /// using the reducer attached with `QueryClient::set_query_reducer`.
+    pub fn apply_update<K, T, U>(&self, key: K, update: U) -> Result<(), QueryError>
+    where
+        K: Into<Key>,
+        T: 'static,
+        U: 'static,
+    {
+        let key = QueryKey::of::<T>(key);
+        self.client.apply_update(&key, update)
+    }
+}
+
+impl Deref for UseQueryClientHandle {
+    type Target = QueryClient;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+/// Returns a handle to the current `QueryClient`.
 #[hook]
-pub fn use_query_client() -> Option<QueryClient> {
+pub fn use_query_client() -> Option<UseQueryClientHandle> {
     let ctx = use_context::<QueryClientContext>()?;
-    Some(ctx.client)
+    Some(UseQueryClientHandle { client: ctx.client })
 }
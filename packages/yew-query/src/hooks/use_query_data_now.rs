@@ -0,0 +1,24 @@
+use super::use_query_client;
+use std::rc::Rc;
+use yew::hook;
+use yew_query_core::{Key, QueryKey};
+
+/// This hook reads the cached data of the query with the given key on render,
+/// the data is returned even if stale.
+///
+/// Unlike `use_query_value` the component is not subscribed to the query and the query
+/// is never fetched, a newer value is only read when the component renders again.
+#[hook]
+pub fn use_query_data_now<K, T>(key: K) -> Option<Rc<T>>
+where
+    K: Into<Key>,
+    T: 'static,
+{
+    let client = use_query_client().expect("expected QueryClient");
+    let key = QueryKey::of::<T>(key.into());
+
+    let query = client.get_query(&key).map(|q| q.clone());
+    query
+        .and_then(|q| q.last_value())
+        .and_then(|x| x.downcast::<T>().ok())
+}
@@ -0,0 +1,32 @@
+use super::common::use_cache_selector;
+use yew::hook;
+use yew_query_core::{Error, QueryFilter, QueryKey};
+
+// The failed queries, compared by key and error message.
+#[derive(Clone)]
+struct FailedQueries(Vec<(QueryKey, Error)>);
+
+impl PartialEq for FailedQueries {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self
+                .0
+                .iter()
+                .zip(other.0.iter())
+                .all(|((a_key, a_err), (b_key, b_err))| {
+                    a_key == b_key && a_err.to_string() == b_err.to_string()
+                })
+    }
+}
+
+/// This hook returns the key and error of the failed queries matching the filter.
+///
+/// Is updated when any query of the cache changes, useful for showing the errors in a single place.
+#[hook]
+pub fn use_query_errors(filter: impl Into<QueryFilter>) -> Vec<(QueryKey, Error)> {
+    let failed = use_cache_selector(filter, |client, filter| {
+        FailedQueries(client.failed_queries(filter.clone()))
+    });
+
+    failed.0
+}
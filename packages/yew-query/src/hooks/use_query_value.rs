@@ -0,0 +1,119 @@
+use super::use_query_client;
+use futures::{future::abortable, StreamExt};
+use std::rc::Rc;
+use yew::{hook, platform::spawn_local, use_effect_with_deps, use_state, UseStateHandle};
+use yew_query_core::{Error, Key, QueryKey, QueryState};
+
+/// Handle returned by `use_query_value`.
+pub struct UseQueryValueHandle<T> {
+    key: QueryKey,
+    value: UseStateHandle<Option<Rc<T>>>,
+    state: UseStateHandle<QueryState>,
+    is_fetching: UseStateHandle<bool>,
+}
+
+impl<T> UseQueryValueHandle<T> {
+    /// Returns the cached data of the query, if any.
+    pub fn data(&self) -> Option<&T> {
+        self.value.as_deref()
+    }
+
+    /// Returns the error of the query, if any.
+    pub fn error(&self) -> Option<&Error> {
+        match &*self.state {
+            QueryState::Failed(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Returns the current state of the query.
+    pub fn state(&self) -> &QueryState {
+        &self.state
+    }
+
+    /// Returns the key of the query.
+    pub fn key(&self) -> &QueryKey {
+        &self.key
+    }
+
+    /// Returns `true` if the query is fetching data.
+    pub fn is_fetching(&self) -> bool {
+        *self.is_fetching
+    }
+}
+
+impl<T> Clone for UseQueryValueHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            value: self.value.clone(),
+            state: self.state.clone(),
+            is_fetching: self.is_fetching.clone(),
+        }
+    }
+}
+
+/// This hook observes the cached value of the query with the given key without fetching it.
+///
+/// The value is updated when other components fetch or update the query.
+#[hook]
+pub fn use_query_value<K, T>(key: K) -> UseQueryValueHandle<T>
+where
+    K: Into<Key>,
+    T: 'static,
+{
+    let client = use_query_client()
+        .expect("expected QueryClient")
+        .client()
+        .clone();
+    let key = QueryKey::of::<T>(key.into());
+
+    let value = {
+        let query = client.get_query(&key).map(|q| q.clone());
+        use_state(move || {
+            query
+                .and_then(|q| q.last_value())
+                .and_then(|x| x.downcast::<T>().ok())
+        })
+    };
+
+    let state = {
+        let last_state = client.get_query_state(&key);
+        use_state(move || last_state.unwrap_or(QueryState::Idle))
+    };
+
+    let is_fetching = use_state(|| false);
+
+    {
+        let value = value.clone();
+        let state = state.clone();
+        let is_fetching = is_fetching.clone();
+
+        use_effect_with_deps(
+            move |key| {
+                let mut watcher = client.watch_query::<T>(key.clone());
+                let (task, handle) = abortable(async move {
+                    while let Some(event) = watcher.next().await {
+                        value.set(event.value);
+                        state.set(event.state);
+                        is_fetching.set(event.is_fetching);
+                    }
+                });
+
+                spawn_local(async move {
+                    task.await.ok();
+                });
+
+                move || handle.abort()
+            },
+            key.clone(),
+        );
+    }
+
+    UseQueryValueHandle {
+        key,
+        value,
+        state,
+        is_fetching,
+    }
+}
@@ -0,0 +1,45 @@
+use super::{use_query_with_options, UseQueryHandle, UseQueryOptions};
+use futures::Future;
+use yew::hook;
+use yew_query_core::{Error, Key};
+
+/// This hook fetches a query that depends on a value, usually the data of other query.
+///
+/// The key of the query is the given key followed by the value, like `projects/42`,
+/// so dependent queries with different keys don't collide in the cache.
+/// The query is disabled while the value is `None`, and then uses the given key alone.
+///
+/// # Example
+/// ```rust,ignore
+/// let user = use_query("user", fetch_user);
+/// let projects = use_query_when("projects", user.data().map(|u| u.id), |id| fetch_projects(id));
+/// ```
+#[hook]
+pub fn use_query_when<K, D, F, Fut, T, E>(
+    key: K,
+    dependency: Option<D>,
+    fetcher: F,
+) -> UseQueryHandle<T>
+where
+    K: Into<Key>,
+    D: Into<Key> + Clone + 'static,
+    F: Fn(D) -> Fut + 'static,
+    Fut: Future<Output = Result<T, E>> + 'static,
+    T: 'static,
+    E: Into<Error> + 'static,
+{
+    // Without a value the query is disabled, so the key is never fetched
+    let prefix: Key = key.into();
+    let key = match dependency.clone().map(Into::<Key>::into) {
+        Some(dependency) => Key::from((&*prefix, &*dependency)),
+        None => prefix,
+    };
+    let enabled = dependency.is_some();
+
+    let options = UseQueryOptions::new(key, move || {
+        let dependency = dependency.clone().expect("expected query dependency");
+        fetcher(dependency)
+    });
+
+    use_query_with_options(options.enabled(enabled))
+}
@@ -0,0 +1,34 @@
+use super::use_query_client;
+use yew::{hook, use_callback, Callback};
+use yew_query_core::QueryFilter;
+
+/// This hook returns a callback that invalidates and refetches the queries matching a filter.
+///
+/// The queries don't need to be owned by the component using the callback.
+///
+/// # Example
+/// ```rust,ignore
+/// let refetch = use_refetch_queries();
+/// let onclick = move |_| refetch.emit("todos".into());
+/// ```
+#[hook]
+pub fn use_refetch_queries() -> Callback<QueryFilter> {
+    let client = use_query_client().expect("expected QueryClient");
+
+    use_callback(move |filter: QueryFilter, _| client.refetch(filter), ())
+}
+
+/// This hook returns a callback that marks as stale the queries matching a filter.
+///
+/// The queries are refetched the next time they are used.
+#[hook]
+pub fn use_invalidate_queries() -> Callback<QueryFilter> {
+    let client = use_query_client().expect("expected QueryClient");
+
+    use_callback(
+        move |filter: QueryFilter, _| {
+            client.invalidate(filter);
+        },
+        (),
+    )
+}
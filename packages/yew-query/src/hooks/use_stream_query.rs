@@ -0,0 +1,61 @@
+use super::{use_query_with_options, UseQueryHandle, UseQueryOptions};
+use futures::{future::LocalBoxFuture, Stream, StreamExt};
+use yew::hook;
+use yew_query_core::{error::QueryError, fetcher::FetcherId, Error, Key};
+
+/// The future used by queries backed by a stream.
+pub type StreamFuture<T> = LocalBoxFuture<'static, Result<T, Error>>;
+
+impl<T> UseQueryOptions<StreamFuture<T>, T, Error>
+where
+    T: Clone + 'static,
+{
+    /// Constructs a new `UseQueryOptions` from a function returning a stream,
+    /// each item of the stream updates the value of the query.
+    ///
+    /// The query keeps fetching until the stream ends, the last item is the value of the query.
+    pub fn from_stream<K, F, S, E>(key: K, stream: F) -> Self
+    where
+        K: Into<Key>,
+        F: Fn() -> S + 'static,
+        S: Stream<Item = Result<T, E>> + 'static,
+        E: Into<Error> + 'static,
+    {
+        Self::with_context_and_signal(key, move |ctx, signal| {
+            let stream = stream();
+
+            Box::pin(async move {
+                futures::pin_mut!(stream);
+                let mut last = None;
+
+                while let Some(item) = stream.next().await {
+                    if signal.aborted() {
+                        return Err(Error::new(QueryError::Cancelled));
+                    }
+
+                    let item = item.map_err(Into::into)?;
+                    ctx.report_partial(item.clone());
+                    last = Some(item);
+                }
+
+                last.ok_or_else(|| Error::new(QueryError::NotReady))
+            })
+        })
+        .with_fetcher_id(FetcherId::of::<F>())
+    }
+}
+
+/// This hook allows to observe a stream, each item of the stream updates the value of the query.
+///
+/// The query keeps fetching until the stream ends, the last item is the value of the query.
+#[hook]
+pub fn use_stream_query<F, S, K, T, E>(key: K, stream: F) -> UseQueryHandle<T>
+where
+    F: Fn() -> S + 'static,
+    S: Stream<Item = Result<T, E>> + 'static,
+    K: Into<Key>,
+    T: Clone + 'static,
+    E: Into<Error> + 'static,
+{
+    use_query_with_options(UseQueryOptions::from_stream(key, stream))
+}
@@ -0,0 +1,17 @@
+use yew::{hook, use_effect_with_deps};
+use yew_query_core::suspend_refetches;
+
+/// This hook suppresses the refetches started by events while `suspended` is `true`,
+/// like the refetches on window focus, reconnection or the refetch interval.
+///
+/// Useful during route transitions, to resume the refetches after the new page settles.
+#[hook]
+pub fn use_suspend_refetches(suspended: bool) {
+    use_effect_with_deps(
+        move |suspended| {
+            let suspension = suspended.then(suspend_refetches);
+            move || drop(suspension)
+        },
+        suspended,
+    );
+}
@@ -0,0 +1,396 @@
+use crate::{use_query_with_options, UseQueryHandle, UseQueryOptions};
+use futures::future::LocalBoxFuture;
+use gloo_net::http::{Method, Request, RequestBuilder, Response};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fmt::{Debug, Display, Write},
+    marker::PhantomData,
+    rc::Rc,
+};
+use yew::hook;
+use yew_query_core::{Key, TypedQueryKey};
+
+/// An error that occurs while executing an HTTP fetcher.
+#[derive(Debug)]
+pub enum HttpError {
+    /// The request failed or the body could not be decoded.
+    Request(gloo_net::Error),
+
+    /// The server responded with a non-success status.
+    Status {
+        /// The status code of the response.
+        status: u16,
+
+        /// The status text of the response.
+        status_text: String,
+    },
+}
+
+impl Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HttpError::Request(err) => write!(f, "request failed: {err}"),
+            HttpError::Status {
+                status,
+                status_text,
+            } => write!(f, "request failed with status {status}: {status_text}"),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HttpError::Request(err) => Some(err),
+            HttpError::Status { .. } => None,
+        }
+    }
+}
+
+impl From<gloo_net::Error> for HttpError {
+    fn from(err: gloo_net::Error) -> Self {
+        HttpError::Request(err)
+    }
+}
+
+/// Sends a `GET` request to the given url and decodes the json response.
+pub async fn get_json<T>(url: &str) -> Result<T, HttpError>
+where
+    T: DeserializeOwned,
+{
+    let res = Request::get(url).send().await?;
+    let value = check_status(res)?.json::<T>().await?;
+    Ok(value)
+}
+
+/// Sends a request with the given method and json body to the url and decodes the json response.
+pub async fn send_json<B, T>(method: Method, url: &str, body: &B) -> Result<T, HttpError>
+where
+    B: Serialize + ?Sized,
+    T: DeserializeOwned,
+{
+    let res = RequestBuilder::new(url)
+        .method(method)
+        .json(body)?
+        .send()
+        .await?;
+    let value = check_status(res)?.json::<T>().await?;
+    Ok(value)
+}
+
+/// Sends a `DELETE` request to the given url, the response body is ignored.
+pub async fn delete(url: &str) -> Result<(), HttpError> {
+    let res = Request::delete(url).send().await?;
+    check_status(res)?;
+    Ok(())
+}
+
+// Returns an error if the response don't have a success status.
+fn check_status(res: Response) -> Result<Response, HttpError> {
+    check_status_code(res.status(), || res.status_text())?;
+    Ok(res)
+}
+
+// Returns an error if the status is not in the `200-299` range, as `Response::ok`.
+fn check_status_code<F>(status: u16, status_text: F) -> Result<(), HttpError>
+where
+    F: FnOnce() -> String,
+{
+    if !(200..300).contains(&status) {
+        return Err(HttpError::Status {
+            status,
+            status_text: status_text(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Returns a fetcher that sends a `GET` request to the given url and decodes the json response.
+pub fn json_fetcher<T>(
+    url: impl Into<String>,
+) -> impl Fn() -> LocalBoxFuture<'static, Result<T, HttpError>> + Clone + 'static
+where
+    T: DeserializeOwned + 'static,
+{
+    let url: String = url.into();
+    move || {
+        let url = url.clone();
+        Box::pin(async move { get_json::<T>(&url).await })
+    }
+}
+
+/// A typed HTTP endpoint that decodes the json responses to `T`.
+///
+/// Declares the method, url, query parameters, headers and body of the request,
+/// the fetcher returns an error for the responses without a success status.
+///
+/// # Example
+/// ```
+/// use serde::Deserialize;
+/// use yew_query::http::Endpoint;
+///
+/// #[derive(Deserialize)]
+/// struct Todo {
+///     title: String,
+/// }
+///
+/// let endpoint = Endpoint::<Vec<Todo>>::get("/api/users")
+///     .path(42)
+///     .path("todos")
+///     .query("done", false)
+///     .header("Accept-Language", "en");
+///
+/// assert_eq!(endpoint.url(), "/api/users/42/todos?done=false");
+/// ```
+pub struct Endpoint<T> {
+    method: Method,
+    url: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Endpoint<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    /// Constructs an `Endpoint` with the given method and url.
+    pub fn new(method: Method, url: impl Into<String>) -> Self {
+        Endpoint {
+            method,
+            url: url.into(),
+            query: Vec::new(),
+            headers: Vec::new(),
+            body: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Constructs a `GET` endpoint.
+    pub fn get(url: impl Into<String>) -> Self {
+        Self::new(Method::GET, url)
+    }
+
+    /// Constructs a `POST` endpoint.
+    pub fn post(url: impl Into<String>) -> Self {
+        Self::new(Method::POST, url)
+    }
+
+    /// Constructs a `PUT` endpoint.
+    pub fn put(url: impl Into<String>) -> Self {
+        Self::new(Method::PUT, url)
+    }
+
+    /// Constructs a `PATCH` endpoint.
+    pub fn patch(url: impl Into<String>) -> Self {
+        Self::new(Method::PATCH, url)
+    }
+
+    /// Constructs a `DELETE` endpoint.
+    pub fn delete(url: impl Into<String>) -> Self {
+        Self::new(Method::DELETE, url)
+    }
+
+    /// Appends a segment to the path of the url, the segment is percent-encoded.
+    pub fn path(mut self, segment: impl Display) -> Self {
+        if !self.url.ends_with('/') {
+            self.url.push('/');
+        }
+
+        encode_component(&segment.to_string(), &mut self.url);
+        self
+    }
+
+    /// Appends a query parameter to the url, the parameters are percent-encoded.
+    pub fn query(mut self, name: impl Into<String>, value: impl Display) -> Self {
+        self.query.push((name.into(), value.to_string()));
+        self
+    }
+
+    /// Sets a header of the request.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the json body of the request.
+    pub fn json<B>(mut self, body: &B) -> Result<Self, HttpError>
+    where
+        B: Serialize + ?Sized,
+    {
+        let body = serde_json::to_string(body).map_err(gloo_net::Error::from)?;
+        self.body = Some(body);
+        Ok(self)
+    }
+
+    /// Returns the method of the request.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Returns the url of the request, including the query parameters.
+    pub fn url(&self) -> String {
+        let mut url = self.url.clone();
+
+        for (idx, (name, value)) in self.query.iter().enumerate() {
+            url.push(if idx == 0 { '?' } else { '&' });
+            encode_component(name, &mut url);
+            url.push('=');
+            encode_component(value, &mut url);
+        }
+
+        url
+    }
+
+    /// Sends the request and decodes the json response.
+    pub async fn send(&self) -> Result<T, HttpError> {
+        let mut request = RequestBuilder::new(&self.url()).method(self.method.clone());
+
+        for (name, value) in self.headers.iter() {
+            request = request.header(name, value);
+        }
+
+        let res = match &self.body {
+            Some(body) => {
+                request
+                    .header("Content-Type", "application/json")
+                    .body(body.as_str())?
+                    .send()
+                    .await?
+            }
+            None => request.send().await?,
+        };
+
+        let value = check_status(res)?.json::<T>().await?;
+        Ok(value)
+    }
+
+    /// Returns a fetcher that sends the request and decodes the json response.
+    pub fn fetcher(
+        &self,
+    ) -> impl Fn() -> LocalBoxFuture<'static, Result<T, HttpError>> + Clone + 'static {
+        let endpoint = Rc::new(self.clone());
+        move || {
+            let endpoint = endpoint.clone();
+            Box::pin(async move { endpoint.send().await })
+        }
+    }
+}
+
+impl<T> Clone for Endpoint<T> {
+    fn clone(&self) -> Self {
+        Endpoint {
+            method: self.method.clone(),
+            url: self.url.clone(),
+            query: self.query.clone(),
+            headers: self.headers.clone(),
+            body: self.body.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Debug for Endpoint<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Endpoint")
+            .field("method", &self.method)
+            .field("url", &self.url)
+            .field("query", &self.query)
+            .field("headers", &self.headers)
+            .finish()
+    }
+}
+
+// Percent-encodes the characters of the value that are not unreserved in urls.
+fn encode_component(value: &str, out: &mut String) {
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => write!(out, "%{byte:02X}").unwrap(),
+        }
+    }
+}
+
+/// This hook fetches the given `Endpoint`, caching the decoded response with the given key.
+///
+/// The `GET` endpoints with the same url share the fetch in progress,
+/// even if cached with different keys, see `QueryOptions::request_key`.
+#[hook]
+pub fn use_endpoint<K, T>(key: K, endpoint: Endpoint<T>) -> UseQueryHandle<T>
+where
+    K: Into<Key>,
+    T: DeserializeOwned + 'static,
+{
+    let options = UseQueryOptions::typed(TypedQueryKey::new(key), endpoint.fetcher());
+    let options = match *endpoint.method() {
+        Method::GET => options.request_key(endpoint.url()),
+        _ => options,
+    };
+
+    use_query_with_options(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_status_code, Endpoint, HttpError};
+    use std::error::Error;
+
+    #[test]
+    fn check_status_code_test() {
+        assert!(check_status_code(200, || unreachable!()).is_ok());
+        assert!(check_status_code(204, || unreachable!()).is_ok());
+        assert!(check_status_code(299, || unreachable!()).is_ok());
+
+        for status in [101, 304, 404, 500] {
+            let err = check_status_code(status, || "Failed".to_owned()).unwrap_err();
+            assert!(matches!(err, HttpError::Status { status: x, .. } if x == status));
+        }
+    }
+
+    #[test]
+    fn http_error_test() {
+        let err = check_status_code(404, || "Not Found".to_owned()).unwrap_err();
+        assert_eq!(err.to_string(), "request failed with status 404: Not Found");
+        assert!(err.source().is_none());
+
+        let err = HttpError::from(gloo_net::Error::GlooError("timeout".to_owned()));
+        assert!(matches!(err, HttpError::Request(_)));
+        assert_eq!(err.to_string(), "request failed: timeout");
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn endpoint_url_test() {
+        let endpoint = Endpoint::<()>::get("/api/users/")
+            .path("john doe")
+            .path("a/b?c#d")
+            .path(-1);
+        assert_eq!(endpoint.url(), "/api/users/john%20doe/a%2Fb%3Fc%23d/-1");
+
+        let endpoint = Endpoint::<()>::get("/search")
+            .query("q", "rust & yew")
+            .query("tag[]", "100%")
+            .query("lang", "español");
+        assert_eq!(
+            endpoint.url(),
+            "/search?q=rust%20%26%20yew&tag%5B%5D=100%25&lang=espa%C3%B1ol"
+        );
+
+        let endpoint = Endpoint::<()>::get("/api").path("~user_1.0-a");
+        assert_eq!(endpoint.url(), "/api/~user_1.0-a");
+    }
+
+    #[test]
+    fn endpoint_json_test() {
+        let endpoint = Endpoint::<()>::post("/api/todos")
+            .json(&serde_json::json!({ "title": "Learn yew" }))
+            .unwrap();
+
+        assert_eq!(*endpoint.method(), gloo_net::http::Method::POST);
+        assert_eq!(endpoint.body.as_deref(), Some(r#"{"title":"Learn yew"}"#));
+    }
+}
@@ -1,11 +1,31 @@
+mod browser;
+mod components;
 mod context;
 mod hooks;
+mod macros;
 
+pub use browser::*;
+pub use components::*;
 pub use context::*;
 pub use hooks::*;
 
 pub use yew_query_core::*;
 
+#[cfg(feature = "http")]
+pub mod http;
+
+#[cfg(feature = "http")]
+pub mod resource;
+
+#[cfg(feature = "cache-storage")]
+pub mod cache_storage;
+
+#[cfg(feature = "websocket")]
+pub mod subscription;
+
+#[cfg(feature = "eventsource")]
+pub mod sse;
+
 #[allow(dead_code)]
 pub(crate) mod listener;
 
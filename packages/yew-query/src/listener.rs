@@ -2,12 +2,14 @@ use std::rc::Rc;
 use wasm_bindgen::{prelude::Closure, JsCast};
 use web_sys::{window, Event, EventTarget};
 
+type EventClosure = Rc<Closure<dyn FnMut(Event)>>;
+
 /// A listener to an element event.
 #[derive(Clone)]
 pub struct EventListener {
     event: String,
     target: EventTarget,
-    closure: Option<Rc<Closure<dyn FnMut(Event)>>>,
+    closure: Option<EventClosure>,
 }
 
 impl EventListener {
@@ -26,7 +28,7 @@ impl EventListener {
             target
                 .add_event_listener_with_callback(
                     event.as_str(),
-                    &(&*closure).as_ref().unchecked_ref(),
+                    (*closure).as_ref().unchecked_ref(),
                 )
                 .unwrap();
         }
@@ -49,7 +51,7 @@ impl EventListener {
 
     /// Returns the event being listened.
     pub fn event(&self) -> &str {
-        &self.event.as_str()
+        self.event.as_str()
     }
 
     /// Returns the event target.
@@ -69,7 +71,7 @@ impl EventListener {
             let closure = closure.clone();
 
             element
-                .remove_event_listener_with_callback(event, &(&*closure).as_ref().unchecked_ref())
+                .remove_event_listener_with_callback(event, (*closure).as_ref().unchecked_ref())
                 .unwrap();
         }
     }
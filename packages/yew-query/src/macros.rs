@@ -0,0 +1,95 @@
+/// Declares a query once and generates a module with its typed key and hook.
+///
+/// The module contains:
+/// - `key(params) -> TypedQueryKey<T>`: the key of the query for the given parameters.
+/// - `use_query(params) -> UseQueryHandle<T>`: a hook fetching the query.
+///
+/// The parameters are cloned for each fetch so they must be `Clone + 'static`,
+/// the `fetch` expression must be a future resolving to `Result<T, E>`.
+/// The items of the parent module are in scope of the generated module.
+///
+/// # Example
+/// ```
+/// use instant::Duration;
+/// use yew::prelude::*;
+/// use yew_query::define_query;
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// pub struct Todo {
+///     id: u64,
+///     title: String,
+/// }
+///
+/// async fn fetch_todo(id: u64) -> Result<Todo, std::convert::Infallible> {
+///     Ok(Todo { id, title: String::from("Write docs") })
+/// }
+///
+/// define_query! {
+///     /// The todo with the given id.
+///     pub todo(id: u64) -> Todo {
+///         key: ("todos", id),
+///         fetch: fetch_todo(id),
+///         options: |options| options.cache_time(Duration::from_secs(60)),
+///     }
+/// }
+///
+/// #[function_component]
+/// fn TodoTitle() -> Html {
+///     let todo = todo::use_query(1);
+///
+///     match todo.data() {
+///         Some(todo) => html! { <h1>{&todo.title}</h1> },
+///         None => html! { "Loading..." },
+///     }
+/// }
+///
+/// fn main() {
+///     assert_eq!(&**todo::key(1).key(), "todos/00000000000000000001");
+/// }
+/// ```
+#[macro_export]
+macro_rules! define_query {
+    (
+        $(#[$meta:meta])*
+        $vis:vis $name:ident($($param:ident: $ty:ty),* $(,)?) -> $value:ty {
+            key: $key:expr,
+            fetch: $fetch:expr
+            $(, options: |$options:ident| $with_options:expr)?
+            $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            /// Returns the key of the query for the given parameters.
+            pub fn key($($param: $ty),*) -> $crate::TypedQueryKey<$value> {
+                $crate::TypedQueryKey::new($key)
+            }
+
+            /// Fetches the query for the given parameters.
+            // The types of the parameters can't be parsed by `#[hook]`,
+            // so the hook is returned like the functions generated by the attribute
+            pub fn use_query(
+                $($param: $ty),*
+            ) -> impl ::yew::functional::Hook<Output = $crate::UseQueryHandle<$value>> {
+                let fetch = {
+                    $(let $param = ::std::clone::Clone::clone(&$param);)*
+                    move || {
+                        $(let $param = ::std::clone::Clone::clone(&$param);)*
+                        async move { $fetch.await }
+                    }
+                };
+
+                let options = $crate::UseQueryOptions::typed(key($($param),*), fetch);
+                $(let options = {
+                    let $options = options;
+                    $with_options
+                };)?
+
+                $crate::use_query_with_options(options)
+            }
+        }
+    };
+}
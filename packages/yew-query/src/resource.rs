@@ -0,0 +1,389 @@
+use crate::{
+    http::{delete, json_fetcher, send_json, HttpError},
+    use_query_client, use_typed_query, UseQueryClientHandle, UseQueryHandle,
+};
+use futures::Future;
+use gloo_net::http::Method;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fmt::{Debug, Display},
+    marker::PhantomData,
+    rc::Rc,
+};
+use yew::{hook, platform::spawn_local, use_state, Callback, UseStateHandle};
+use yew_query_core::{Error, QueryFilter, TypedQueryKey};
+
+/// A REST resource with the list of items at `url` and each item at `url/{id}`.
+///
+/// The list is cached with the url as key and each item with its url,
+/// the mutations refetch the list and update the cached items.
+pub struct Resource<T, Id = u64> {
+    url: Rc<str>,
+    _marker: PhantomData<fn() -> (T, Id)>,
+}
+
+impl<T, Id> Resource<T, Id>
+where
+    T: 'static,
+    Id: Display,
+{
+    /// Constructs a `Resource` with the given base url.
+    pub fn new(url: impl Into<String>) -> Self {
+        let url: String = url.into();
+        Resource {
+            url: Rc::from(url.trim_end_matches('/')),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the url of the list of items.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the url of the item with the given id.
+    pub fn detail_url(&self, id: &Id) -> String {
+        format!("{}/{id}", self.url)
+    }
+
+    /// Returns the key of the list of items.
+    pub fn list_key(&self) -> TypedQueryKey<Vec<T>> {
+        TypedQueryKey::new(self.url())
+    }
+
+    /// Returns the key of the item with the given id.
+    pub fn detail_key(&self, id: &Id) -> TypedQueryKey<T> {
+        TypedQueryKey::new(self.detail_url(id))
+    }
+
+    // Refetches the list of items in the background.
+    fn refetch_list(&self, client: &UseQueryClientHandle) {
+        client.refetch(QueryFilter::from(self.list_key().query_key()));
+    }
+}
+
+impl<T, Id> Clone for Resource<T, Id> {
+    fn clone(&self) -> Self {
+        Resource {
+            url: self.url.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, Id> Debug for Resource<T, Id> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Resource").field("url", &self.url).finish()
+    }
+}
+
+enum MutationState<O> {
+    Idle,
+    Loading,
+    Success(Rc<O>),
+    Failed(Error),
+}
+
+/// Handle returned by the mutation hooks of a `Resource`.
+pub struct UseResourceMutationHandle<I, O> {
+    state: UseStateHandle<MutationState<O>>,
+    mutate: Callback<I>,
+}
+
+impl<I, O> UseResourceMutationHandle<I, O> {
+    /// Sends the request with the given input.
+    pub fn mutate(&self, input: I) {
+        self.mutate.emit(input);
+    }
+
+    /// Returns a callback that sends the request with the given input.
+    pub fn callback(&self) -> Callback<I> {
+        self.mutate.clone()
+    }
+
+    /// Returns `true` if the request is in progress.
+    pub fn is_loading(&self) -> bool {
+        matches!(*self.state, MutationState::Loading)
+    }
+
+    /// Returns `true` if the last request succeeded.
+    pub fn is_success(&self) -> bool {
+        matches!(*self.state, MutationState::Success(_))
+    }
+
+    /// Returns the response of the last request, if succeeded.
+    pub fn data(&self) -> Option<Rc<O>> {
+        match &*self.state {
+            MutationState::Success(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the error of the last request, if failed.
+    pub fn error(&self) -> Option<Error> {
+        match &*self.state {
+            MutationState::Failed(err) => Some(err.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl<I, O> Clone for UseResourceMutationHandle<I, O> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            mutate: self.mutate.clone(),
+        }
+    }
+}
+
+impl<I, O> Debug for UseResourceMutationHandle<I, O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match &*self.state {
+            MutationState::Idle => "Idle",
+            MutationState::Loading => "Loading",
+            MutationState::Success(_) => "Success",
+            MutationState::Failed(_) => "Failed",
+        };
+
+        f.debug_struct("UseResourceMutationHandle")
+            .field("state", &state)
+            .finish()
+    }
+}
+
+#[hook]
+fn use_resource_mutation<I, O, F, Fut>(mutate: F) -> UseResourceMutationHandle<I, O>
+where
+    I: 'static,
+    O: 'static,
+    F: Fn(UseQueryClientHandle, I) -> Fut + 'static,
+    Fut: Future<Output = Result<O, HttpError>> + 'static,
+{
+    let client = use_query_client().expect("expected QueryClient");
+    let state = use_state(|| MutationState::Idle);
+
+    let mutate = {
+        let state = state.clone();
+        Callback::from(move |input: I| {
+            let state = state.clone();
+            let fut = mutate(client.clone(), input);
+            state.set(MutationState::Loading);
+
+            spawn_local(async move {
+                match fut.await {
+                    Ok(value) => state.set(MutationState::Success(Rc::new(value))),
+                    Err(err) => state.set(MutationState::Failed(Error::new(err))),
+                }
+            });
+        })
+    };
+
+    UseResourceMutationHandle { state, mutate }
+}
+
+/// Fetches the list of items of the resource.
+#[hook]
+pub fn use_resource_list<T, Id>(resource: Resource<T, Id>) -> UseQueryHandle<Vec<T>>
+where
+    T: DeserializeOwned + 'static,
+    Id: Display,
+{
+    use_typed_query(resource.list_key(), json_fetcher(resource.url()))
+}
+
+/// Fetches the item of the resource with the given id.
+#[hook]
+pub fn use_resource_detail<T, Id>(resource: Resource<T, Id>, id: Id) -> UseQueryHandle<T>
+where
+    T: DeserializeOwned + 'static,
+    Id: Display,
+{
+    use_typed_query(
+        resource.detail_key(&id),
+        json_fetcher(resource.detail_url(&id)),
+    )
+}
+
+/// Creates items with a `POST` request to the url of the resource,
+/// the list is refetched after each creation.
+#[hook]
+pub fn use_resource_create<T, Id, N>(resource: Resource<T, Id>) -> UseResourceMutationHandle<N, T>
+where
+    T: DeserializeOwned + 'static,
+    Id: Display + 'static,
+    N: Serialize + 'static,
+{
+    use_resource_mutation(move |client, item: N| {
+        let resource = resource.clone();
+        async move {
+            let created = send_json(Method::POST, resource.url(), &item).await?;
+            resource.refetch_list(&client);
+            Ok(created)
+        }
+    })
+}
+
+/// Updates items with a `PUT` request to the url of the item,
+/// the response replaces the cached item and the list is refetched.
+#[hook]
+pub fn use_resource_update<T, Id>(
+    resource: Resource<T, Id>,
+) -> UseResourceMutationHandle<(Id, T), T>
+where
+    T: Serialize + DeserializeOwned + Clone + 'static,
+    Id: Display + 'static,
+{
+    use_resource_mutation(move |client, (id, item): (Id, T)| {
+        let resource = resource.clone();
+        async move {
+            let updated: T = send_json(Method::PUT, &resource.detail_url(&id), &item).await?;
+
+            // The item is only updated if is in the cache
+            let mut query_client = client.client().clone();
+            let key = resource.detail_key(&id).query_key();
+            query_client.set_query_data(key, updated.clone()).ok();
+
+            resource.refetch_list(&client);
+            Ok(updated)
+        }
+    })
+}
+
+/// Deletes items with a `DELETE` request to the url of the item,
+/// the item is removed from the cache and the list is refetched.
+#[hook]
+pub fn use_resource_delete<T, Id>(resource: Resource<T, Id>) -> UseResourceMutationHandle<Id, ()>
+where
+    T: 'static,
+    Id: Display + 'static,
+{
+    use_resource_mutation(move |client, id: Id| {
+        let resource = resource.clone();
+        async move {
+            delete(&resource.detail_url(&id)).await?;
+
+            let mut query_client = client.client().clone();
+            query_client.remove_query_data(&resource.detail_key(&id).query_key());
+
+            resource.refetch_list(&client);
+            Ok(())
+        }
+    })
+}
+
+/// Declares a REST resource and generates a module with its CRUD hooks.
+///
+/// The module contains:
+/// - `resource() -> Resource<T, Id>`: the resource, with its urls and keys.
+/// - `use_list()`: fetches the list of items at the url.
+/// - `use_detail(id)`: fetches the item at `url/{id}`.
+/// - `use_create()`: creates items with a `POST` to the url, taking the `create` type.
+/// - `use_update()`: updates items with a `PUT` to the url of the item, taking `(id, item)`.
+/// - `use_delete()`: deletes items with a `DELETE` to the url of the item, taking the id.
+///
+/// The mutations refetch the list and update the cached items.
+/// The items of the parent module are in scope of the generated module.
+///
+/// # Example
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use yew::prelude::*;
+/// use yew_query::resource;
+///
+/// #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// pub struct Todo {
+///     id: u64,
+///     title: String,
+/// }
+///
+/// #[derive(Serialize)]
+/// pub struct NewTodo {
+///     title: String,
+/// }
+///
+/// resource! {
+///     /// The todos of the user.
+///     pub todos("/api/todos") -> Todo {
+///         id: u64,
+///         create: NewTodo,
+///     }
+/// }
+///
+/// #[function_component]
+/// fn TodoList() -> Html {
+///     let list = todos::use_list();
+///     let delete = todos::use_delete();
+///
+///     let items = list.data().cloned().unwrap_or_default();
+///     html! {
+///         <ul>
+///         {for items.into_iter().map(|todo| {
+///             let onclick = delete.callback().reform(move |_| todo.id);
+///             html! { <li {onclick}>{todo.title}</li> }
+///         })}
+///         </ul>
+///     }
+/// }
+///
+/// fn main() {
+///     assert_eq!(todos::resource().detail_url(&1), "/api/todos/1");
+/// }
+/// ```
+#[macro_export]
+macro_rules! resource {
+    (
+        $(#[$meta:meta])*
+        $vis:vis $name:ident($url:expr) -> $item:ty {
+            id: $id:ty,
+            create: $create:ty
+            $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            /// Returns the resource.
+            pub fn resource() -> $crate::resource::Resource<$item, $id> {
+                $crate::resource::Resource::new($url)
+            }
+
+            /// Fetches the list of items.
+            pub fn use_list(
+            ) -> impl ::yew::functional::Hook<Output = $crate::UseQueryHandle<::std::vec::Vec<$item>>>
+            {
+                $crate::resource::use_resource_list(resource())
+            }
+
+            /// Fetches the item with the given id.
+            pub fn use_detail(
+                id: $id,
+            ) -> impl ::yew::functional::Hook<Output = $crate::UseQueryHandle<$item>> {
+                $crate::resource::use_resource_detail(resource(), id)
+            }
+
+            /// Creates items, the list is refetched after each creation.
+            pub fn use_create() -> impl ::yew::functional::Hook<
+                Output = $crate::resource::UseResourceMutationHandle<$create, $item>,
+            > {
+                $crate::resource::use_resource_create(resource())
+            }
+
+            /// Updates items, the response replaces the cached item and the list is refetched.
+            pub fn use_update() -> impl ::yew::functional::Hook<
+                Output = $crate::resource::UseResourceMutationHandle<($id, $item), $item>,
+            > {
+                $crate::resource::use_resource_update(resource())
+            }
+
+            /// Deletes items, the item is removed from the cache and the list is refetched.
+            pub fn use_delete() -> impl ::yew::functional::Hook<
+                Output = $crate::resource::UseResourceMutationHandle<$id, ()>,
+            > {
+                $crate::resource::use_resource_delete(resource())
+            }
+        }
+    };
+}
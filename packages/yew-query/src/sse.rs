@@ -0,0 +1,194 @@
+use crate::{use_query_with_options, StreamFuture, UseQueryHandle, UseQueryOptions};
+use futures::{Stream, StreamExt};
+use gloo_net::eventsource::futures::{EventSource, EventSourceSubscription};
+use std::{fmt::Display, rc::Rc};
+use yew::hook;
+use yew_query_core::{Error, Key};
+
+/// An error that occurs in a query backed by an `EventSource`.
+#[derive(Debug)]
+pub enum EventSourceQueryError {
+    /// The `EventSource` could not be opened.
+    Open(gloo_net::Error),
+
+    /// The connection of the `EventSource` failed.
+    Connection,
+
+    /// The event had no text data.
+    InvalidData,
+}
+
+impl Display for EventSourceQueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventSourceQueryError::Open(err) => write!(f, "failed to open event source: {err}"),
+            EventSourceQueryError::Connection => write!(f, "event source connection failed"),
+            EventSourceQueryError::InvalidData => write!(f, "event data is not text"),
+        }
+    }
+}
+
+impl std::error::Error for EventSourceQueryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EventSourceQueryError::Open(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl<T> UseQueryOptions<StreamFuture<T>, T, Error>
+where
+    T: Clone + 'static,
+{
+    /// Constructs a new `UseQueryOptions` that opens an `EventSource` and parses
+    /// the events of the given type into the value of the query.
+    ///
+    /// When the connection fails the query fails, and is reopened using the retry of the query.
+    pub fn event_source<K, P, E>(key: K, url: &str, event_type: &str, parse: P) -> Self
+    where
+        K: Into<Key>,
+        P: Fn(String) -> Result<T, E> + 'static,
+        E: Into<Error> + 'static,
+    {
+        let url = url.to_owned();
+        let event_type = event_type.to_owned();
+        let parse = Rc::new(parse);
+
+        Self::from_stream(key, move || {
+            let parse = parse.clone();
+            subscribe(&url, &event_type).map(move |event| {
+                let data = event?;
+                parse(data).map_err(Into::into)
+            })
+        })
+    }
+}
+
+/// This hook opens an `EventSource` and updates the query with each `message` event parsed into `T`.
+#[hook]
+pub fn use_event_source_query<K, T, P, E>(key: K, url: &str, parse: P) -> UseQueryHandle<T>
+where
+    K: Into<Key>,
+    T: Clone + 'static,
+    P: Fn(String) -> Result<T, E> + 'static,
+    E: Into<Error> + 'static,
+{
+    use_query_with_options(UseQueryOptions::event_source(key, url, "message", parse))
+}
+
+// Returns a stream of the data of the events, the stream ends after the first error
+fn subscribe(url: &str, event_type: &str) -> impl Stream<Item = Result<String, Error>> {
+    let opened = EventSource::new(url).and_then(|mut source| {
+        let subscription = source.subscribe(event_type.to_owned())?;
+        Ok((source, subscription))
+    });
+
+    let events = match opened {
+        Ok((source, subscription)) => events(source, subscription).boxed_local(),
+        Err(err) => {
+            let err = Error::new(EventSourceQueryError::Open(gloo_net::Error::JsError(err)));
+            futures::stream::once(async { Err(err) }).boxed_local()
+        }
+    };
+
+    until_error(events)
+}
+
+// Returns a stream of the data of the events, the source is closed when the stream is dropped
+fn events(
+    source: EventSource,
+    subscription: EventSourceSubscription,
+) -> impl Stream<Item = Result<String, Error>> {
+    futures::stream::unfold(
+        (source, subscription),
+        |(source, mut subscription)| async move {
+            let item = match subscription.next().await {
+                Some(Ok((_, event))) => event
+                    .data()
+                    .as_string()
+                    .ok_or_else(|| Error::new(EventSourceQueryError::InvalidData)),
+                _ => Err(Error::new(EventSourceQueryError::Connection)),
+            };
+
+            Some((item, (source, subscription)))
+        },
+    )
+}
+
+// Returns a stream that ends after the first error of the given stream
+fn until_error<S, T>(stream: S) -> impl Stream<Item = Result<T, Error>>
+where
+    S: Stream<Item = Result<T, Error>> + Unpin,
+{
+    futures::stream::unfold(Some(stream), |stream| async move {
+        let mut stream = stream?;
+
+        // We drop the stream after an error to close the connection
+        match stream.next().await? {
+            Ok(x) => Some((Ok(x), Some(stream))),
+            Err(err) => Some((Err(err), None)),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{until_error, EventSourceQueryError};
+    use futures::{executor::block_on, stream, StreamExt};
+    use std::{cell::Cell, error::Error as _, rc::Rc};
+    use yew_query_core::Error;
+
+    // Sets the flag when dropped.
+    struct DropGuard(Rc<Cell<bool>>);
+
+    impl Drop for DropGuard {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    #[test]
+    fn until_error_test() {
+        let dropped = Rc::new(Cell::new(false));
+        let guard = DropGuard(dropped.clone());
+        let events = stream::iter(vec![
+            Ok(1),
+            Ok(2),
+            Err(Error::new(EventSourceQueryError::Connection)),
+            Ok(3),
+        ])
+        .map(move |x| {
+            let _ = &guard;
+            x
+        });
+
+        let mut stream = Box::pin(until_error(events));
+        assert_eq!(block_on(stream.next()).unwrap().unwrap(), 1);
+        assert_eq!(block_on(stream.next()).unwrap().unwrap(), 2);
+        assert!(!dropped.get());
+
+        let err = block_on(stream.next()).unwrap().unwrap_err();
+        assert!(err.is::<EventSourceQueryError>());
+        assert!(dropped.get());
+        assert!(block_on(stream.next()).is_none());
+    }
+
+    #[test]
+    fn until_error_end_test() {
+        let stream = until_error(stream::iter(vec![Ok::<_, Error>("a"), Ok("b")]));
+        let items = block_on(stream.map(Result::unwrap).collect::<Vec<_>>());
+        assert_eq!(items, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn event_source_error_test() {
+        let err = EventSourceQueryError::Connection;
+        assert_eq!(err.to_string(), "event source connection failed");
+        assert!(err.source().is_none());
+
+        let err = EventSourceQueryError::Open(gloo_net::Error::GlooError("closed".to_owned()));
+        assert_eq!(err.to_string(), "failed to open event source: closed");
+        assert!(err.source().is_some());
+    }
+}
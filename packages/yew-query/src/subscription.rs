@@ -0,0 +1,220 @@
+use crate::{use_query_client, use_stream_query, UseQueryHandle};
+use futures::{Stream, StreamExt};
+use gloo_net::websocket::{futures::WebSocket, Message};
+use instant::Duration;
+use std::{cell::RefCell, rc::Rc};
+use yew::{hook, use_mut_ref, use_state_eq, UseStateHandle};
+use yew_query_core::{Error, Key, QueryClient, QueryKey, QueryState};
+
+/// The max time to wait before trying to reconnect.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// The status of the connection of a subscription.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// The connection is being established.
+    Connecting,
+
+    /// The connection is open and receiving messages.
+    Open,
+
+    /// The connection was closed and will try to reconnect.
+    Reconnecting {
+        /// The number of the reconnection attempt, starting from `1`.
+        attempt: u32,
+    },
+}
+
+/// Handle returned by `use_subscription`.
+pub struct UseSubscriptionHandle<T> {
+    query: UseQueryHandle<T>,
+    status: UseStateHandle<ConnectionStatus>,
+}
+
+impl<T> UseSubscriptionHandle<T> {
+    /// Returns the last value received for the query.
+    pub fn data(&self) -> Option<&T> {
+        self.query.data()
+    }
+
+    /// Returns the error of the query, if any.
+    pub fn error(&self) -> Option<&Error> {
+        self.query.error()
+    }
+
+    /// Returns the current state of the query.
+    pub fn state(&self) -> &QueryState {
+        self.query.state()
+    }
+
+    /// Returns the status of the connection.
+    pub fn status(&self) -> ConnectionStatus {
+        *self.status
+    }
+
+    /// Returns `true` if the connection is open.
+    pub fn is_open(&self) -> bool {
+        matches!(*self.status, ConnectionStatus::Open)
+    }
+
+    /// Returns the handle of the query that receives the messages.
+    pub fn query(&self) -> &UseQueryHandle<T> {
+        &self.query
+    }
+}
+
+impl<T> Clone for UseSubscriptionHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            query: self.query.clone(),
+            status: self.status.clone(),
+        }
+    }
+}
+
+/// This hook opens a WebSocket connection and feeds the messages into the queries of the cache.
+///
+/// The `router` converts each message into the values of the queries with the given keys,
+/// the value for `key` updates the returned query and the others update the queries in the cache
+/// with the same type, the keys without a query are skipped. The connection is reopened when closed.
+#[hook]
+pub fn use_subscription<K, T, R>(key: K, url: &str, router: R) -> UseSubscriptionHandle<T>
+where
+    K: Into<Key>,
+    T: Clone + 'static,
+    R: Fn(Message) -> Vec<(Key, T)> + 'static,
+{
+    let client = use_query_client()
+        .expect("expected QueryClient")
+        .client()
+        .clone();
+
+    // The connection outlives the render, so the status is set through the latest handle
+    let status = use_state_eq(|| ConnectionStatus::Connecting);
+    let latest_status = use_mut_ref(|| status.clone());
+    *latest_status.borrow_mut() = status.clone();
+
+    let key = key.into();
+    let router = Rc::new(router);
+    let url = url.to_owned();
+
+    let query = {
+        let key = key.clone();
+        use_stream_query(key.clone(), move || {
+            let route = Route {
+                key: key.clone(),
+                client: client.clone(),
+                router: router.clone(),
+            };
+
+            subscribe(url.clone(), route, latest_status.clone()).map(Ok::<_, Error>)
+        })
+    };
+
+    UseSubscriptionHandle { query, status }
+}
+
+// Routes the messages to the query of the subscription and the other queries of the cache.
+struct Route<R> {
+    key: Key,
+    client: QueryClient,
+    router: Rc<R>,
+}
+
+impl<R> Route<R> {
+    // Updates the other queries and returns the value of the query of the subscription, if any.
+    fn route<T>(&self, message: Message) -> Option<T>
+    where
+        T: 'static,
+        R: Fn(Message) -> Vec<(Key, T)>,
+    {
+        let mut value = None;
+        let mut routed = Vec::new();
+
+        for (key, x) in (self.router)(message) {
+            if key == self.key {
+                value = Some(x);
+            } else {
+                routed.push((QueryKey::of::<T>(key), x));
+            }
+        }
+
+        self.client.clone().route_query_data(routed);
+        value
+    }
+}
+
+struct Connection {
+    socket: Option<WebSocket>,
+    attempt: u32,
+}
+
+// Returns a stream of the routed messages of a WebSocket which reconnects when closed
+fn subscribe<T, R>(
+    url: String,
+    route: Route<R>,
+    status: Rc<RefCell<UseStateHandle<ConnectionStatus>>>,
+) -> impl Stream<Item = T>
+where
+    T: 'static,
+    R: Fn(Message) -> Vec<(Key, T)> + 'static,
+{
+    let conn = Connection {
+        socket: None,
+        attempt: 0,
+    };
+
+    let route = Rc::new(route);
+    futures::stream::unfold(conn, move |mut conn| {
+        let url = url.clone();
+        let route = route.clone();
+        let status = status.clone();
+        let set_status = move |x| status.borrow().set(x);
+
+        async move {
+            loop {
+                let socket = match conn.socket.as_mut() {
+                    Some(socket) => socket,
+                    None => {
+                        if conn.attempt > 0 {
+                            set_status(ConnectionStatus::Reconnecting {
+                                attempt: conn.attempt,
+                            });
+                            yew::platform::time::sleep(reconnect_delay(conn.attempt)).await;
+                        }
+
+                        match WebSocket::open(&url) {
+                            Ok(socket) => {
+                                set_status(ConnectionStatus::Open);
+                                conn.socket.insert(socket)
+                            }
+                            Err(err) => {
+                                log::warn!("failed to open websocket `{url}`: {err}");
+                                conn.attempt += 1;
+                                continue;
+                            }
+                        }
+                    }
+                };
+
+                match socket.next().await {
+                    Some(Ok(message)) => {
+                        conn.attempt = 0;
+                        if let Some(value) = route.route(message) {
+                            return Some((value, conn));
+                        }
+                    }
+                    _ => {
+                        conn.socket = None;
+                        conn.attempt += 1;
+                    }
+                }
+            }
+        }
+    })
+}
+
+fn reconnect_delay(attempt: u32) -> Duration {
+    let secs = 2_u64.saturating_pow(attempt.saturating_sub(1));
+    Duration::from_secs(secs).min(MAX_RECONNECT_DELAY)
+}
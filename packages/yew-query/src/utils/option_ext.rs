@@ -10,12 +10,9 @@ impl<T> OptionExt<T> for Option<T> {
     where
         F: FnOnce(T) -> T,
     {
-        match self.take() {
-            Some(x) => {
-                let new_value = f(x);
-                self.replace(new_value);
-            }
-            None => {}
+        if let Some(x) = self.take() {
+            let new_value = f(x);
+            self.replace(new_value);
         }
     }
 }